@@ -0,0 +1,342 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Bit reader, keeping checksums of the bytes read, and bit writer.
+
+use std::io::{ErrorKind, Read, Result, Seek, SeekFrom};
+
+use crate::crc::{crc16, crc8};
+
+/// Number of bytes read from the reader at once.
+const BUFFER: usize = 4096;
+
+/// Maximum length of a frame header in bytes.
+pub(crate) const MAX_HEADER: usize = 16;
+
+/// Reads big endian bit fields.  Bytes are only taken from the reader as
+/// needed, so after [`align()`](Bits::align) no bits are held back.
+///
+/// Bytes from the last [`mark()`](Bits::mark) are kept, so that
+/// [`resync()`](Bits::resync) can search them again for a frame header.
+pub(crate) struct Bits<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    // Index of the marked byte in `buffer`.
+    mark: Option<usize>,
+    // Index of the next byte in `buffer`.
+    pos: usize,
+    // Number of bytes in `buffer`.
+    len: usize,
+    // Number of bytes taken from the reader.
+    taken: u64,
+    // Bits not yet read are the low `count` bits.
+    acc: u64,
+    count: u32,
+    // Checksums of bytes since the last reset.
+    crc8: u8,
+    crc16: u16,
+}
+
+impl<R: Read> Bits<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self::after(reader, 0)
+    }
+
+    /// Create a bit reader for what's left of a reader, after `taken` bytes
+    /// were already read from it.
+    pub(crate) fn after(reader: R, taken: u64) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; BUFFER],
+            mark: None,
+            pos: 0,
+            len: 0,
+            taken,
+            acc: 0,
+            count: 0,
+            crc8: 0,
+            crc16: 0,
+        }
+    }
+
+    /// Create a bit reader for the rest of a reader, after the `prefix`
+    /// bytes were read from it (and are to be read again).
+    pub(crate) fn with_prefix(reader: R, prefix: &[u8]) -> Self {
+        let mut bits = Self::after(reader, prefix.len() as u64);
+        bits.buffer[..prefix.len()].copy_from_slice(prefix);
+        bits.len = prefix.len();
+        bits
+    }
+
+    // Read more into the buffer, returning false at the end of the reader.
+    fn refill(&mut self) -> Result<bool> {
+        // Drop bytes before the mark (or all bytes read, if not marked).
+        let keep = self.mark.unwrap_or(self.pos);
+        self.buffer.copy_within(keep..self.len, 0);
+        self.mark = self.mark.map(|_| 0);
+        self.pos -= keep;
+        self.len -= keep;
+        if self.len == self.buffer.len() {
+            self.buffer.resize(self.len * 2, 0);
+        }
+        loop {
+            match self.reader.read(&mut self.buffer[self.len..]) {
+                Ok(len) => {
+                    self.len += len;
+                    self.taken += len as u64;
+                    return Ok(len != 0);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Load the next byte into the low bits.
+    fn load(&mut self) -> Result<()> {
+        if self.pos == self.len && !self.refill()? {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let byte = self.buffer[self.pos];
+        self.pos += 1;
+        self.crc8 = crc8(self.crc8, byte);
+        self.crc16 = crc16(self.crc16, byte);
+        self.acc = self.acc << 8 | u64::from(byte);
+        self.count += 8;
+        Ok(())
+    }
+
+    /// Get the offset of the next whole byte from where the reader started.
+    pub(crate) fn position(&self) -> u64 {
+        self.taken - (self.len - self.pos) as u64 - u64::from(self.count / 8)
+    }
+
+    /// Keep the bytes from the next whole byte on, for
+    /// [`resync()`](Bits::resync).
+    pub(crate) fn mark(&mut self) {
+        debug_assert_eq!(self.count % 8, 0);
+        self.mark = Some(self.pos - self.count as usize / 8);
+    }
+
+    /// Go back to the byte after the mark (or on to the next whole byte, if
+    /// not marked), then skip to the next byte that starts a frame header,
+    /// as decided by `check` given the bytes from there.  Returns false if
+    /// the end of the reader is reached first.
+    pub(crate) fn resync<C>(&mut self, mut check: C) -> Result<bool>
+    where
+        C: FnMut(&[u8]) -> bool,
+    {
+        if let Some(mark) = self.mark.take() {
+            self.pos = mark + 1;
+        }
+        self.count = 0;
+        loop {
+            // Look ahead far enough for a whole header.
+            let bytes = self.peek(MAX_HEADER)?;
+            if bytes.is_empty() {
+                return Ok(false);
+            }
+            if bytes[0] == 0xFF && check(bytes) {
+                return Ok(true);
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Get at least `len` of the next bytes without reading them (fewer only
+    /// at the end of the reader).  No bits may be held back.
+    pub(crate) fn peek(&mut self, len: usize) -> Result<&[u8]> {
+        debug_assert_eq!(self.count, 0);
+        while self.len - self.pos < len && self.refill()? {}
+        Ok(&self.buffer[self.pos..self.len])
+    }
+
+    /// Check if the end of the reader is reached at a byte boundary.
+    pub(crate) fn at_end(&mut self) -> Result<bool> {
+        Ok(self.count == 0 && self.pos == self.len && !self.refill()?)
+    }
+
+    /// Read an unsigned integer of up to 32 bits.
+    pub(crate) fn read(&mut self, bits: u32) -> Result<u32> {
+        debug_assert!(bits <= 32);
+        while self.count < bits {
+            self.load()?;
+        }
+        self.count -= bits;
+        let mask = (1u64 << bits) - 1;
+        Ok((self.acc >> self.count & mask) as u32)
+    }
+
+    /// Read an unsigned integer of up to 64 bits.
+    pub(crate) fn read_u64(&mut self, bits: u32) -> Result<u64> {
+        if bits <= 32 {
+            return Ok(self.read(bits)?.into());
+        }
+        let high = u64::from(self.read(bits - 32)?);
+        Ok(high << 32 | u64::from(self.read(32)?))
+    }
+
+    /// Read a two's complement integer of up to 33 bits.
+    pub(crate) fn signed(&mut self, bits: u32) -> Result<i64> {
+        if bits == 0 {
+            return Ok(0);
+        }
+        let value = self.read_u64(bits)?;
+        Ok((value << (64 - bits)) as i64 >> (64 - bits))
+    }
+
+    /// Read a unary number: the count of 0 bits before a 1 bit.
+    pub(crate) fn unary(&mut self) -> Result<u32> {
+        let mut zeros = 0;
+        loop {
+            if self.count == 0 {
+                self.load()?;
+            }
+            let bits = self.acc & ((1u64 << self.count) - 1);
+            if bits == 0 {
+                zeros += self.count;
+                self.count = 0;
+                continue;
+            }
+            // Position of the highest 1 bit.
+            let high = 63 - bits.leading_zeros();
+            zeros += self.count - 1 - high;
+            self.count = high;
+            return Ok(zeros);
+        }
+    }
+
+    /// Read a Rice coded signed integer with parameter `k`.
+    pub(crate) fn rice(&mut self, k: u32) -> Result<i64> {
+        let high = u64::from(self.unary()?);
+        let value = high.wrapping_shl(k) | u64::from(self.read(k)?);
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Skip to the next byte boundary.
+    pub(crate) fn align(&mut self) {
+        self.count -= self.count % 8;
+    }
+
+    /// Restart the checksums from the next byte.
+    pub(crate) fn reset_crc(&mut self) {
+        debug_assert_eq!(self.count, 0);
+        self.crc8 = 0;
+        self.crc16 = 0;
+    }
+
+    /// Get the CRC-8 of bytes since the last reset.
+    pub(crate) fn crc8(&self) -> u8 {
+        self.crc8
+    }
+
+    /// Get the CRC-16 of bytes since the last reset.
+    pub(crate) fn crc16(&self) -> u16 {
+        self.crc16
+    }
+}
+
+impl<R: Read + Seek> Bits<R> {
+    /// Move to an offset from where the reader started.
+    pub(crate) fn seek(&mut self, offset: u64) -> Result<()> {
+        let start = self.reader.stream_position()? - self.taken;
+        self.reader.seek(SeekFrom::Start(start + offset))?;
+        self.taken = offset;
+        self.mark = None;
+        self.pos = 0;
+        self.len = 0;
+        self.count = 0;
+        Ok(())
+    }
+
+    /// Get the length of the reader from where it started.
+    pub(crate) fn length(&mut self) -> Result<u64> {
+        let current = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(current))?;
+        Ok(end - (current - self.taken))
+    }
+}
+
+/// Writes big endian bit fields.
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    // Bits not yet written are the low `count` bits.
+    acc: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            acc: 0,
+            count: 0,
+        }
+    }
+
+    /// Write an unsigned integer of up to 32 bits.
+    pub(crate) fn write(&mut self, value: u32, bits: u32) {
+        debug_assert!(bits <= 32);
+        let mask = (1u64 << bits) - 1;
+        self.acc = self.acc << bits | u64::from(value) & mask;
+        self.count += bits;
+        while self.count >= 8 {
+            self.count -= 8;
+            self.bytes.push((self.acc >> self.count) as u8);
+        }
+    }
+
+    /// Write an unsigned integer of up to 64 bits.
+    pub(crate) fn write_u64(&mut self, value: u64, bits: u32) {
+        if bits > 32 {
+            self.write((value >> 32) as u32, bits - 32);
+            self.write(value as u32, 32);
+        } else {
+            self.write(value as u32, bits);
+        }
+    }
+
+    /// Write a two's complement integer of up to 33 bits.
+    pub(crate) fn signed(&mut self, value: i64, bits: u32) {
+        self.write_u64(value as u64, bits);
+    }
+
+    /// Write a unary number: `zeros` 0 bits followed by a 1 bit.
+    pub(crate) fn unary(&mut self, mut zeros: u32) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros + 1);
+    }
+
+    /// Write a Rice coded signed integer with parameter `k`.
+    pub(crate) fn rice(&mut self, value: i64, k: u32) {
+        let value = (value << 1 ^ value >> 63) as u64;
+        self.unary((value >> k) as u32);
+        self.write_u64(value, k);
+    }
+
+    /// Pad with 0 bits to the next byte boundary.
+    pub(crate) fn align(&mut self) {
+        if self.count != 0 {
+            self.write(0, 8 - self.count);
+        }
+    }
+
+    /// Get the bytes written so far, not including an incomplete byte.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Get the bytes written, after [`align()`](BitWriter::align).
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        debug_assert_eq!(self.count, 0);
+        self.bytes
+    }
+}
@@ -0,0 +1,58 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! CRC-8 (frame header) and CRC-16 (frame) checksums.
+
+const CRC8: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                crc << 1 ^ 0x07
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC16: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                crc << 1 ^ 0x8005
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Update a CRC-8 (polynomial 0x07) with a byte.
+pub(crate) fn crc8(crc: u8, byte: u8) -> u8 {
+    CRC8[usize::from(crc ^ byte)]
+}
+
+/// Update a CRC-16 (polynomial 0x8005) with a byte.
+pub(crate) fn crc16(crc: u16, byte: u8) -> u16 {
+    crc << 8 ^ CRC16[usize::from((crc >> 8) as u8 ^ byte)]
+}
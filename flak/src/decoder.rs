@@ -0,0 +1,449 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::io::{Cursor, Read, Seek};
+use std::marker::PhantomData;
+
+use fon::chan::{Ch64, Channel};
+use fon::Frame;
+use raw_audio::Mixer;
+
+use crate::bits::{Bits, MAX_HEADER};
+use crate::frame::{self, Header};
+use crate::md5::Md5;
+use crate::metadata::{self, Block, SeekPoint};
+use crate::ogg::{OggReader, Source};
+use crate::verify::{self, Corruption, Damage, Report};
+use crate::{Error, Result, StreamInfo};
+
+/// Bisection stops when the frame to seek to is within this many bytes, and
+/// the frames are decoded from there.
+const BISECT: u64 = 1 << 16;
+
+/// FLAC decoder, iterating over frames of audio at the stream's sample rate.
+pub struct Decoder<R: Read, F: Frame> {
+    bits: Bits<Source<R>>,
+    info: StreamInfo,
+    // Metadata blocks after STREAMINFO.
+    metadata: Vec<Block>,
+    // Offset of the first FLAC frame from where the reader started.
+    first_frame: u64,
+    // Sample number of the first sample of the first FLAC frame.
+    first_sample: u64,
+    // Sample number of the first sample of the current FLAC frame.
+    start: u64,
+    // Samples of the current FLAC frame, one block for each channel.
+    block: Vec<i64>,
+    // Number of samples in each block.
+    len: usize,
+    // Index of the next sample in each block.
+    pos: usize,
+    // Bits per sample of the current FLAC frame.
+    sample_bits: u32,
+    // Decoded samples of each channel.
+    decoded: Vec<F::Chan>,
+    // Mixer to the channels of `F`, if the stream's channels differ.
+    mixer: Option<Mixer>,
+    // Whether to resynchronize after corrupted frames.
+    resync: bool,
+    // Corrupted frames skipped so far.
+    corrupted: Vec<Corruption>,
+    // Number of samples of silence before the current FLAC frame, filling
+    // in for frames that were skipped.
+    gap: u64,
+    // Sample number the next FLAC frame should start at.
+    next: u64,
+    _phantom: PhantomData<F>,
+}
+
+impl<R: Read, F: Frame> Decoder<R, F> {
+    /// Create a decoder for a FLAC or Ogg FLAC stream, reading the metadata
+    /// blocks of the stream.
+    ///
+    /// Streams of bare FLAC frames, without the `fLaC` marker and metadata
+    /// (like a broadcast joined partway through), are decoded too, with
+    /// stream information made up from the first frame header.  Their total
+    /// number of samples is unknown (0), as it may be in any stream.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut marker = [0; 4];
+        reader.read_exact(&mut marker)?;
+        // Offsets count from the start of the FLAC stream.
+        let mut bits = match marker {
+            [0xFF, sync, ..] if sync & 0xFE == 0xF8 => {
+                Bits::with_prefix(Source::Flac(reader), &marker)
+            }
+            _ if &marker == b"fLaC" => {
+                Bits::after(Source::Flac(reader), marker.len() as u64)
+            }
+            _ if &marker == b"OggS" => {
+                let reader = Cursor::new(marker).chain(reader);
+                let source = Source::Ogg(OggReader::new(reader)?);
+                Bits::after(source, marker.len() as u64)
+            }
+            _ => return Err(Error::NotFlac),
+        };
+        let (info, metadata, first_sample) = if marker[0] == 0xFF {
+            let (header, info) = Header::first(bits.peek(MAX_HEADER)?)?;
+            let first_sample = header.first_sample(&info);
+            (info, Vec::new(), first_sample)
+        } else {
+            let (info, metadata) = metadata::read(&mut bits)?;
+            (info, metadata, 0)
+        };
+        Ok(Self {
+            first_frame: bits.position(),
+            first_sample,
+            bits,
+            metadata,
+            start: first_sample,
+            block: Vec::new(),
+            len: 0,
+            pos: 0,
+            sample_bits: info.bits_per_sample.into(),
+            decoded: vec![F::Chan::default(); info.channels.into()],
+            mixer: Mixer::new(info.channels.into(), F::CHAN_COUNT),
+            resync: false,
+            corrupted: Vec::new(),
+            gap: 0,
+            next: first_sample,
+            info,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Keep decoding after corrupted frames, rather than returning an error.
+    ///
+    /// Decoding resumes at the next frame header with a valid CRC-8, and the
+    /// samples of skipped frames are replaced with silence.  Skipped frames
+    /// are listed by [`corrupted()`](Decoder::corrupted).  I/O errors, other
+    /// than the stream ending early, are still returned.
+    pub fn with_resync(mut self) -> Self {
+        self.resync = true;
+        self
+    }
+
+    /// Get the corrupted frames skipped so far, when decoding
+    /// [`with_resync()`](Decoder::with_resync).
+    pub fn corrupted(&self) -> &[Corruption] {
+        &self.corrupted
+    }
+
+    /// Get the stream information.
+    pub fn stream_info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    /// Get the metadata blocks after the STREAMINFO block.
+    pub fn metadata(&self) -> &[Block] {
+        &self.metadata
+    }
+
+    /// Get the sample rate of the stream in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.info.sample_rate
+    }
+
+    /// Get the number of channels in the stream.
+    pub fn channels(&self) -> usize {
+        self.info.channels.into()
+    }
+
+    /// Get the index of the next frame to be decoded.
+    pub fn position(&self) -> u64 {
+        self.start + self.pos as u64 - self.gap
+    }
+
+    /// Decode the next frame without mixing, returning one channel for each
+    /// of the stream's [`channels()`](Decoder::channels).  Returns `None` at
+    /// the end of the stream.
+    pub fn next_channels(&mut self) -> Option<Result<&[F::Chan]>> {
+        if self.gap == 0 && self.pos == self.len {
+            match self.next_block().transpose() {
+                Some(Ok(())) => {}
+                Some(Err(e)) => return Some(Err(e)),
+                None => return None,
+            }
+        }
+        if self.gap != 0 {
+            self.gap -= 1;
+            for decoded in self.decoded.iter_mut() {
+                *decoded = F::Chan::from(Ch64::new(0.0));
+            }
+            return Some(Ok(&self.decoded));
+        }
+        let samples = self.block[self.pos..].iter().step_by(self.len);
+        for (decoded, sample) in self.decoded.iter_mut().zip(samples) {
+            *decoded = chan_from(*sample, self.sample_bits);
+        }
+        self.pos += 1;
+        Some(Ok(&self.decoded))
+    }
+
+    /// Decode the rest of the stream, checking every frame's header (CRC-8)
+    /// and frame (CRC-16) checksums, and the MD5 signature of the audio,
+    /// like `flac -t`.  Corrupted frames are skipped (searching for the next
+    /// frame header) and listed in the report, rather than stopping
+    /// decoding; only I/O errors are returned.
+    pub fn verify(mut self) -> Result<Report> {
+        let mut report = Report {
+            info: self.info.clone(),
+            frames: 0,
+            samples: 0,
+            md5: [0; 16],
+            corrupted: Vec::new(),
+        };
+        let mut md5 = Md5::new();
+        let bytes = usize::from(self.info.bits_per_sample).div_ceil(8);
+        self.resync = true;
+        while self.next_block()?.is_some() {
+            verify::hash(&mut md5, &self.block, self.len, bytes);
+            report.frames += 1;
+            report.samples += self.len as u64;
+        }
+        report.corrupted = self.corrupted;
+        report.md5 = md5.finish();
+        Ok(report)
+    }
+
+    // Decode the next FLAC frame, returning `None` at the end of the stream.
+    fn next_block(&mut self) -> Result<Option<()>> {
+        if !self.resync {
+            return match Header::read(&mut self.bits, &self.info)? {
+                Some(header) => self.read_block(&header).map(Some),
+                None => Ok(None),
+            };
+        }
+        loop {
+            let offset = self.bits.position();
+            self.bits.mark();
+            let (sample, damage) =
+                match Header::read(&mut self.bits, &self.info) {
+                    Ok(None) => return Ok(None),
+                    Ok(Some(header)) => match self.read_block(&header) {
+                        Ok(()) => break,
+                        Err(e) => (Some(self.start), Damage::new(e, false)?),
+                    },
+                    Err(e) => (None, Damage::new(e, true)?),
+                };
+            self.corrupted.push(Corruption {
+                offset,
+                sample,
+                damage,
+            });
+            let info = &self.info;
+            let found = damage != Damage::Truncated
+                && self
+                    .bits
+                    .resync(|bytes| Header::parse(bytes, info).is_some())?;
+            if !found {
+                return Ok(None);
+            }
+        }
+        // Fill in for skipped frames.
+        self.gap = self.start.saturating_sub(self.next);
+        self.next = self.start + self.len as u64;
+        Ok(Some(()))
+    }
+
+    // Decode the rest of a FLAC frame after its header.
+    fn read_block(&mut self, header: &Header) -> Result<()> {
+        self.start = header.first_sample(&self.info);
+        self.len = 0;
+        self.pos = 0;
+        if header.assignment.channels() != self.channels() {
+            return Err(Error::Frame);
+        }
+        frame::read(&mut self.bits, header, &mut self.block)?;
+        self.len = header.block_size;
+        self.sample_bits = header.bits;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek, F: Frame> Decoder<R, F> {
+    /// Move to a frame, so it's decoded next.  Frames are counted from the
+    /// start of the stream.  Seeking past the end is allowed; decoding then
+    /// ends right away.
+    ///
+    /// The search starts from the closest points of the SEEKTABLE block, if
+    /// there is one, then bisects the stream looking for frame headers (with
+    /// a valid CRC-8), and finally decodes and discards audio up to `frame`
+    /// from the FLAC frame containing it.  Ogg FLAC streams can't seek, and
+    /// return an I/O error.
+    pub fn seek(&mut self, frame: u64) -> Result<()> {
+        let mut low = self.first_frame;
+        // Sample number of the first sample of the FLAC frame at `low`.
+        let mut low_sample = self.first_sample;
+        let mut high = self.bits.length()?;
+        let points = self.metadata.iter().filter_map(|block| match block {
+            Block::SeekTable(points) => Some(points),
+            _ => None,
+        });
+        for point in points.flatten() {
+            let offset = self.first_frame + point.offset;
+            if point.sample == SeekPoint::PLACEHOLDER || offset >= high {
+                continue;
+            }
+            if point.sample > frame {
+                high = offset;
+            } else if offset >= low {
+                low = offset;
+                low_sample = point.sample;
+            }
+        }
+        // A FLAC frame that starts at or before `frame` is always at `low`.
+        while high - low > BISECT {
+            let middle = low + (high - low) / 2;
+            match self.find_frame(middle)? {
+                Some((offset, start)) if offset < high && start <= frame => {
+                    low = offset;
+                    low_sample = start;
+                }
+                _ => high = middle,
+            }
+        }
+        self.bits.seek(low)?;
+        self.start = 0;
+        self.len = 0;
+        self.pos = 0;
+        self.gap = 0;
+        self.next = low_sample;
+        while self.next_block()?.is_some() {
+            if frame < self.start + self.len as u64 {
+                self.pos = frame.saturating_sub(self.start) as usize;
+                // Part of the silence for skipped frames may be left.
+                self.gap = self.gap.min(self.start.saturating_sub(frame));
+                return Ok(());
+            }
+        }
+        // Past the last FLAC frame, any samples up to `frame` are skipped.
+        self.start = frame;
+        self.len = 0;
+        self.pos = 0;
+        self.gap = 0;
+        self.next = self.start;
+        Ok(())
+    }
+
+    // Find the first frame header at or after `offset`, returning its
+    // offset and the sample number of its first sample.
+    fn find_frame(&mut self, offset: u64) -> Result<Option<(u64, u64)>> {
+        self.bits.seek(offset)?;
+        let info = &self.info;
+        let mut start = 0;
+        let found = self.bits.resync(|bytes| {
+            let header = Header::parse(bytes, info);
+            start = header.map_or(0, |header| header.first_sample(info));
+            header.is_some()
+        })?;
+        Ok(Some((self.bits.position(), start)).filter(|_| found))
+    }
+}
+
+impl<R: Read, F: Frame> Iterator for Decoder<R, F> {
+    type Item = Result<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.next_channels()? {
+            return Some(Err(e));
+        }
+        Some(Ok(mix(&self.mixer, &self.decoded)))
+    }
+}
+
+/// A FLAC frame decoded on its own, for containers other than native FLAC
+/// and Ogg (like MP4 and Matroska) that carry bare FLAC frames.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawFrame {
+    /// Sample number of the first sample in the frame.
+    pub sample: u64,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Bits per sample.
+    pub bits_per_sample: u32,
+    /// Length of the frame in bytes.
+    pub size: usize,
+    // Samples, one block for each channel.
+    block: Vec<i64>,
+    // Number of samples in each block.
+    len: usize,
+}
+
+impl RawFrame {
+    /// Decode the FLAC frame at the start of `bytes`, checking its header
+    /// (CRC-8) and frame (CRC-16) checksums.  The stream information comes
+    /// from the container (see [`metadata::parse()`]), and fills in for
+    /// anything the frame header leaves out.
+    pub fn parse(bytes: &[u8], info: &StreamInfo) -> Result<Self> {
+        let mut bits = Bits::new(bytes);
+        let header = Header::read(&mut bits, info)?.ok_or(Error::Frame)?;
+        let mut block = Vec::new();
+        frame::read(&mut bits, &header, &mut block)?;
+        Ok(Self {
+            sample: header.first_sample(info),
+            sample_rate: header.sample_rate,
+            bits_per_sample: header.bits,
+            size: bits.position() as usize,
+            block,
+            len: header.block_size,
+        })
+    }
+
+    /// Get the number of samples of each channel.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the frame has no samples (never true of a valid frame).
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the number of channels.
+    pub fn channels(&self) -> usize {
+        self.block.len() / self.len
+    }
+
+    /// Get the integer samples of a channel.
+    ///
+    /// # Panics
+    /// If `channel` isn't less than [`channels()`](RawFrame::channels).
+    pub fn channel(&self, channel: usize) -> &[i64] {
+        &self.block[channel * self.len..][..self.len]
+    }
+
+    /// Get the samples as frames of audio, like the [`Decoder`] would.
+    pub fn frames<F: Frame>(&self) -> Vec<F> {
+        let mixer = Mixer::new(self.channels(), F::CHAN_COUNT);
+        let mut channels = vec![F::Chan::default(); self.channels()];
+        (0..self.len)
+            .map(|i| {
+                let samples = self.block[i..].iter().step_by(self.len);
+                for (chan, sample) in channels.iter_mut().zip(samples) {
+                    *chan = chan_from(*sample, self.bits_per_sample);
+                }
+                mix(&mixer, &channels)
+            })
+            .collect()
+    }
+}
+
+// Convert a sample of `bits` bits to a channel.
+fn chan_from<C: Channel>(sample: i64, bits: u32) -> C {
+    let scale = (1u64 << (bits - 1)) as f64 - 0.5;
+    C::from(Ch64::new((sample as f64 + 0.5) / scale))
+}
+
+// Make a frame from the channels of a stream, mixing if needed.
+fn mix<F: Frame>(mixer: &Option<Mixer>, channels: &[F::Chan]) -> F {
+    let mut frame = F::default();
+    match mixer {
+        Some(mixer) => mixer.mix(channels, frame.channels_mut()),
+        None => frame.channels_mut().copy_from_slice(channels),
+    }
+    frame
+}
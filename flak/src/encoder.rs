@@ -0,0 +1,834 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::io::{Result, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use fon::chan::Channel;
+use fon::{Frame, Stream};
+
+use crate::bits::BitWriter;
+use crate::crc::crc16;
+use crate::frame::{Assignment, Header};
+use crate::lpc::{Analysis, Predictor};
+use crate::md5::Md5;
+use crate::metadata::{self, Block, SeekPoint, VorbisComment};
+use crate::ogg::OggWriter;
+use crate::StreamInfo;
+
+/// Settings of a compression level.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Level {
+    /// Number of samples in each block.
+    pub(crate) block_size: u16,
+    // Maximum LPC order, 0 for only FIXED subframes.
+    max_lpc_order: usize,
+    // Maximum Rice partition order.
+    max_partition_order: u32,
+    // Whether to try stereo decorrelation.
+    mid_side: bool,
+    // Whether to try every LPC order rather than the estimated best.
+    exhaustive: bool,
+}
+
+/// Number of blocks each thread encodes at once, when encoding with several
+/// threads.
+#[cfg(feature = "threads")]
+const BATCH: usize = 4;
+
+/// Vendor string of the VORBIS_COMMENT block.
+const VENDOR: &str = concat!("flak ", env!("CARGO_PKG_VERSION"));
+
+/// Compression levels 0 to 8, like the reference encoder's.
+const LEVELS: [Level; 9] = [
+    Level::new(1152, 0, 3, false, false),
+    Level::new(1152, 0, 3, true, false),
+    Level::new(1152, 0, 3, true, false),
+    Level::new(4096, 6, 4, false, false),
+    Level::new(4096, 8, 4, true, false),
+    Level::new(4096, 8, 5, true, false),
+    Level::new(4096, 8, 6, true, false),
+    Level::new(4096, 12, 6, true, false),
+    Level::new(4096, 12, 6, true, true),
+];
+
+impl Level {
+    const fn new(
+        block_size: u16,
+        max_lpc_order: usize,
+        max_partition_order: u32,
+        mid_side: bool,
+        exhaustive: bool,
+    ) -> Self {
+        Self {
+            block_size,
+            max_lpc_order,
+            max_partition_order,
+            mid_side,
+            exhaustive,
+        }
+    }
+}
+
+/// FLAC encoder.  Frames are written as each block of samples fills up; call
+/// [`finish()`](Encoder::finish) (or [`into_inner()`](Encoder::into_inner)
+/// for writers that can't seek) to write out the rest, otherwise it is lost
+/// when the encoder is dropped.
+pub struct Encoder<W: Write, F: Frame> {
+    writer: W,
+    // Stream information of the audio so far.
+    info: StreamInfo,
+    level: Level,
+    // Metadata blocks written after STREAMINFO.
+    metadata: Vec<Block>,
+    // Samples waiting for a full block, one block for each channel.
+    block: Vec<i64>,
+    // Number of samples in each channel's block.
+    len: usize,
+    // Full blocks waiting to be encoded.
+    blocks: Vec<Vec<i64>>,
+    // Number of threads encoding FLAC frames.
+    #[cfg(feature = "threads")]
+    threads: usize,
+    // Number of FLAC frames written.
+    frames: u64,
+    // Number of bytes written, including the metadata.
+    written: u64,
+    // Number of bytes of metadata.
+    first_frame: u64,
+    // Ogg pages, when encoding Ogg FLAC.
+    ogg: Option<OggWriter>,
+    // Signature of the samples so far.
+    md5: Md5,
+    _phantom: PhantomData<F>,
+}
+
+impl<W: Write, F: Frame> Encoder<W, F> {
+    /// Create a new FLAC encoder for audio at `sample_rate` Hz, quantized to
+    /// `bits` bits per sample, using compression level 5.  The metadata is
+    /// a VORBIS_COMMENT block with no tags and
+    /// [`DEFAULT_PADDING`](metadata::DEFAULT_PADDING) bytes of padding.
+    ///
+    /// # Panics
+    /// If the frame type doesn't have 1 to 8 channels, `bits` isn't from 4
+    /// to 32, or the sample rate isn't from 1 to 655350 Hz.
+    pub fn new(writer: W, sample_rate: u32, bits: u8) -> Self {
+        assert!(
+            (1..=8).contains(&F::CHAN_COUNT),
+            "FLAC supports 1 to 8 channels",
+        );
+        assert!((4..=32).contains(&bits), "FLAC supports 4 to 32 bits");
+        assert!(
+            (1..=655_350).contains(&sample_rate),
+            "FLAC supports sample rates from 1 to 655350 Hz",
+        );
+        let level = LEVELS[5];
+        Self {
+            writer,
+            info: StreamInfo {
+                min_block_size: level.block_size,
+                max_block_size: level.block_size,
+                min_frame_size: 0,
+                max_frame_size: 0,
+                sample_rate,
+                channels: F::CHAN_COUNT as u8,
+                bits_per_sample: bits,
+                samples: 0,
+                md5: [0; 16],
+            },
+            level,
+            metadata: vec![
+                Block::VorbisComment(VorbisComment::new(VENDOR)),
+                Block::Padding(metadata::DEFAULT_PADDING),
+            ],
+            block: Vec::new(),
+            len: 0,
+            blocks: Vec::new(),
+            #[cfg(feature = "threads")]
+            threads: 1,
+            frames: 0,
+            written: 0,
+            first_frame: 0,
+            ogg: None,
+            md5: Md5::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Set the compression level, from 0 (fastest) to 8 (smallest).
+    ///
+    /// # Panics
+    /// If `level` is greater than 8, or audio has already been encoded.
+    pub fn with_level(mut self, level: u8) -> Self {
+        assert!(level <= 8, "FLAC compression levels are 0 to 8");
+        assert_eq!(self.info.samples, 0, "Level set after encoding");
+        self.level = LEVELS[usize::from(level)];
+        self.info.min_block_size = self.level.block_size;
+        self.info.max_block_size = self.level.block_size;
+        self
+    }
+
+    /// Set the metadata blocks written after the STREAMINFO block.
+    ///
+    /// Points of a SEEKTABLE block with a sample number, but no offset or
+    /// number of samples, are filled in by [`finish()`](Encoder::finish),
+    /// pointing to the FLAC frame containing that sample.
+    ///
+    /// # Panics
+    /// If audio has already been encoded.  Writing panics if a block is
+    /// 16 MiB or larger.
+    pub fn with_metadata(mut self, blocks: Vec<Block>) -> Self {
+        assert_eq!(self.info.samples, 0, "Metadata set after encoding");
+        self.metadata = blocks;
+        self
+    }
+
+    /// Encode Ogg FLAC, rather than native FLAC.  The serial number
+    /// identifies the stream among others in the Ogg container (any number
+    /// will do for an Ogg FLAC file on its own).  SEEKTABLE points aren't
+    /// filled in for Ogg FLAC.
+    ///
+    /// # Panics
+    /// If audio has already been encoded.
+    pub fn with_ogg(mut self, serial: u32) -> Self {
+        assert_eq!(self.info.samples, 0, "Ogg set after encoding");
+        self.ogg = Some(OggWriter::new(serial));
+        self
+    }
+
+    /// Encode FLAC frames on `threads` threads at once.  The output is the
+    /// same as with one thread, but frames are written in batches of a few
+    /// blocks for each thread, rather than as each block fills up.
+    ///
+    /// # Panics
+    /// If `threads` is 0.
+    #[cfg(feature = "threads")]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        assert_ne!(threads, 0, "Encoding needs at least one thread");
+        self.threads = threads;
+        self
+    }
+
+    /// Get the stream information of the audio encoded so far, including
+    /// its MD5 signature.
+    pub fn stream_info(&self) -> StreamInfo {
+        StreamInfo {
+            md5: self.md5.clone().finish(),
+            ..self.info.clone()
+        }
+    }
+
+    /// Append encoded data from a stream to the output, returning the number
+    /// of frames of audio encoded.  This can be called multiple times to
+    /// encode as needed instead of all at once.
+    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> Result<usize> {
+        let size = usize::from(self.level.block_size);
+        let bits = u32::from(self.info.bits_per_sample);
+        let bytes = usize::from(self.info.bits_per_sample).div_ceil(8);
+        self.block.resize(size * F::CHAN_COUNT, 0);
+        let mut count = 0;
+        for frame in stream.into_iter() {
+            for (i, chan) in frame.channels().iter().enumerate() {
+                let sample = quantize(chan.to_f64(), bits);
+                self.block[i * size + self.len] = sample;
+                self.md5.update(&sample.to_le_bytes()[..bytes]);
+            }
+            self.len += 1;
+            self.info.samples += 1;
+            if self.len == size {
+                self.write_block()?;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Finish encoding and return the writer, without going back to fill in
+    /// the total number of samples, frame sizes and MD5 signature of the
+    /// STREAMINFO block (they're left as unknown).
+    pub fn into_inner(mut self) -> Result<W> {
+        self.finish_frames()?;
+        Ok(self.writer)
+    }
+
+    // Write the last (partial) block, and the metadata if nothing has been
+    // written yet.
+    fn finish_frames(&mut self) -> Result<()> {
+        if self.len != 0 {
+            self.write_block()?;
+        }
+        if !self.blocks.is_empty() {
+            self.write_blocks()?;
+        }
+        if self.written == 0 {
+            self.write_metadata()?;
+        }
+        if let Some(ogg) = self.ogg.as_mut() {
+            let pages = ogg.finish();
+            self.writer.write_all(&pages)?;
+            self.written += pages.len() as u64;
+        }
+        self.writer.flush()
+    }
+
+    // Write the metadata, with a STREAMINFO block that doesn't know the
+    // audio yet.
+    fn write_metadata(&mut self) -> Result<()> {
+        let info = StreamInfo {
+            min_frame_size: 0,
+            max_frame_size: 0,
+            samples: 0,
+            md5: [0; 16],
+            ..self.info.clone()
+        };
+        let mut metadata = metadata::write(&info, &self.metadata);
+        if let Some(ogg) = self.ogg.as_mut() {
+            metadata = ogg.header(&metadata);
+        }
+        self.writer.write_all(&metadata)?;
+        self.written += metadata.len() as u64;
+        self.first_frame = self.written;
+        Ok(())
+    }
+
+    // Queue the samples waiting in the block to be encoded as a FLAC frame.
+    fn write_block(&mut self) -> Result<()> {
+        let size = usize::from(self.level.block_size);
+        let mut block = vec![0; size * F::CHAN_COUNT];
+        std::mem::swap(&mut block, &mut self.block);
+        if self.len != size {
+            // Move the channels of the last partial block together.
+            for channel in 1..F::CHAN_COUNT {
+                let start = channel * size;
+                block.copy_within(start..start + self.len, channel * self.len);
+            }
+            block.truncate(self.len * F::CHAN_COUNT);
+        }
+        self.len = 0;
+        self.blocks.push(block);
+        if self.blocks.len() >= self.batch() {
+            self.write_blocks()?;
+        }
+        Ok(())
+    }
+
+    // Get the number of blocks to encode at once.
+    #[cfg(feature = "threads")]
+    fn batch(&self) -> usize {
+        if self.threads == 1 {
+            1
+        } else {
+            self.threads * BATCH
+        }
+    }
+
+    // Get the number of blocks to encode at once.
+    #[cfg(not(feature = "threads"))]
+    fn batch(&self) -> usize {
+        1
+    }
+
+    // Encode the waiting blocks as FLAC frames, and write them.
+    fn write_blocks(&mut self) -> Result<()> {
+        if self.written == 0 {
+            self.write_metadata()?;
+        }
+        let blocks = std::mem::take(&mut self.blocks);
+        let (level, info) = (&self.level, &self.info);
+        #[cfg(feature = "threads")]
+        let frames = threaded(level, info, self.frames, &blocks, self.threads);
+        #[cfg(not(feature = "threads"))]
+        let frames = frames(level, info, self.frames, &blocks);
+        for (block, frame) in blocks.iter().zip(frames) {
+            self.write_frame(frame, block.len() / F::CHAN_COUNT)?;
+        }
+        Ok(())
+    }
+
+    // Write a FLAC frame of `len` samples for each channel.
+    fn write_frame(&mut self, frame: Vec<u8>, len: usize) -> Result<()> {
+        let size = frame.len() as u32;
+        if self.frames == 0 || size < self.info.min_frame_size {
+            self.info.min_frame_size = size;
+        }
+        self.info.max_frame_size = self.info.max_frame_size.max(size);
+        let bytes = match self.ogg.as_mut() {
+            Some(ogg) => ogg.frame(&frame, len as u64),
+            None => {
+                let point = SeekPoint {
+                    sample: self.frames * u64::from(self.level.block_size),
+                    offset: self.written - self.first_frame,
+                    samples: len as u16,
+                };
+                for block in self.metadata.iter_mut() {
+                    if let Block::SeekTable(points) = block {
+                        fill(points, point);
+                    }
+                }
+                frame
+            }
+        };
+        self.writer.write_all(&bytes)?;
+        self.written += bytes.len() as u64;
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek, F: Frame> Encoder<W, F> {
+    /// Finish encoding, fill in the STREAMINFO block and return the writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.finish_frames()?;
+        let end = self.writer.stream_position()?;
+        for block in self.metadata.iter_mut() {
+            if let Block::SeekTable(points) = block {
+                tidy(points);
+            }
+        }
+        // The metadata stays the same length.
+        let mut metadata = metadata::write(&self.stream_info(), &self.metadata);
+        if let Some(ogg) = self.ogg.as_ref() {
+            metadata = ogg.rewrite(&metadata);
+        }
+        self.writer.seek(SeekFrom::Start(end - self.written))?;
+        self.writer.write_all(&metadata)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Fill in seek points for samples in the FLAC frame at `frame`.
+fn fill(points: &mut [SeekPoint], frame: SeekPoint) {
+    let samples = frame.sample..frame.sample + u64::from(frame.samples);
+    for point in points.iter_mut() {
+        let template = point.offset == 0 && point.samples == 0;
+        if template && samples.contains(&point.sample) {
+            *point = frame;
+        }
+    }
+}
+
+// Turn seek points that weren't filled in, or that point to the same FLAC
+// frame as another, into placeholders, which go last.
+fn tidy(points: &mut [SeekPoint]) {
+    let placeholder = SeekPoint {
+        sample: SeekPoint::PLACEHOLDER,
+        offset: 0,
+        samples: 0,
+    };
+    for point in points.iter_mut() {
+        if point.samples == 0 {
+            *point = placeholder;
+        }
+    }
+    points.sort_by_key(|point| point.sample);
+    for i in 1..points.len() {
+        if points[i].sample == points[i - 1].sample {
+            points[i - 1] = placeholder;
+        }
+    }
+    points.sort_by_key(|point| point.sample);
+}
+
+// Quantize a channel to a `bits`-bit integer, the inverse of decoding.
+fn quantize(sample: f64, bits: u32) -> i64 {
+    let max = 1i64 << (bits - 1);
+    let scale = max as f64 - 0.5;
+    ((sample * scale - 0.5).round() as i64).clamp(-max, max - 1)
+}
+
+// Encode FLAC frames from blocks, numbered from `number`.
+fn frames(
+    level: &Level,
+    info: &StreamInfo,
+    number: u64,
+    blocks: &[Vec<i64>],
+) -> Vec<Vec<u8>> {
+    blocks
+        .iter()
+        .zip(number..)
+        .map(|(block, number)| frame(level, info, number, block))
+        .collect()
+}
+
+// Encode FLAC frames from blocks, numbered from `number`, splitting the
+// blocks between up to `threads` threads.
+#[cfg(feature = "threads")]
+fn threaded(
+    level: &Level,
+    info: &StreamInfo,
+    number: u64,
+    blocks: &[Vec<i64>],
+    threads: usize,
+) -> Vec<Vec<u8>> {
+    if threads == 1 || blocks.len() == 1 {
+        return frames(level, info, number, blocks);
+    }
+    let chunk = blocks.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = blocks
+            .chunks(chunk)
+            .zip((number..).step_by(chunk))
+            .map(|(blocks, number)| {
+                scope.spawn(move || frames(level, info, number, blocks))
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+/// Encode a FLAC frame from one block of samples for each channel.
+pub(crate) fn frame(
+    level: &Level,
+    info: &StreamInfo,
+    number: u64,
+    block: &[i64],
+) -> Vec<u8> {
+    let channels = usize::from(info.channels);
+    let size = block.len() / channels;
+    let bits = u32::from(info.bits_per_sample);
+    let mut writer = BitWriter::new();
+    if channels == 2 && level.mid_side {
+        let (left, right) = block.split_at(size);
+        let pairs = left.iter().zip(right);
+        let side: Vec<i64> = pairs.clone().map(|(l, r)| l - r).collect();
+        let mid: Vec<i64> = pairs.map(|(l, r)| (l + r) >> 1).collect();
+        let left = (Subframe::new(left, bits, level), left);
+        let right = (Subframe::new(right, bits, level), right);
+        let side = (Subframe::new(&side, bits + 1, level), &side[..]);
+        let mid = (Subframe::new(&mid, bits, level), &mid[..]);
+        let choices = [
+            (Assignment::Independent(2), &left, &right),
+            (Assignment::LeftSide, &left, &side),
+            (Assignment::SideRight, &side, &right),
+            (Assignment::MidSide, &mid, &side),
+        ];
+        let (assignment, first, second) = choices
+            .iter()
+            .min_by_key(|(_, first, second)| first.0.size + second.0.size)
+            .unwrap();
+        header(info, size, *assignment, number).write(&mut writer);
+        for (subframe, samples) in [first, second].iter() {
+            subframe.write(&mut writer, samples);
+        }
+    } else {
+        let assignment = Assignment::Independent(info.channels);
+        header(info, size, assignment, number).write(&mut writer);
+        for samples in block.chunks_exact(size) {
+            Subframe::new(samples, bits, level).write(&mut writer, samples);
+        }
+    }
+    writer.align();
+    let crc = writer.bytes().iter().fold(0, |crc, byte| crc16(crc, *byte));
+    writer.write(crc.into(), 16);
+    writer.into_bytes()
+}
+
+// Get the header of a frame in a fixed block size stream.
+fn header(
+    info: &StreamInfo,
+    block_size: usize,
+    assignment: Assignment,
+    number: u64,
+) -> Header {
+    Header {
+        variable: false,
+        block_size,
+        sample_rate: info.sample_rate,
+        assignment,
+        bits: info.bits_per_sample.into(),
+        number,
+    }
+}
+
+/// How a subframe is coded.
+enum Kind {
+    Constant,
+    Verbatim,
+    Fixed(usize),
+    Lpc(Predictor),
+}
+
+/// An analyzed subframe, ready to be written.
+struct Subframe {
+    kind: Kind,
+    // Number of 0 bits at the bottom of every sample.
+    wasted: u32,
+    // Bits per sample, not including wasted bits.
+    bits: u32,
+    residual: Vec<i64>,
+    rice: Rice,
+    // Size in bits.
+    size: u64,
+}
+
+impl Subframe {
+    // Find the smallest coding of `samples` of `bits` bits per sample.
+    fn new(samples: &[i64], bits: u32, level: &Level) -> Self {
+        let or = samples.iter().fold(0, |or, sample| or | sample);
+        let wasted = if or == 0 {
+            0
+        } else {
+            or.trailing_zeros().min(bits - 1)
+        };
+        let shifted: Vec<i64> = samples.iter().map(|s| s >> wasted).collect();
+        let bits = bits - wasted;
+        // Type, and wasted bits flag and count.
+        let overhead = 8 + u64::from(wasted);
+        let mut best = Subframe {
+            kind: Kind::Verbatim,
+            wasted,
+            bits,
+            residual: Vec::new(),
+            rice: Rice::default(),
+            size: overhead + u64::from(bits) * samples.len() as u64,
+        };
+        if shifted.iter().all(|sample| *sample == shifted[0]) {
+            best.kind = Kind::Constant;
+            best.size = overhead + u64::from(bits);
+            return best;
+        }
+        let len = shifted.len();
+        let mut consider = |kind: Kind, residual: Vec<i64>, size: u64| {
+            let order = len - residual.len();
+            let range = i64::from(i32::MIN)..=i64::from(i32::MAX);
+            if !residual.iter().all(|r| range.contains(r)) {
+                return;
+            }
+            let rice = Rice::new(&residual, order, len, level);
+            let size = overhead + size + rice.size;
+            if size < best.size {
+                best = Subframe {
+                    kind,
+                    wasted,
+                    bits,
+                    residual,
+                    rice,
+                    size,
+                };
+            }
+        };
+        for order in 0..=4.min(len - 1) {
+            let residual = fixed(&shifted, order);
+            consider(
+                Kind::Fixed(order),
+                residual,
+                order as u64 * u64::from(bits),
+            );
+        }
+        let analysis = match level.max_lpc_order {
+            0 => None,
+            order => Analysis::new(&shifted, order),
+        };
+        if let Some(analysis) = analysis {
+            let precision = precision(bits, len);
+            let orders = if level.exhaustive {
+                1..=analysis.max_order()
+            } else {
+                let order = analysis.best_order(len, bits, precision);
+                order..=order
+            };
+            for order in orders {
+                let predictor = match analysis.quantize(order, precision) {
+                    Some(predictor) => predictor,
+                    None => continue,
+                };
+                let residual = lpc(&shifted, &predictor);
+                let size = order as u64 * u64::from(bits + precision) + 9;
+                consider(Kind::Lpc(predictor), residual, size);
+            }
+        }
+        best
+    }
+
+    // Write the subframe of the original `samples`.
+    fn write(&self, writer: &mut BitWriter, samples: &[i64]) {
+        let len = samples.len();
+        let mut samples = samples.iter().map(|sample| sample >> self.wasted);
+        writer.write(0, 1);
+        match &self.kind {
+            Kind::Constant => writer.write(0, 6),
+            Kind::Verbatim => writer.write(1, 6),
+            Kind::Fixed(order) => writer.write(8 + *order as u32, 6),
+            Kind::Lpc(lpc) => {
+                writer.write(31 + lpc.coefficients.len() as u32, 6)
+            }
+        }
+        if self.wasted == 0 {
+            writer.write(0, 1);
+        } else {
+            writer.write(1, 1);
+            writer.unary(self.wasted - 1);
+        }
+        match &self.kind {
+            Kind::Constant => writer.signed(samples.next().unwrap(), self.bits),
+            Kind::Verbatim => {
+                samples.for_each(|sample| writer.signed(sample, self.bits))
+            }
+            Kind::Fixed(order) => {
+                for sample in samples.take(*order) {
+                    writer.signed(sample, self.bits);
+                }
+                self.rice.write(writer, &self.residual, len);
+            }
+            Kind::Lpc(lpc) => {
+                let order = lpc.coefficients.len();
+                for sample in samples.take(order) {
+                    writer.signed(sample, self.bits);
+                }
+                writer.write(lpc.precision - 1, 4);
+                writer.write(lpc.shift, 5);
+                for coefficient in &lpc.coefficients {
+                    writer.signed(*coefficient, lpc.precision);
+                }
+                self.rice.write(writer, &self.residual, len);
+            }
+        }
+    }
+}
+
+// Get the LPC coefficient precision, like the reference encoder.
+fn precision(bits: u32, block_size: usize) -> u32 {
+    match (bits, block_size) {
+        (0..=15, _) => (2 + bits / 2).max(5),
+        (16, 0..=192) => 7,
+        (16, 193..=384) => 8,
+        (16, 385..=576) => 9,
+        (16, 577..=1152) => 10,
+        (16, 1153..=2304) => 11,
+        (16, 2305..=4608) => 12,
+        (16, _) => 13,
+        _ => 15,
+    }
+}
+
+// Get the residual of a FIXED predictor.
+fn fixed(samples: &[i64], order: usize) -> Vec<i64> {
+    let mut residual = samples.to_vec();
+    for _ in 0..order {
+        for i in (1..residual.len()).rev() {
+            residual[i] -= residual[i - 1];
+        }
+    }
+    residual.split_off(order)
+}
+
+// Get the residual of an LPC predictor.
+fn lpc(samples: &[i64], predictor: &Predictor) -> Vec<i64> {
+    let order = predictor.coefficients.len();
+    (order..samples.len())
+        .map(|i| {
+            let history = samples[i - order..i].iter().rev();
+            let prediction: i64 = predictor
+                .coefficients
+                .iter()
+                .zip(history)
+                .map(|(c, s)| c * s)
+                .sum();
+            samples[i] - (prediction >> predictor.shift)
+        })
+        .collect()
+}
+
+/// Partitioned Rice coding of a residual.
+#[derive(Default)]
+struct Rice {
+    // Partition order.
+    order: u32,
+    // Rice parameter of each partition.
+    parameters: Vec<u32>,
+    // Whether 5-bit parameters are needed.
+    wide: bool,
+    // Size in bits.
+    size: u64,
+}
+
+impl Rice {
+    // Find the partition order and parameters with the smallest coding of
+    // the `residual` of a block of `len` samples after `warm_up` samples.
+    fn new(
+        residual: &[i64],
+        warm_up: usize,
+        len: usize,
+        level: &Level,
+    ) -> Self {
+        let values: Vec<u64> =
+            residual.iter().map(|r| (r << 1 ^ r >> 63) as u64).collect();
+        let mut best: Option<Rice> = None;
+        for order in 0..=level.max_partition_order {
+            let partition = len >> order;
+            if partition << order != len || partition < warm_up {
+                break;
+            }
+            let mut parameters = Vec::with_capacity(1 << order);
+            let mut size = 0;
+            let mut start = 0;
+            for i in 0..1 << order {
+                let end = (i + 1) * partition - warm_up;
+                let (parameter, bits) = parameter(&values[start..end]);
+                parameters.push(parameter);
+                size += bits;
+                start = end;
+            }
+            let wide = parameters.iter().any(|parameter| *parameter >= 15);
+            let parameter_bits = if wide { 5 } else { 4 };
+            size += 6 + parameters.len() as u64 * parameter_bits;
+            if best.as_ref().map_or(true, |best| size < best.size) {
+                best = Some(Rice {
+                    order,
+                    parameters,
+                    wide,
+                    size,
+                });
+            }
+        }
+        best.unwrap()
+    }
+
+    // Write the coded residual.
+    fn write(&self, writer: &mut BitWriter, residual: &[i64], len: usize) {
+        writer.write(self.wide.into(), 2);
+        writer.write(self.order, 4);
+        let parameter_bits = if self.wide { 5 } else { 4 };
+        let partition = len >> self.order;
+        let warm_up = len - residual.len();
+        let mut start = 0;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let end = (i + 1) * partition - warm_up;
+            writer.write(*parameter, parameter_bits);
+            for value in &residual[start..end] {
+                writer.rice(*value, *parameter);
+            }
+            start = end;
+        }
+    }
+}
+
+// Find the best Rice parameter for zigzag coded `values`, returning it with
+// the size of the coded values in bits.
+fn parameter(values: &[u64]) -> (u32, u64) {
+    if values.is_empty() {
+        return (0, 0);
+    }
+    let sum: u64 = values.iter().sum();
+    let mean = sum / values.len() as u64;
+    let estimate = 63 - mean.max(1).leading_zeros();
+    let low = estimate.saturating_sub(1);
+    let high = (estimate + 1).min(30);
+    (low..=high)
+        .map(|k| {
+            let bits = values.iter().map(|value| (value >> k) + 1).sum::<u64>()
+                + values.len() as u64 * u64::from(k);
+            (k, bits)
+        })
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap()
+}
@@ -0,0 +1,60 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::fmt;
+use std::io;
+
+/// FLAC Result Type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// FLAC Error.
+#[derive(Debug)]
+pub enum Error {
+    /// The stream doesn't start with the `fLaC` marker.
+    NotFlac,
+    /// The STREAMINFO metadata block is missing or invalid.
+    StreamInfo,
+    /// A metadata block other than STREAMINFO is invalid.
+    Metadata,
+    /// A frame header is invalid, or isn't where one should be.
+    Frame,
+    /// A subframe is invalid.
+    Subframe,
+    /// A frame header (CRC-8) or frame (CRC-16) checksum doesn't match.
+    Crc,
+    /// An I/O Error
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFlac => f.write_str("Not a FLAC stream"),
+            Error::StreamInfo => f.write_str("Invalid STREAMINFO block"),
+            Error::Metadata => f.write_str("Invalid metadata block"),
+            Error::Frame => f.write_str("Invalid frame header"),
+            Error::Subframe => f.write_str("Invalid subframe"),
+            Error::Crc => f.write_str("Checksum mismatch"),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
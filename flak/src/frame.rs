@@ -0,0 +1,507 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Frames and subframes.
+
+use std::io::Read;
+
+use crate::bits::{BitWriter, Bits};
+use crate::crc::crc8;
+use crate::{Error, Result, StreamInfo};
+
+/// How the channels of a frame are coded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Assignment {
+    /// Each channel coded separately.
+    Independent(u8),
+    /// Left and side (left - right) channels.
+    LeftSide,
+    /// Side (left - right) and right channels.
+    SideRight,
+    /// Mid ((left + right) / 2) and side (left - right) channels.
+    MidSide,
+}
+
+impl Assignment {
+    /// Get the number of channels.
+    pub(crate) fn channels(self) -> usize {
+        match self {
+            Assignment::Independent(channels) => channels.into(),
+            _ => 2,
+        }
+    }
+
+    // Get the index of the side channel, which has an extra bit.
+    fn side(self) -> Option<usize> {
+        match self {
+            Assignment::Independent(_) => None,
+            Assignment::LeftSide | Assignment::MidSide => Some(1),
+            Assignment::SideRight => Some(0),
+        }
+    }
+}
+
+/// Frame header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Header {
+    /// Whether the block size may change between frames, in which case
+    /// `number` counts samples rather than frames.
+    pub(crate) variable: bool,
+    /// Number of samples of each channel.
+    pub(crate) block_size: usize,
+    /// Sample rate in Hz.
+    pub(crate) sample_rate: u32,
+    pub(crate) assignment: Assignment,
+    /// Bits per sample.
+    pub(crate) bits: u32,
+    /// Frame number, or sample number of the first sample if `variable`.
+    pub(crate) number: u64,
+}
+
+impl Header {
+    /// Read a frame header, starting at the sync code, returning `None` at
+    /// the end of the stream.
+    pub(crate) fn read<R: Read>(
+        bits: &mut Bits<R>,
+        info: &StreamInfo,
+    ) -> Result<Option<Self>> {
+        if bits.at_end()? {
+            return Ok(None);
+        }
+        bits.reset_crc();
+        let sync = bits.read(16)?;
+        if sync & 0xFFFE != 0xFFF8 {
+            return Err(Error::Frame);
+        }
+        let variable = sync & 1 == 1;
+        let block_size = bits.read(4)?;
+        let sample_rate = bits.read(4)?;
+        let assignment = match bits.read(4)? {
+            n @ 0..=7 => Assignment::Independent(n as u8 + 1),
+            8 => Assignment::LeftSide,
+            9 => Assignment::SideRight,
+            10 => Assignment::MidSide,
+            _ => return Err(Error::Frame),
+        };
+        let sample_bits = match bits.read(3)? {
+            0 => info.bits_per_sample.into(),
+            1 => 8,
+            2 => 12,
+            4 => 16,
+            5 => 20,
+            6 => 24,
+            7 => 32,
+            _ => return Err(Error::Frame),
+        };
+        if bits.read(1)? != 0 {
+            return Err(Error::Frame);
+        }
+        let number = coded_number(bits)?;
+        let block_size = match block_size {
+            0 => return Err(Error::Frame),
+            1 => 192,
+            n @ 2..=5 => 576 << (n - 2),
+            6 => bits.read(8)? as usize + 1,
+            7 => bits.read(16)? as usize + 1,
+            n => 256 << (n - 8),
+        };
+        let sample_rate = match sample_rate {
+            0 => info.sample_rate,
+            1 => 88_200,
+            2 => 176_400,
+            3 => 192_000,
+            4 => 8_000,
+            5 => 16_000,
+            6 => 22_050,
+            7 => 24_000,
+            8 => 32_000,
+            9 => 44_100,
+            10 => 48_000,
+            11 => 96_000,
+            12 => bits.read(8)? * 1000,
+            13 => bits.read(16)?,
+            14 => bits.read(16)? * 10,
+            _ => return Err(Error::Frame),
+        };
+        let crc = bits.crc8();
+        if bits.read(8)? != u32::from(crc) {
+            return Err(Error::Crc);
+        }
+        Ok(Some(Header {
+            variable,
+            block_size,
+            sample_rate,
+            assignment,
+            bits: sample_bits,
+            number,
+        }))
+    }
+
+    /// Parse a frame header at the start of `bytes`, if there's a valid one
+    /// (with a matching CRC-8) that fits the stream.
+    pub(crate) fn parse(bytes: &[u8], info: &StreamInfo) -> Option<Self> {
+        if bytes.get(1).map_or(true, |byte| byte & 0xFE != 0xF8) {
+            return None;
+        }
+        let header = Header::read(&mut Bits::new(bytes), info).ok()??;
+        let fits = header.assignment.channels() == info.channels.into()
+            && header.sample_rate == info.sample_rate
+            && header.bits == u32::from(info.bits_per_sample)
+            && header.block_size <= info.max_block_size.into()
+            && (info.samples == 0 || header.first_sample(info) < info.samples);
+        Some(header).filter(|_| fits)
+    }
+
+    /// Read the header of the first frame of a stream without a STREAMINFO
+    /// block, along with stream information made up from it.
+    pub(crate) fn first(bytes: &[u8]) -> Result<(Self, StreamInfo)> {
+        let unknown = StreamInfo {
+            min_block_size: 0,
+            max_block_size: 0,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            sample_rate: 0,
+            channels: 0,
+            bits_per_sample: 0,
+            samples: 0,
+            md5: [0; 16],
+        };
+        let header = Header::read(&mut Bits::new(bytes), &unknown)?
+            .ok_or(Error::NotFlac)?;
+        // The sample rate and bits per sample may be left to STREAMINFO.
+        if header.sample_rate == 0 || header.bits == 0 {
+            return Err(Error::StreamInfo);
+        }
+        let block_size = header.block_size.min(u16::MAX.into()) as u16;
+        let (min_block_size, max_block_size) = if header.variable {
+            (16, u16::MAX)
+        } else {
+            (block_size, block_size)
+        };
+        let info = StreamInfo {
+            min_block_size,
+            max_block_size,
+            sample_rate: header.sample_rate,
+            channels: header.assignment.channels() as u8,
+            bits_per_sample: header.bits as u8,
+            ..unknown
+        };
+        Ok((header, info))
+    }
+
+    /// Get the sample number of the first sample in the frame.
+    pub(crate) fn first_sample(&self, info: &StreamInfo) -> u64 {
+        if self.variable {
+            self.number
+        } else {
+            self.number * u64::from(info.max_block_size)
+        }
+    }
+
+    /// Write the frame header, including its CRC-8.
+    pub(crate) fn write(&self, writer: &mut BitWriter) {
+        let start = writer.bytes().len();
+        let block_size = match self.block_size {
+            192 => (1, 0),
+            576 | 1152 | 2304 | 4608 => {
+                (2 + (self.block_size / 576).trailing_zeros(), 0)
+            }
+            256 | 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768 => {
+                (8 + (self.block_size / 256).trailing_zeros(), 0)
+            }
+            1..=256 => (6, 8),
+            _ => (7, 16),
+        };
+        let sample_rate = match self.sample_rate {
+            88_200 => (1, 0),
+            176_400 => (2, 0),
+            192_000 => (3, 0),
+            8_000 => (4, 0),
+            16_000 => (5, 0),
+            22_050 => (6, 0),
+            24_000 => (7, 0),
+            32_000 => (8, 0),
+            44_100 => (9, 0),
+            48_000 => (10, 0),
+            96_000 => (11, 0),
+            hz if hz % 1000 == 0 && hz <= 255_000 => (12, 8),
+            hz if hz <= 65_535 => (13, 16),
+            hz if hz % 10 == 0 && hz <= 655_350 => (14, 16),
+            _ => (0, 0),
+        };
+        let assignment = match self.assignment {
+            Assignment::Independent(channels) => u32::from(channels) - 1,
+            Assignment::LeftSide => 8,
+            Assignment::SideRight => 9,
+            Assignment::MidSide => 10,
+        };
+        let sample_bits = match self.bits {
+            8 => 1,
+            12 => 2,
+            16 => 4,
+            20 => 5,
+            24 => 6,
+            32 => 7,
+            _ => 0,
+        };
+        writer.write(0xFFF8 | u32::from(self.variable), 16);
+        writer.write(block_size.0, 4);
+        writer.write(sample_rate.0, 4);
+        writer.write(assignment, 4);
+        writer.write(sample_bits, 3);
+        writer.write(0, 1);
+        write_coded_number(writer, self.number);
+        writer.write(self.block_size as u32 - 1, block_size.1);
+        let hz = match sample_rate.0 {
+            12 => self.sample_rate / 1000,
+            14 => self.sample_rate / 10,
+            _ => self.sample_rate,
+        };
+        writer.write(hz, sample_rate.1);
+        let crc = writer.bytes()[start..]
+            .iter()
+            .fold(0, |crc, byte| crc8(crc, *byte));
+        writer.write(crc.into(), 8);
+    }
+}
+
+// Read a number coded like UTF-8 (up to 36 bits in 7 bytes).
+fn coded_number<R: Read>(bits: &mut Bits<R>) -> Result<u64> {
+    let first = bits.read(8)? as u8;
+    let len = first.leading_ones();
+    match len {
+        0 => return Ok(first.into()),
+        1 | 8 => return Err(Error::Frame),
+        _ => {}
+    }
+    let mut number = u64::from(first & (0x7F >> len));
+    for _ in 1..len {
+        let byte = bits.read(8)?;
+        if byte & 0xC0 != 0x80 {
+            return Err(Error::Frame);
+        }
+        number = number << 6 | u64::from(byte & 0x3F);
+    }
+    Ok(number)
+}
+
+// Write a number coded like UTF-8 (up to 36 bits in 7 bytes).
+fn write_coded_number(writer: &mut BitWriter, number: u64) {
+    if number < 0x80 {
+        writer.write(number as u32, 8);
+        return;
+    }
+    // Each continuation byte holds 6 bits, and the first byte 7 - len bits.
+    let len = (2..7).find(|len| number >> (5 * len + 1) == 0).unwrap_or(7);
+    let prefix = !(0xFFu32 >> len) & 0xFF;
+    writer.write(prefix | (number >> (6 * (len - 1))) as u32, 8);
+    for i in (0..len - 1).rev() {
+        writer.write(0x80 | (number >> (6 * i)) as u32 & 0x3F, 8);
+    }
+}
+
+/// Read the rest of a frame after its header into `samples` (one block of
+/// `header.block_size` samples for each channel), checking the frame CRC.
+pub(crate) fn read<R: Read>(
+    bits: &mut Bits<R>,
+    header: &Header,
+    samples: &mut Vec<i64>,
+) -> Result<()> {
+    let size = header.block_size;
+    let channels = header.assignment.channels();
+    samples.clear();
+    samples.resize(size * channels, 0);
+    for (channel, block) in samples.chunks_exact_mut(size).enumerate() {
+        let side = header.assignment.side() == Some(channel);
+        subframe(bits, block, header.bits + u32::from(side))?;
+    }
+    bits.align();
+    let crc = bits.crc16();
+    if bits.read(16)? != u32::from(crc) {
+        return Err(Error::Crc);
+    }
+    decorrelate(header.assignment, samples, size);
+    Ok(())
+}
+
+// Restore left and right channels from stereo decorrelation.
+fn decorrelate(assignment: Assignment, samples: &mut [i64], size: usize) {
+    let (first, second) = samples.split_at_mut(size);
+    let pairs = first.iter_mut().zip(second.iter_mut());
+    match assignment {
+        Assignment::Independent(_) => {}
+        Assignment::LeftSide => {
+            for (left, side) in pairs {
+                *side = left.wrapping_sub(*side);
+            }
+        }
+        Assignment::SideRight => {
+            for (side, right) in pairs {
+                *side = side.wrapping_add(*right);
+            }
+        }
+        Assignment::MidSide => {
+            for (mid, side) in pairs {
+                let sum = (*mid << 1 | *side & 1).wrapping_add(*side);
+                let difference = (*mid << 1 | *side & 1).wrapping_sub(*side);
+                *mid = sum >> 1;
+                *side = difference >> 1;
+            }
+        }
+    }
+}
+
+// Read a subframe of `sample_bits` bits per sample.
+fn subframe<R: Read>(
+    bits: &mut Bits<R>,
+    block: &mut [i64],
+    sample_bits: u32,
+) -> Result<()> {
+    if bits.read(1)? != 0 {
+        return Err(Error::Subframe);
+    }
+    let kind = bits.read(6)?;
+    let wasted = match bits.read(1)? {
+        0 => 0,
+        _ => bits.unary()? + 1,
+    };
+    if wasted >= sample_bits {
+        return Err(Error::Subframe);
+    }
+    let sample_bits = sample_bits - wasted;
+    match kind {
+        // CONSTANT
+        0 => {
+            let value = bits.signed(sample_bits)?;
+            block.iter_mut().for_each(|sample| *sample = value);
+        }
+        // VERBATIM
+        1 => {
+            for sample in block.iter_mut() {
+                *sample = bits.signed(sample_bits)?;
+            }
+        }
+        // FIXED
+        8..=12 => fixed(bits, block, kind as usize - 8, sample_bits)?,
+        // LPC
+        32..=63 => lpc(bits, block, kind as usize - 31, sample_bits)?,
+        _ => return Err(Error::Subframe),
+    }
+    if wasted != 0 {
+        block.iter_mut().for_each(|sample| *sample <<= wasted);
+    }
+    Ok(())
+}
+
+// Read the warm-up samples of a predicted subframe.
+fn warm_up<R: Read>(
+    bits: &mut Bits<R>,
+    block: &mut [i64],
+    order: usize,
+    sample_bits: u32,
+) -> Result<()> {
+    if order > block.len() {
+        return Err(Error::Subframe);
+    }
+    for sample in block[..order].iter_mut() {
+        *sample = bits.signed(sample_bits)?;
+    }
+    Ok(())
+}
+
+// Read a FIXED subframe.
+fn fixed<R: Read>(
+    bits: &mut Bits<R>,
+    block: &mut [i64],
+    order: usize,
+    sample_bits: u32,
+) -> Result<()> {
+    warm_up(bits, block, order, sample_bits)?;
+    residual(bits, block, order)?;
+    let coefficients: &[i64] = match order {
+        0 => &[],
+        1 => &[1],
+        2 => &[2, -1],
+        3 => &[3, -3, 1],
+        _ => &[4, -6, 4, -1],
+    };
+    predict(block, coefficients, 0);
+    Ok(())
+}
+
+// Add the prediction from the previous samples to the residual.
+fn predict(block: &mut [i64], coefficients: &[i64], shift: u32) {
+    let order = coefficients.len();
+    for i in order..block.len() {
+        let history = block[i - order..i].iter().rev();
+        let prediction = coefficients
+            .iter()
+            .zip(history)
+            .fold(0i64, |sum, (c, s)| sum.wrapping_add(c.wrapping_mul(*s)));
+        block[i] = block[i].wrapping_add(prediction >> shift);
+    }
+}
+
+// Read an LPC subframe.
+fn lpc<R: Read>(
+    bits: &mut Bits<R>,
+    block: &mut [i64],
+    order: usize,
+    sample_bits: u32,
+) -> Result<()> {
+    warm_up(bits, block, order, sample_bits)?;
+    let precision = bits.read(4)? + 1;
+    if precision == 16 {
+        return Err(Error::Subframe);
+    }
+    let shift = bits.signed(5)?;
+    if shift < 0 {
+        return Err(Error::Subframe);
+    }
+    let mut coefficients = [0; 32];
+    for coefficient in coefficients[..order].iter_mut() {
+        *coefficient = bits.signed(precision)?;
+    }
+    residual(bits, block, order)?;
+    predict(block, &coefficients[..order], shift as u32);
+    Ok(())
+}
+
+// Read a (partitioned) Rice coded residual after `order` warm-up samples.
+fn residual<R: Read>(
+    bits: &mut Bits<R>,
+    block: &mut [i64],
+    order: usize,
+) -> Result<()> {
+    let (parameter_bits, escape) = match bits.read(2)? {
+        0 => (4, 0xF),
+        1 => (5, 0x1F),
+        _ => return Err(Error::Subframe),
+    };
+    let partition_order = bits.read(4)?;
+    let partition = block.len() >> partition_order;
+    if partition << partition_order != block.len() || partition < order {
+        return Err(Error::Subframe);
+    }
+    let mut start = order;
+    for i in 0..1usize << partition_order {
+        let end = (i + 1) * partition;
+        let parameter = bits.read(parameter_bits)?;
+        if parameter == escape {
+            let raw = bits.read(5)?;
+            for sample in block[start..end].iter_mut() {
+                *sample = bits.signed(raw)?;
+            }
+        } else {
+            for sample in block[start..end].iter_mut() {
+                *sample = bits.rice(parameter)?;
+            }
+        }
+        start = end;
+    }
+    Ok(())
+}
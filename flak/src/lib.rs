@@ -1,3 +1,74 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+//
+//! Safe, pure Rust FLAC decoding into and encoding from fon types.
+//!
+//! A [`Decoder`] reads the `fLaC` marker and metadata blocks, then decodes
+//! each frame (CONSTANT, VERBATIM, FIXED and LPC subframes with Rice coded
+//! residuals) checking the frame header CRC-8 and frame CRC-16.  Streams may
+//! have 1 to 8 channels and 4 to 32 bits per sample.
+//!
+//! Ogg FLAC (FLAC in an Ogg container) is decoded the same way, and encoded
+//! with [`Encoder::with_ogg()`].
+//!
+//! An [`Encoder`] quantizes a [`Stream`](fon::Stream) and compresses it with
+//! a compression level from 0 (fastest) to 8 (smallest), like the reference
+//! encoder, recording the MD5 signature of the audio in the STREAMINFO block.
+//! With the `threads` feature, `Encoder::with_threads()` encodes several
+//! frames at once.
+//!
+//! [`Decoder::verify()`] checks the integrity of a whole stream, like
+//! `flac -t`, reporting every corrupted frame.  Decoding
+//! [`with_resync()`](Decoder::with_resync) skips corrupted frames instead,
+//! filling in for them with silence.
+//!
+//! Other metadata blocks, like tags and pictures, are in the [`metadata`]
+//! module, which can also replace them in an existing file.
+//!
+//! Streams without metadata, like a broadcast joined partway through, are
+//! decoded from the first frame header, and single frames from containers
+//! like MP4 and Matroska are decoded with [`RawFrame`].
+//!
+//! Channels follow the FLAC channel order (following SMPTE/ITU-R
+//! recommendations):
+//!  - 1 Channel: Mono (Mono)
+//!  - 2 Channels: Stereo (Left, Right)
+//!  - 3 Channels: Surround 3.0 (Left, Right, Center)
+//!  - 4 Channels: Surround 4.0 (F.Left, F.Right, B.Left, B.Right)
+//!  - 5 Channels: Surround 5.0 (F.Left, F.Right, F.Center, B.Left, B.Right)
+//!  - 6 Channels: Surround 5.1 (F.Left, F.Right, F.Center, LFE, B.Left,
+//!    B.Right)
+//!  - 7 Channels: Surround 6.1 (F.Left, F.Right, F.Center, LFE, B.Center,
+//!    S.Left, S.Right)
+//!  - 8 Channels: Surround 7.1 (F.Left, F.Right, F.Center, LFE, B.Left,
+//!    B.Right, S.Left, S.Right)
+//!
+//! If the [`Frame`](fon::Frame) type has a different channel count than the
+//! stream, channels are up/down-mixed by speaker position with
+//! [`raw_audio::Mixer`].
+
+mod bits;
+mod crc;
+mod decoder;
+mod encoder;
+mod error;
+mod frame;
+mod lpc;
+mod md5;
+pub mod metadata;
+mod ogg;
+mod verify;
+
+pub use decoder::{Decoder, RawFrame};
+pub use encoder::Encoder;
+pub use error::{Error, Result};
+pub use metadata::StreamInfo;
+pub use verify::{Corruption, Damage, Report};
+
 #[cfg(test)]
 mod tests {
     #[test]
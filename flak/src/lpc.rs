@@ -0,0 +1,180 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Linear prediction analysis for the encoder.
+
+use std::f64::consts::PI;
+
+/// Maximum LPC order allowed by FLAC.
+pub(crate) const MAX_ORDER: usize = 32;
+
+/// Quantized linear predictor.
+#[derive(Clone, Debug)]
+pub(crate) struct Predictor {
+    /// Coefficients, the first applying to the previous sample.
+    pub(crate) coefficients: Vec<i64>,
+    /// Bits of precision of each coefficient.
+    pub(crate) precision: u32,
+    /// Right shift of the prediction.
+    pub(crate) shift: u32,
+}
+
+/// Predictor coefficients of every order up to the maximum, with the
+/// prediction error of each.
+pub(crate) struct Analysis {
+    // Row `order - 1` holds the `order` coefficients of that order.
+    coefficients: Vec<[f64; MAX_ORDER]>,
+    errors: Vec<f64>,
+}
+
+impl Analysis {
+    /// Analyze `samples`, returning `None` if they're all silent or too
+    /// few for prediction.
+    pub(crate) fn new(samples: &[i64], max_order: usize) -> Option<Self> {
+        let max_order = max_order.min(samples.len() - 1);
+        let autocorrelation = autocorrelation(samples, max_order);
+        if max_order == 0 || autocorrelation[0] == 0.0 {
+            return None;
+        }
+        Some(levinson(&autocorrelation))
+    }
+
+    /// Get the highest order analyzed.
+    pub(crate) fn max_order(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Estimate the best order for `len` samples of `bits` bits, balancing
+    /// the residual size against the size of the warm-up and coefficients.
+    pub(crate) fn best_order(
+        &self,
+        len: usize,
+        bits: u32,
+        precision: u32,
+    ) -> usize {
+        let scale = 0.5 / len as f64;
+        let mut best = (f64::INFINITY, 1);
+        for (i, error) in self.errors.iter().enumerate() {
+            let order = i + 1;
+            let per_sample = if *error > 0.0 {
+                (0.5 * (error * scale).log2()).max(0.0)
+            } else {
+                0.0
+            };
+            let total = per_sample * (len - order) as f64
+                + (order as u32 * (bits + precision)) as f64;
+            if total < best.0 {
+                best = (total, order);
+            }
+        }
+        best.1
+    }
+
+    /// Quantize the coefficients of an order to `precision` bits, returning
+    /// `None` if they can't be represented.
+    pub(crate) fn quantize(
+        &self,
+        order: usize,
+        precision: u32,
+    ) -> Option<Predictor> {
+        let coefficients = &self.coefficients[order - 1][..order];
+        let max = coefficients.iter().fold(0.0f64, |max, c| max.max(c.abs()));
+        if max <= 0.0 || !max.is_finite() {
+            return None;
+        }
+        // One bit is for the sign.
+        let shift = precision as i32 - 1 - (max.log2().floor() as i32 + 1);
+        if shift < 0 {
+            return None;
+        }
+        let shift = shift.min(15) as u32;
+        let limit = 1i64 << (precision - 1);
+        let mut error = 0.0;
+        let quantized = coefficients
+            .iter()
+            .map(|c| {
+                error += c * f64::from(1u32 << shift);
+                let q = (error.round() as i64).clamp(-limit, limit - 1);
+                error -= q as f64;
+                q
+            })
+            .collect();
+        Some(Predictor {
+            coefficients: quantized,
+            precision,
+            shift,
+        })
+    }
+}
+
+// Autocorrelation of the Tukey (0.5) windowed samples, for lags up to
+// `max_order`.
+fn autocorrelation(samples: &[i64], max_order: usize) -> Vec<f64> {
+    let len = samples.len();
+    let taper = (len / 4).max(1);
+    let windowed: Vec<f64> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let edge = i.min(len - 1 - i);
+            let window = if edge < taper {
+                0.5 - 0.5 * (PI * edge as f64 / taper as f64).cos()
+            } else {
+                1.0
+            };
+            *sample as f64 * window
+        })
+        .collect();
+    (0..=max_order)
+        .map(|lag| {
+            windowed[lag..]
+                .iter()
+                .zip(&windowed)
+                .map(|(a, b)| a * b)
+                .sum()
+        })
+        .collect()
+}
+
+// Levinson-Durbin recursion, finding predictor coefficients of each order
+// from the autocorrelation.
+fn levinson(autocorrelation: &[f64]) -> Analysis {
+    let max_order = autocorrelation.len() - 1;
+    let mut lpc = [0.0; MAX_ORDER];
+    let mut error = autocorrelation[0];
+    let mut analysis = Analysis {
+        coefficients: Vec::with_capacity(max_order),
+        errors: Vec::with_capacity(max_order),
+    };
+    for i in 0..max_order {
+        let mut reflection = -autocorrelation[i + 1];
+        for j in 0..i {
+            reflection -= lpc[j] * autocorrelation[i - j];
+        }
+        reflection /= error;
+        lpc[i] = reflection;
+        for j in 0..i / 2 {
+            let tmp = lpc[j];
+            lpc[j] += reflection * lpc[i - 1 - j];
+            lpc[i - 1 - j] += reflection * tmp;
+        }
+        if i % 2 == 1 {
+            lpc[i / 2] += lpc[i / 2] * reflection;
+        }
+        error *= 1.0 - reflection * reflection;
+        let mut coefficients = [0.0; MAX_ORDER];
+        for (c, l) in coefficients.iter_mut().zip(&lpc[..=i]) {
+            *c = -l;
+        }
+        analysis.coefficients.push(coefficients);
+        analysis.errors.push(error);
+        if error <= 0.0 {
+            break;
+        }
+    }
+    analysis
+}
@@ -0,0 +1,117 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! MD5 (RFC 1321) signature of the unencoded audio.
+
+/// Per-round shift amounts.
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4,
+    11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6,
+    10, 15, 21,
+];
+
+/// Per-round constants (integer part of 2³² × abs(sin(i + 1))).
+const CONSTANTS: [u32; 64] = [
+    0xD76AA478, 0xE8C7B756, 0x242070DB, 0xC1BDCEEE, 0xF57C0FAF, 0x4787C62A,
+    0xA8304613, 0xFD469501, 0x698098D8, 0x8B44F7AF, 0xFFFF5BB1, 0x895CD7BE,
+    0x6B901122, 0xFD987193, 0xA679438E, 0x49B40821, 0xF61E2562, 0xC040B340,
+    0x265E5A51, 0xE9B6C7AA, 0xD62F105D, 0x02441453, 0xD8A1E681, 0xE7D3FBC8,
+    0x21E1CDE6, 0xC33707D6, 0xF4D50D87, 0x455A14ED, 0xA9E3E905, 0xFCEFA3F8,
+    0x676F02D9, 0x8D2A4C8A, 0xFFFA3942, 0x8771F681, 0x6D9D6122, 0xFDE5380C,
+    0xA4BEEA44, 0x4BDECFA9, 0xF6BB4B60, 0xBEBFBC70, 0x289B7EC6, 0xEAA127FA,
+    0xD4EF3085, 0x04881D05, 0xD9D4D039, 0xE6DB99E5, 0x1FA27CF8, 0xC4AC5665,
+    0xF4292244, 0x432AFF97, 0xAB9423A7, 0xFC93A039, 0x655B59C3, 0x8F0CCC92,
+    0xFFEFF47D, 0x85845DD1, 0x6FA87E4F, 0xFE2CE6E0, 0xA3014314, 0x4E0811A1,
+    0xF7537E82, 0xBD3AF235, 0x2AD7D2BB, 0xEB86D391,
+];
+
+/// Incremental MD5 hasher.
+#[derive(Clone)]
+pub(crate) struct Md5 {
+    state: [u32; 4],
+    // Bytes of an incomplete block.
+    block: [u8; 64],
+    // Total number of bytes hashed.
+    len: u64,
+}
+
+impl Md5 {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476],
+            block: [0; 64],
+            len: 0,
+        }
+    }
+
+    /// Hash more bytes.
+    pub(crate) fn update(&mut self, mut bytes: &[u8]) {
+        let offset = (self.len % 64) as usize;
+        self.len += bytes.len() as u64;
+        if offset != 0 {
+            let count = bytes.len().min(64 - offset);
+            self.block[offset..offset + count].copy_from_slice(&bytes[..count]);
+            bytes = &bytes[count..];
+            if offset + count < 64 {
+                return;
+            }
+            let block = self.block;
+            self.compress(&block);
+        }
+        let mut blocks = bytes.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+    }
+
+    /// Finish hashing, returning the signature.
+    pub(crate) fn finish(mut self) -> [u8; 16] {
+        let bits = self.len.wrapping_mul(8);
+        // Pad with a 1 bit and 0 bits up to 8 bytes before a block boundary.
+        let zeros = (119 - self.len % 64) % 64;
+        self.update(&[0x80]);
+        self.update(&[0; 64][..zeros as usize]);
+        self.update(&bits.to_le_bytes());
+        let mut signature = [0; 16];
+        for (bytes, word) in signature.chunks_exact_mut(4).zip(&self.state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        signature
+    }
+
+    // Process one 64-byte block.
+    fn compress(&mut self, block: &[u8]) {
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word =
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => (b & c | !b & d, i),
+                1 => (d & b | !d & c, (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), 7 * i % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(CONSTANTS[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i]));
+        }
+        for (state, value) in self.state.iter_mut().zip(&[a, b, c, d]) {
+            *state = state.wrapping_add(*value);
+        }
+    }
+}
@@ -0,0 +1,648 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Metadata blocks.
+//!
+//! The blocks of a stream are available from
+//! [`Decoder::metadata()`](crate::Decoder::metadata), written by the
+//! [`Encoder`](crate::Encoder) (see
+//! [`Encoder::with_metadata()`](crate::Encoder::with_metadata)), and can be
+//! replaced in an existing file with [`rewrite()`].
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::bits::{BitWriter, Bits};
+use crate::{Error, Result};
+
+/// Block type of STREAMINFO.
+const STREAMINFO: u32 = 0;
+const PADDING: u8 = 1;
+const APPLICATION: u8 = 2;
+const SEEKTABLE: u8 = 3;
+const VORBIS_COMMENT: u8 = 4;
+const CUESHEET: u8 = 5;
+const PICTURE: u8 = 6;
+
+/// Largest metadata block body, in bytes.
+const MAX_LEN: usize = (1 << 24) - 1;
+
+/// Padding added when the metadata outgrows the space before the audio.
+pub const DEFAULT_PADDING: usize = 8192;
+
+/// Stream information (the STREAMINFO metadata block).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// Minimum block size (in samples) used in the stream.
+    pub min_block_size: u16,
+    /// Maximum block size (in samples) used in the stream.
+    pub max_block_size: u16,
+    /// Minimum frame size (in bytes) used in the stream, 0 if unknown.
+    pub min_frame_size: u32,
+    /// Maximum frame size (in bytes) used in the stream, 0 if unknown.
+    pub max_frame_size: u32,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Number of channels (1 to 8).
+    pub channels: u8,
+    /// Bits per sample (4 to 32).
+    pub bits_per_sample: u8,
+    /// Total number of samples (for each channel), 0 if unknown.
+    pub samples: u64,
+    /// MD5 signature of the unencoded audio, all zeros if unknown.
+    pub md5: [u8; 16],
+}
+
+impl StreamInfo {
+    // Read the body of a STREAMINFO block.
+    fn read<R: Read>(bits: &mut Bits<R>) -> Result<Self> {
+        let mut info = StreamInfo {
+            min_block_size: bits.read(16)? as u16,
+            max_block_size: bits.read(16)? as u16,
+            min_frame_size: bits.read(24)?,
+            max_frame_size: bits.read(24)?,
+            sample_rate: bits.read(20)?,
+            channels: bits.read(3)? as u8 + 1,
+            bits_per_sample: bits.read(5)? as u8 + 1,
+            samples: bits.read_u64(36)?,
+            md5: [0; 16],
+        };
+        for byte in info.md5.iter_mut() {
+            *byte = bits.read(8)? as u8;
+        }
+        if info.min_block_size < 16
+            || info.max_block_size < info.min_block_size
+            || info.sample_rate == 0
+            || info.bits_per_sample < 4
+        {
+            return Err(Error::StreamInfo);
+        }
+        Ok(info)
+    }
+
+    // Write the body of a STREAMINFO block.
+    fn write(&self, writer: &mut BitWriter) {
+        writer.write(self.min_block_size.into(), 16);
+        writer.write(self.max_block_size.into(), 16);
+        writer.write(self.min_frame_size, 24);
+        writer.write(self.max_frame_size, 24);
+        writer.write(self.sample_rate, 20);
+        writer.write(u32::from(self.channels) - 1, 3);
+        writer.write(u32::from(self.bits_per_sample) - 1, 5);
+        writer.write_u64(self.samples, 36);
+        for byte in self.md5.iter() {
+            writer.write((*byte).into(), 8);
+        }
+    }
+}
+
+/// A metadata block, other than STREAMINFO.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    /// Space reserved for metadata to grow into, in bytes.
+    Padding(usize),
+    /// Data for a third party application.
+    Application(Application),
+    /// Points to seek to in the stream.
+    SeekTable(Vec<SeekPoint>),
+    /// Tags (Vorbis comments).
+    VorbisComment(VorbisComment),
+    /// Track and index points of a CD or other media.
+    CueSheet(CueSheet),
+    /// A picture, like cover art.
+    Picture(Picture),
+    /// A block of a reserved type, kept as is.
+    Unknown(u8, Vec<u8>),
+}
+
+/// APPLICATION block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Application {
+    /// Registered application ID.
+    pub id: [u8; 4],
+    /// Application data.
+    pub data: Vec<u8>,
+}
+
+/// A point of a SEEKTABLE block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SeekPoint {
+    /// Sample number of the first sample in the target frame, or
+    /// [`SeekPoint::PLACEHOLDER`].
+    pub sample: u64,
+    /// Offset in bytes from the first frame to the target frame.
+    pub offset: u64,
+    /// Number of samples in the target frame.
+    pub samples: u16,
+}
+
+impl SeekPoint {
+    /// Sample number of a placeholder point.
+    pub const PLACEHOLDER: u64 = u64::MAX;
+}
+
+/// VORBIS_COMMENT block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VorbisComment {
+    /// Name and version of the encoder.
+    pub vendor: String,
+    /// Field names (like `TITLE` or `ARTIST`) and values, in order.  Field
+    /// names are case-insensitive, and may repeat.
+    pub comments: Vec<(String, String)>,
+}
+
+impl VorbisComment {
+    /// Create tags with no comments.
+    pub fn new<S: Into<String>>(vendor: S) -> Self {
+        Self {
+            vendor: vendor.into(),
+            comments: Vec::new(),
+        }
+    }
+
+    /// Get the values of a field.
+    pub fn get<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.comments
+            .iter()
+            .filter(move |(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Add a value to a field.
+    pub fn add<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        self.comments.push((name.into(), value.into()));
+    }
+
+    /// Replace the values of a field with one value.
+    pub fn set<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        let name = name.into();
+        self.remove(&name);
+        self.comments.push((name, value.into()));
+    }
+
+    /// Remove the values of a field.
+    pub fn remove(&mut self, name: &str) {
+        self.comments
+            .retain(|(field, _)| !field.eq_ignore_ascii_case(name));
+    }
+}
+
+/// CUESHEET block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CueSheet {
+    /// Media catalog number (up to 128 ASCII characters).
+    pub catalog: String,
+    /// Number of lead-in samples (for CDs).
+    pub lead_in: u64,
+    /// Whether the cue sheet is for a CD.
+    pub cd: bool,
+    /// Tracks, ending with the lead-out track.
+    pub tracks: Vec<CueTrack>,
+}
+
+/// A track of a CUESHEET block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CueTrack {
+    /// Offset of the track in samples, from the start of the stream.
+    pub offset: u64,
+    /// Track number (170 or 255 for the lead-out track).
+    pub number: u8,
+    /// International Standard Recording Code (12 ASCII characters), or
+    /// empty.
+    pub isrc: String,
+    /// Whether the track is audio (rather than data).
+    pub audio: bool,
+    /// Whether the track has pre-emphasis.
+    pub pre_emphasis: bool,
+    /// Index points of the track.
+    pub indices: Vec<CueIndex>,
+}
+
+/// An index point of a CUESHEET track.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CueIndex {
+    /// Offset of the index point in samples, from the start of the track.
+    pub offset: u64,
+    /// Index point number.
+    pub number: u8,
+}
+
+/// PICTURE block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Picture {
+    /// Picture type as defined for ID3v2 APIC frames (3 for the front
+    /// cover).
+    pub picture_type: u32,
+    /// MIME type (like `image/png`), or `-->` if `data` is a URL.
+    pub mime: String,
+    /// Description of the picture.
+    pub description: String,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Color depth in bits per pixel.
+    pub depth: u32,
+    /// Number of colors for indexed color pictures, otherwise 0.
+    pub colors: u32,
+    /// The encoded picture.
+    pub data: Vec<u8>,
+}
+
+// Reads the fields of a metadata block body.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.0.len() {
+            return Err(Error::Metadata);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    // Read a big endian integer of `len` bytes.
+    fn int(&mut self, len: usize) -> Result<u64> {
+        let bytes = self.bytes(len)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |int, byte| int << 8 | u64::from(*byte)))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(self.int(4)? as u32)
+    }
+
+    fn u32_le(&mut self) -> Result<u32> {
+        Ok(self.u32()?.swap_bytes())
+    }
+
+    // Read a UTF-8 string of `len` bytes.
+    fn string(&mut self, len: usize) -> Result<String> {
+        let bytes = self.bytes(len)?.to_vec();
+        String::from_utf8(bytes).map_err(|_| Error::Metadata)
+    }
+
+    // Read an ASCII string padded with NUL bytes to `len` bytes.
+    fn padded(&mut self, len: usize) -> Result<String> {
+        let bytes = self.bytes(len)?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+        if !bytes[..end].is_ascii() {
+            return Err(Error::Metadata);
+        }
+        Ok(bytes[..end].iter().map(|b| char::from(*b)).collect())
+    }
+}
+
+impl Block {
+    // Parse the body of a block.
+    fn parse(kind: u8, data: Vec<u8>) -> Result<Self> {
+        let mut fields = Fields(&data);
+        let block = match kind {
+            PADDING => Block::Padding(data.len()),
+            APPLICATION => Block::Application(Application {
+                id: (fields.u32()?).to_be_bytes(),
+                data: fields.0.to_vec(),
+            }),
+            SEEKTABLE => {
+                if data.len() % 18 != 0 {
+                    return Err(Error::Metadata);
+                }
+                let mut points = Vec::with_capacity(data.len() / 18);
+                while !fields.0.is_empty() {
+                    points.push(SeekPoint {
+                        sample: fields.int(8)?,
+                        offset: fields.int(8)?,
+                        samples: fields.int(2)? as u16,
+                    });
+                }
+                Block::SeekTable(points)
+            }
+            VORBIS_COMMENT => {
+                let len = fields.u32_le()?;
+                let vendor = fields.string(len as usize)?;
+                let count = fields.u32_le()?;
+                let mut comments = Vec::new();
+                for _ in 0..count {
+                    let len = fields.u32_le()?;
+                    let comment = fields.string(len as usize)?;
+                    // Comments without `=` are invalid, but occur in the
+                    // wild, so they're skipped.
+                    if let Some((name, value)) = comment.split_once('=') {
+                        comments.push((name.to_string(), value.to_string()));
+                    }
+                }
+                Block::VorbisComment(VorbisComment { vendor, comments })
+            }
+            CUESHEET => {
+                let catalog = fields.padded(128)?;
+                let lead_in = fields.int(8)?;
+                let cd = fields.bytes(259)?[0] & 0x80 != 0;
+                let mut tracks = Vec::new();
+                for _ in 0..fields.int(1)? {
+                    let offset = fields.int(8)?;
+                    let number = fields.int(1)? as u8;
+                    let isrc = fields.padded(12)?;
+                    let flags = fields.bytes(14)?[0];
+                    let mut indices = Vec::new();
+                    for _ in 0..fields.int(1)? {
+                        indices.push(CueIndex {
+                            offset: fields.int(8)?,
+                            number: fields.int(1)? as u8,
+                        });
+                        fields.bytes(3)?;
+                    }
+                    tracks.push(CueTrack {
+                        offset,
+                        number,
+                        isrc,
+                        audio: flags & 0x80 == 0,
+                        pre_emphasis: flags & 0x40 != 0,
+                        indices,
+                    });
+                }
+                Block::CueSheet(CueSheet {
+                    catalog,
+                    lead_in,
+                    cd,
+                    tracks,
+                })
+            }
+            PICTURE => {
+                let picture_type = fields.u32()?;
+                let len = fields.u32()?;
+                let mime = fields.string(len as usize)?;
+                let len = fields.u32()?;
+                let description = fields.string(len as usize)?;
+                let width = fields.u32()?;
+                let height = fields.u32()?;
+                let depth = fields.u32()?;
+                let colors = fields.u32()?;
+                let len = fields.u32()?;
+                let data = fields.bytes(len as usize)?.to_vec();
+                Block::Picture(Picture {
+                    picture_type,
+                    mime,
+                    description,
+                    width,
+                    height,
+                    depth,
+                    colors,
+                    data,
+                })
+            }
+            _ => return Ok(Block::Unknown(kind, data)),
+        };
+        if !fields.0.is_empty() && kind != PADDING && kind != APPLICATION {
+            return Err(Error::Metadata);
+        }
+        Ok(block)
+    }
+
+    // Get the block type.
+    fn kind(&self) -> u8 {
+        match self {
+            Block::Padding(_) => PADDING,
+            Block::Application(_) => APPLICATION,
+            Block::SeekTable(_) => SEEKTABLE,
+            Block::VorbisComment(_) => VORBIS_COMMENT,
+            Block::CueSheet(_) => CUESHEET,
+            Block::Picture(_) => PICTURE,
+            Block::Unknown(kind, _) => *kind,
+        }
+    }
+
+    // Write the body of the block.
+    fn write(&self, writer: &mut BitWriter) {
+        let bytes = |writer: &mut BitWriter, bytes: &[u8]| {
+            for byte in bytes {
+                writer.write((*byte).into(), 8);
+            }
+        };
+        // Write a string with its length.
+        let string = |writer: &mut BitWriter, string: &str, le: bool| {
+            let len = string.len() as u32;
+            writer.write(if le { len.swap_bytes() } else { len }, 32);
+            bytes(writer, string.as_bytes());
+        };
+        // Write an ASCII string padded with NUL bytes to `len` bytes.
+        let padded = |writer: &mut BitWriter, string: &str, len: usize| {
+            let string = &string.as_bytes()[..string.len().min(len)];
+            bytes(writer, string);
+            bytes(writer, &vec![0; len - string.len()]);
+        };
+        match self {
+            Block::Padding(len) => bytes(writer, &vec![0; *len]),
+            Block::Application(application) => {
+                bytes(writer, &application.id);
+                bytes(writer, &application.data);
+            }
+            Block::SeekTable(points) => {
+                for point in points {
+                    writer.write_u64(point.sample, 64);
+                    writer.write_u64(point.offset, 64);
+                    writer.write(point.samples.into(), 16);
+                }
+            }
+            Block::VorbisComment(tags) => {
+                string(writer, &tags.vendor, true);
+                writer.write((tags.comments.len() as u32).swap_bytes(), 32);
+                for (name, value) in &tags.comments {
+                    string(writer, &format!("{}={}", name, value), true);
+                }
+            }
+            Block::CueSheet(cue) => {
+                padded(writer, &cue.catalog, 128);
+                writer.write_u64(cue.lead_in, 64);
+                writer.write(u32::from(cue.cd) << 7, 8);
+                bytes(writer, &[0; 258]);
+                writer.write(cue.tracks.len() as u32, 8);
+                for track in &cue.tracks {
+                    writer.write_u64(track.offset, 64);
+                    writer.write(track.number.into(), 8);
+                    padded(writer, &track.isrc, 12);
+                    writer.write(u32::from(!track.audio), 1);
+                    writer.write(track.pre_emphasis.into(), 1);
+                    writer.write(0, 6);
+                    bytes(writer, &[0; 13]);
+                    writer.write(track.indices.len() as u32, 8);
+                    for index in &track.indices {
+                        writer.write_u64(index.offset, 64);
+                        writer.write(index.number.into(), 8);
+                        bytes(writer, &[0; 3]);
+                    }
+                }
+            }
+            Block::Picture(picture) => {
+                writer.write(picture.picture_type, 32);
+                string(writer, &picture.mime, false);
+                string(writer, &picture.description, false);
+                writer.write(picture.width, 32);
+                writer.write(picture.height, 32);
+                writer.write(picture.depth, 32);
+                writer.write(picture.colors, 32);
+                writer.write(picture.data.len() as u32, 32);
+                bytes(writer, &picture.data);
+            }
+            Block::Unknown(_, data) => bytes(writer, data),
+        }
+    }
+
+    /// Get the length of the block's body in bytes.
+    pub fn len(&self) -> usize {
+        let mut writer = BitWriter::new();
+        self.write(&mut writer);
+        writer.bytes().len()
+    }
+
+    /// Check whether the block's body is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Parse metadata blocks kept outside of a FLAC stream, returning the stream
+/// information and other blocks, as in an MP4 `dfLa` box (the blocks) or
+/// Matroska `CodecPrivate` element (the `fLaC` marker, then the blocks).
+/// The frames of such a stream can be decoded with
+/// [`RawFrame::parse()`](crate::RawFrame::parse).
+pub fn parse(bytes: &[u8]) -> Result<(StreamInfo, Vec<Block>)> {
+    let blocks = bytes.strip_prefix(b"fLaC").unwrap_or(bytes);
+    read(&mut Bits::new(blocks))
+}
+
+/// Read the metadata blocks after the `fLaC` marker, returning the stream
+/// information and other blocks.
+pub(crate) fn read<R: Read>(
+    bits: &mut Bits<R>,
+) -> Result<(StreamInfo, Vec<Block>)> {
+    let mut info = None;
+    let mut blocks = Vec::new();
+    loop {
+        let last = bits.read(1)? == 1;
+        let kind = bits.read(7)?;
+        let length = bits.read(24)?;
+        match kind {
+            STREAMINFO if info.is_none() && length == 34 => {
+                info = Some(StreamInfo::read(bits)?);
+            }
+            // STREAMINFO must come first, and only once.
+            _ if info.is_none() => return Err(Error::StreamInfo),
+            STREAMINFO => return Err(Error::StreamInfo),
+            // Frame sync code, which can't be a metadata block header.
+            127 => return Err(Error::Metadata),
+            _ => {
+                let mut data = vec![0; length as usize];
+                for byte in data.iter_mut() {
+                    *byte = bits.read(8)? as u8;
+                }
+                blocks.push(Block::parse(kind as u8, data)?);
+            }
+        }
+        if last {
+            return Ok((info.unwrap(), blocks));
+        }
+    }
+}
+
+/// Write the `fLaC` marker and metadata blocks.
+///
+/// # Panics
+/// If a block is too large (16 MiB or more).
+pub(crate) fn write(info: &StreamInfo, blocks: &[Block]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    for byte in b"fLaC" {
+        writer.write((*byte).into(), 8);
+    }
+    writer.write(blocks.is_empty().into(), 1);
+    writer.write(STREAMINFO, 7);
+    writer.write(34, 24);
+    info.write(&mut writer);
+    for (i, block) in blocks.iter().enumerate() {
+        let len = block.len();
+        assert!(len <= MAX_LEN, "Metadata block is too large");
+        writer.write((i == blocks.len() - 1).into(), 1);
+        writer.write(block.kind().into(), 7);
+        writer.write(len as u32, 24);
+        block.write(&mut writer);
+    }
+    writer.into_bytes()
+}
+
+/// Replace the metadata blocks (other than STREAMINFO) of a FLAC file.
+///
+/// Padding blocks in `blocks` are ignored.  If the new blocks fit in the
+/// space of the old blocks, they're written in place with the rest of the
+/// space as padding.  Otherwise, the audio is moved to make room for the new
+/// blocks and [`DEFAULT_PADDING`] bytes of padding.  Returns whether the
+/// audio was moved.  Ogg FLAC files aren't supported.
+pub fn rewrite<F: Read + Write + Seek>(
+    mut file: F,
+    blocks: &[Block],
+) -> Result<bool> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut marker = [0; 4];
+    file.read_exact(&mut marker)?;
+    if &marker != b"fLaC" {
+        return Err(Error::NotFlac);
+    }
+    let (info, _) = read(&mut Bits::new(&mut file))?;
+    // Find the end of the old metadata.
+    let mut end = 4;
+    loop {
+        file.seek(SeekFrom::Start(end))?;
+        let mut header = [0; 4];
+        file.read_exact(&mut header)?;
+        end += 4 + u64::from(u32::from_be_bytes(header) & 0xFF_FFFF);
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    let mut blocks: Vec<Block> = blocks
+        .iter()
+        .filter(|block| !matches!(block, Block::Padding(_)))
+        .cloned()
+        .collect();
+    if blocks.iter().any(|block| block.len() > MAX_LEN) {
+        return Err(Error::Metadata);
+    }
+    let len = write(&info, &blocks).len() as u64;
+    let moved = len != end && len + 4 > end;
+    if moved {
+        blocks.push(Block::Padding(DEFAULT_PADDING));
+    } else if len != end {
+        blocks.push(Block::Padding((end - len - 4) as usize));
+    }
+    let metadata = write(&info, &blocks);
+    if moved {
+        shift(&mut file, end, metadata.len() as u64 - end)?;
+    }
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&metadata)?;
+    file.flush()?;
+    Ok(moved)
+}
+
+// Move everything from `start` to the end of the file `by` bytes later.
+fn shift<F: Read + Write + Seek>(
+    file: &mut F,
+    start: u64,
+    by: u64,
+) -> Result<()> {
+    let mut pos = file.seek(SeekFrom::End(0))?;
+    let mut buffer = vec![0; 1 << 16];
+    while pos > start {
+        let len = (pos - start).min(buffer.len() as u64);
+        pos -= len;
+        let buffer = &mut buffer[..len as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(buffer)?;
+        file.seek(SeekFrom::Start(pos + by))?;
+        file.write_all(buffer)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,188 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Ogg FLAC encapsulation.
+//!
+//! The first Ogg packet is `\x7FFLAC`, the mapping version (1.0), the number
+//! of header packets that follow, the `fLaC` marker and the STREAMINFO block.
+//! Each other metadata block is a header packet of its own, and each FLAC
+//! frame is a packet, with the number of samples up to its end as the
+//! granule position.
+
+use std::io::{self, Chain, Cursor, Read, Seek, SeekFrom};
+
+use ogg_page::{Packer, Unpacker};
+
+use crate::{Error, Result};
+
+/// Start of the first packet, before the header packet count.
+const MAGIC: &[u8] = b"\x7FFLAC\x01\x00";
+
+/// Length of the first packet up to the STREAMINFO block.
+const PREFIX: usize = 13;
+
+/// A native FLAC stream, or one in Ogg, after the `fLaC` or `OggS` marker.
+pub(crate) enum Source<R: Read> {
+    Flac(R),
+    Ogg(OggReader<Chain<Cursor<[u8; 4]>, R>>),
+}
+
+impl<R: Read> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::Flac(reader) => reader.read(buf),
+            Source::Ogg(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for Source<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Source::Flac(reader) => reader.seek(pos),
+            Source::Ogg(_) => {
+                Err(io::Error::other("Can't seek in Ogg FLAC streams"))
+            }
+        }
+    }
+}
+
+/// Reads the FLAC stream (after the `fLaC` marker) out of Ogg packets.
+pub(crate) struct OggReader<R: Read> {
+    unpacker: Unpacker<R>,
+    packet: Vec<u8>,
+    // Index of the next byte in `packet`.
+    pos: usize,
+}
+
+impl<R: Read> OggReader<R> {
+    /// Read the first packet, checking it's Ogg FLAC.
+    pub(crate) fn new(reader: R) -> Result<Self> {
+        let mut unpacker = Unpacker::new(reader);
+        let packet = unpacker.packet()?.ok_or(Error::NotFlac)?;
+        if packet.len() < PREFIX
+            || !packet.starts_with(MAGIC)
+            || &packet[9..PREFIX] != b"fLaC"
+        {
+            return Err(Error::NotFlac);
+        }
+        Ok(Self {
+            unpacker,
+            packet,
+            pos: PREFIX,
+        })
+    }
+}
+
+impl<R: Read> Read for OggReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.packet.len() {
+            match self.unpacker.packet()? {
+                Some(packet) => self.packet = packet,
+                None => return Ok(0),
+            }
+            self.pos = 0;
+        }
+        let len = buf.len().min(self.packet.len() - self.pos);
+        buf[..len].copy_from_slice(&self.packet[self.pos..][..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Lays out the FLAC stream from the encoder into Ogg pages.
+pub(crate) struct OggWriter {
+    serial: u32,
+    packer: Packer,
+    // The last packet, held back until it's known whether it ends the
+    // stream, with its granule position.
+    last: Option<(Vec<u8>, u64)>,
+    // Number of samples in FLAC frames so far.
+    samples: u64,
+}
+
+impl OggWriter {
+    pub(crate) fn new(serial: u32) -> Self {
+        Self {
+            serial,
+            packer: Packer::new(serial),
+            last: None,
+            samples: 0,
+        }
+    }
+
+    /// Get the pages for the metadata (`fLaC` marker and blocks), except
+    /// the last header packet.
+    pub(crate) fn header(&mut self, metadata: &[u8]) -> Vec<u8> {
+        let mut packets = packets(metadata);
+        self.last = packets.pop().map(|packet| (packet, 0));
+        for packet in packets {
+            self.packer.packet(&packet, 0, false);
+        }
+        self.pages()
+    }
+
+    /// Get the pages finished by adding a FLAC frame of `samples` samples.
+    pub(crate) fn frame(&mut self, frame: &[u8], samples: u64) -> Vec<u8> {
+        if let Some((packet, granule)) = self.last.take() {
+            self.packer.packet(&packet, granule, false);
+            // Audio starts on a new page.
+            if self.samples == 0 {
+                self.packer.flush();
+            }
+        }
+        self.samples += samples;
+        self.last = Some((frame.to_vec(), self.samples));
+        self.pages()
+    }
+
+    /// Get the rest of the pages, ending the stream.
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        if let Some((packet, granule)) = self.last.take() {
+            self.packer.packet(&packet, granule, true);
+        }
+        self.pages()
+    }
+
+    /// Get the header pages again for updated metadata of the same length,
+    /// laid out as they were first written.
+    pub(crate) fn rewrite(&self, metadata: &[u8]) -> Vec<u8> {
+        let mut packer = Packer::new(self.serial);
+        let packets = packets(metadata);
+        for (i, packet) in packets.iter().enumerate() {
+            let end = self.samples == 0 && i == packets.len() - 1;
+            packer.packet(packet, 0, end);
+        }
+        packer.flush();
+        std::iter::from_fn(|| packer.take()).flatten().collect()
+    }
+
+    // Take the finished pages.
+    fn pages(&mut self) -> Vec<u8> {
+        let packer = &mut self.packer;
+        std::iter::from_fn(|| packer.take()).flatten().collect()
+    }
+}
+
+// Split the metadata into header packets.
+fn packets(metadata: &[u8]) -> Vec<Vec<u8>> {
+    let mut blocks = Vec::new();
+    let mut start = 4;
+    while start < metadata.len() {
+        let header = &metadata[start..start + 4];
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+        blocks.push(&metadata[start..start + 4 + len as usize]);
+        start += 4 + len as usize;
+    }
+    let mut first = MAGIC.to_vec();
+    first.extend_from_slice(&(blocks.len() as u16 - 1).to_be_bytes());
+    first.extend_from_slice(&metadata[..4]);
+    first.extend_from_slice(blocks[0]);
+    let mut packets = vec![first];
+    packets.extend(blocks[1..].iter().map(|block| block.to_vec()));
+    packets
+}
@@ -0,0 +1,105 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Integrity verification, like `flac -t`.
+
+use std::io::ErrorKind;
+
+use crate::md5::Md5;
+use crate::{Error, Result, StreamInfo};
+
+/// How a FLAC frame is corrupted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Damage {
+    /// The frame header is invalid, or there's no frame where one should be.
+    Header,
+    /// The frame header checksum (CRC-8) doesn't match.
+    HeaderCrc,
+    /// A subframe is invalid.
+    Subframe,
+    /// The frame checksum (CRC-16) doesn't match.
+    FrameCrc,
+    /// The stream ends partway through the frame.
+    Truncated,
+}
+
+impl Damage {
+    /// Get the damage an error shows, or return I/O errors other than the
+    /// stream ending early.
+    pub(crate) fn new(error: Error, in_header: bool) -> Result<Self> {
+        Ok(match error {
+            Error::Crc if in_header => Damage::HeaderCrc,
+            Error::Crc => Damage::FrameCrc,
+            Error::Subframe => Damage::Subframe,
+            Error::Io(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                Damage::Truncated
+            }
+            Error::Io(e) => return Err(Error::Io(e)),
+            _ => Damage::Header,
+        })
+    }
+}
+
+/// A corrupted FLAC frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Corruption {
+    /// Offset of the frame in bytes from the start of the stream (the FLAC
+    /// stream inside, for Ogg FLAC).
+    pub offset: u64,
+    /// Sample number of the first sample in the frame, if the frame header
+    /// is intact.
+    pub sample: Option<u64>,
+    /// How the frame is corrupted.
+    pub damage: Damage,
+}
+
+/// Result of verifying a stream with
+/// [`Decoder::verify()`](crate::Decoder::verify).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// Stream information from the STREAMINFO block.
+    pub info: StreamInfo,
+    /// Number of intact FLAC frames.
+    pub frames: u64,
+    /// Number of samples (for each channel) in intact FLAC frames.
+    pub samples: u64,
+    /// MD5 signature of the audio in intact FLAC frames.
+    pub md5: [u8; 16],
+    /// Corrupted FLAC frames, in stream order.
+    pub corrupted: Vec<Corruption>,
+}
+
+impl Report {
+    /// Check if the MD5 signature matches the STREAMINFO block, returning
+    /// `None` if the STREAMINFO block doesn't have one.
+    pub fn md5_matches(&self) -> Option<bool> {
+        if self.info.md5 == [0; 16] {
+            None
+        } else {
+            Some(self.md5 == self.info.md5)
+        }
+    }
+
+    /// Check if the stream passed: no frames are corrupted, and the number
+    /// of samples and MD5 signature match the STREAMINFO block (where
+    /// known).
+    pub fn is_ok(&self) -> bool {
+        self.corrupted.is_empty()
+            && (self.info.samples == 0 || self.samples == self.info.samples)
+            && self.md5_matches() != Some(false)
+    }
+}
+
+/// Add the samples of a FLAC frame (`len` samples of each channel) to an
+/// MD5 signature, interleaved as little endian integers of `bytes` bytes.
+pub(crate) fn hash(md5: &mut Md5, block: &[i64], len: usize, bytes: usize) {
+    for i in 0..len {
+        for channel in block.chunks_exact(len) {
+            md5.update(&channel[i].to_le_bytes()[..bytes]);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+// Helpers shared by the integration tests; not every test uses all of them.
+#![allow(dead_code)]
+
+use std::io::Cursor;
+
+use fon::chan::{Ch64, Channel};
+use fon::{Audio, Frame};
+
+use flak::metadata::Block;
+use flak::Encoder;
+
+// Convert a `bits`-bit integer sample to a channel, like the decoder.
+pub fn chan(sample: i64, bits: u32) -> Ch64 {
+    Ch64::new((sample as f64 + 0.5) / ((1u64 << (bits - 1)) as f64 - 0.5))
+}
+
+// Convert a channel back to a `bits`-bit integer sample.
+pub fn int(chan: Ch64, bits: u32) -> i64 {
+    (chan.to_f64() * ((1u64 << (bits - 1)) as f64 - 0.5) - 0.5).round() as i64
+}
+
+// Encode frames to a FLAC stream in memory, with the given metadata blocks.
+pub fn encode<F: Frame>(
+    frames: Vec<F>,
+    sample_rate: u32,
+    bits: u8,
+    blocks: Vec<Block>,
+) -> Vec<u8> {
+    let audio = Audio::with_frames(sample_rate, frames);
+    let mut encoder =
+        Encoder::<_, F>::new(Cursor::new(Vec::new()), sample_rate, bits)
+            .with_metadata(blocks);
+    encoder.encode(&audio).unwrap();
+    encoder.finish().unwrap().into_inner()
+}
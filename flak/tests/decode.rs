@@ -0,0 +1,78 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use fon::chan::Channel;
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::Frame;
+
+use flak::{Decoder, Error};
+
+/// 16 frames of 16-bit stereo at 44100 Hz in one FLAC frame, coded as left
+/// (CONSTANT 1000) and side (VERBATIM) channels.  Right goes from -800 to 700
+/// in steps of 100.
+const STREAM: [u8; 89] = [
+    0x66, 0x4C, 0x61, 0x43, 0x80, 0x00, 0x00, 0x22, 0x00, 0x10, 0x00, 0x10,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0A, 0xC4, 0x42, 0xF0, 0x00, 0x00,
+    0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xF8, 0x60, 0x88, 0x00, 0x0F,
+    0x9D, 0x00, 0x03, 0xE8, 0x02, 0x03, 0x84, 0x01, 0xA9, 0x00, 0xC8, 0x00,
+    0x5D, 0xC0, 0x2B, 0xC0, 0x14, 0x50, 0x09, 0x60, 0x04, 0x4C, 0x01, 0xF4,
+    0x00, 0xE1, 0x00, 0x64, 0x00, 0x2B, 0xC0, 0x12, 0xC0, 0x07, 0xD0, 0x03,
+    0x20, 0x01, 0x2C, 0x6C, 0x87,
+];
+
+// Convert back to a 16-bit integer sample.
+fn int<C: Channel>(chan: C) -> i64 {
+    (chan.to_f64() * 32767.5 - 0.5).round() as i64
+}
+
+#[test]
+fn stream_info() {
+    let decoder = Decoder::<_, Stereo64>::new(&STREAM[..]).unwrap();
+    let info = decoder.stream_info();
+    assert_eq!(decoder.sample_rate(), 44100);
+    assert_eq!(decoder.channels(), 2);
+    assert_eq!(info.bits_per_sample, 16);
+    assert_eq!(info.samples, 16);
+    assert_eq!((info.min_block_size, info.max_block_size), (16, 16));
+}
+
+#[test]
+fn left_side() {
+    let decoder = Decoder::<_, Stereo64>::new(&STREAM[..]).unwrap();
+    let frames: Vec<Stereo64> = decoder.map(Result::unwrap).collect();
+    assert_eq!(frames.len(), 16);
+    for (i, frame) in frames.iter().enumerate() {
+        let channels = frame.channels();
+        assert_eq!(int(channels[0]), 1000);
+        assert_eq!(int(channels[1]), i as i64 * 100 - 800);
+    }
+}
+
+#[test]
+fn downmix() {
+    let decoder = Decoder::<_, Mono64>::new(&STREAM[..]).unwrap();
+    let frames: Vec<Mono64> = decoder.map(Result::unwrap).collect();
+    assert_eq!(int(frames[0].channels()[0]), 142);
+    assert_eq!(int(frames[15].channels()[0]), 1202);
+}
+
+#[test]
+fn errors() {
+    let result = Decoder::<_, Stereo64>::new(&b"RIFF"[..]);
+    assert!(matches!(result, Err(Error::NotFlac)));
+
+    let mut corrupt = STREAM;
+    corrupt[60] ^= 0x10;
+    let mut decoder = Decoder::<_, Stereo64>::new(&corrupt[..]).unwrap();
+    assert!(matches!(decoder.next(), Some(Err(Error::Crc))));
+
+    let truncated = &STREAM[..70];
+    let mut decoder = Decoder::<_, Stereo64>::new(truncated).unwrap();
+    assert!(matches!(decoder.next(), Some(Err(Error::Io(_)))));
+}
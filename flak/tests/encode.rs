@@ -0,0 +1,170 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::Cursor;
+
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::{Audio, Frame};
+
+use flak::{Decoder, Encoder};
+
+use common::{chan, int};
+
+// Two tones with a little noise, spanning most of the range.
+fn music(len: usize, bits: u32) -> Vec<[i64; 2]> {
+    let amplitude = (1u64 << (bits - 2)) as f64;
+    let mut seed = 1u32;
+    (0..len)
+        .map(|i| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = f64::from(seed >> 16) / 65536.0 * 0.01;
+            let t = i as f64 / 44100.0 * std::f64::consts::TAU;
+            let left = (t * 440.0).sin() * 0.6 + (t * 660.0).sin() * 0.3;
+            let right = (t * 440.0 + 0.3).sin() * 0.6 + (t * 990.0).sin() * 0.2;
+            [
+                ((left + noise) * amplitude) as i64,
+                ((right + noise) * amplitude) as i64,
+            ]
+        })
+        .collect()
+}
+
+// Encode and decode stereo audio, checking the decoded samples match.
+fn roundtrip(samples: &[[i64; 2]], bits: u32, level: u8) -> Vec<u8> {
+    let frames: Vec<Stereo64> = samples
+        .iter()
+        .map(|[l, r]| Stereo64::new(chan(*l, bits), chan(*r, bits)))
+        .collect();
+    let audio = Audio::with_frames(44100, frames);
+    let mut encoder =
+        Encoder::<_, Stereo64>::new(Cursor::new(Vec::new()), 44100, bits as u8)
+            .with_level(level)
+            .with_metadata(Vec::new());
+    assert_eq!(encoder.encode(&audio).unwrap(), samples.len());
+    let flac = encoder.finish().unwrap().into_inner();
+
+    let decoder = Decoder::<_, Stereo64>::new(&flac[..]).unwrap();
+    let info = decoder.stream_info();
+    assert_eq!(info.samples, samples.len() as u64);
+    assert_eq!(u32::from(info.bits_per_sample), bits);
+    let decoded: Vec<[i64; 2]> = decoder
+        .map(|frame| {
+            let frame = frame.unwrap();
+            let channels = frame.channels();
+            [int(channels[0], bits), int(channels[1], bits)]
+        })
+        .collect();
+    assert_eq!(decoded, samples, "{}-bit level {}", bits, level);
+    flac
+}
+
+#[test]
+fn levels() {
+    let samples = music(20_000, 16);
+    let sizes: Vec<usize> = (0..=8)
+        .map(|level| roundtrip(&samples, 16, level).len())
+        .collect();
+    let raw = samples.len() * 4;
+    assert!(sizes.iter().all(|size| *size < raw * 2 / 3), "{:?}", sizes);
+    assert!(sizes[8] <= sizes[5] && sizes[5] < sizes[0], "{:?}", sizes);
+}
+
+#[test]
+fn bit_depths() {
+    for bits in [4, 8, 12, 20, 24, 32].iter().cloned() {
+        roundtrip(&music(5000, bits), bits, 5);
+    }
+}
+
+#[test]
+fn edge_cases() {
+    // Silence, full scale square wave, and a partial block of a few samples.
+    roundtrip(&vec![[0, 0]; 5000], 16, 5);
+    let square: Vec<[i64; 2]> = (0..5000)
+        .map(|i| {
+            if i / 50 % 2 == 0 {
+                [32767, -32768]
+            } else {
+                [-32768, 32767]
+            }
+        })
+        .collect();
+    roundtrip(&square, 16, 8);
+    roundtrip(&[[1, -1], [2, -2], [3, -3]], 16, 5);
+    // Only even samples, coded with wasted bits.
+    let even: Vec<[i64; 2]> = music(5000, 16)
+        .iter()
+        .map(|[l, r]| [l & !7, r & !1])
+        .collect();
+    roundtrip(&even, 16, 5);
+}
+
+#[test]
+fn md5() {
+    let frames: Vec<Mono64> = (0..1000)
+        .map(|i| Mono64::new(chan(i * 37 % 65536 - 32768, 16)))
+        .collect();
+    let audio = Audio::with_frames(48000, frames);
+    let mut encoder =
+        Encoder::<_, Mono64>::new(Cursor::new(Vec::new()), 48000, 16);
+    encoder.encode(&audio).unwrap();
+    let expected = [
+        0x3F, 0x99, 0x0F, 0x19, 0xED, 0xEF, 0x63, 0xBE, 0xE1, 0xFC, 0xD0, 0x94,
+        0x0A, 0xEE, 0x68, 0x7F,
+    ];
+    assert_eq!(encoder.stream_info().md5, expected);
+    let flac = encoder.finish().unwrap().into_inner();
+    let decoder = Decoder::<_, Mono64>::new(&flac[..]).unwrap();
+    assert_eq!(decoder.stream_info().md5, expected);
+    assert_eq!(decoder.stream_info().sample_rate, 48000);
+
+    // Without seeking back, the STREAMINFO block doesn't know the audio.
+    let mut encoder = Encoder::<_, Mono64>::new(Vec::new(), 48000, 16);
+    encoder.encode(&audio).unwrap();
+    let flac = encoder.into_inner().unwrap();
+    let decoder = Decoder::<_, Mono64>::new(&flac[..]).unwrap();
+    assert_eq!(decoder.stream_info().md5, [0; 16]);
+    assert_eq!(decoder.stream_info().samples, 0);
+    assert_eq!(decoder.count(), 1000);
+}
+
+#[cfg(feature = "threads")]
+#[test]
+fn threads() {
+    use flak::metadata::{Block, SeekPoint};
+
+    let frames: Vec<Stereo64> = music(100_000, 24)
+        .iter()
+        .map(|[l, r]| Stereo64::new(chan(*l, 24), chan(*r, 24)))
+        .collect();
+    let encode = |threads: usize, chunk: usize| {
+        let points = (0..10)
+            .map(|i| SeekPoint {
+                sample: i * 10_000,
+                offset: 0,
+                samples: 0,
+            })
+            .collect();
+        let mut encoder =
+            Encoder::<_, Stereo64>::new(Cursor::new(Vec::new()), 96000, 24)
+                .with_metadata(vec![Block::SeekTable(points)])
+                .with_threads(threads);
+        for frames in frames.chunks(chunk) {
+            let audio = Audio::with_frames(96000, frames.to_vec());
+            encoder.encode(&audio).unwrap();
+        }
+        encoder.finish().unwrap().into_inner()
+    };
+    let expected = encode(1, 100_000);
+    for threads in [2, 3, 8].iter().cloned() {
+        assert_eq!(encode(threads, 100_000), expected, "{}", threads);
+        assert_eq!(encode(threads, 777), expected, "{}", threads);
+    }
+}
@@ -0,0 +1,113 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use fon::mono::Mono64;
+
+use flak::metadata::Block;
+use flak::Decoder;
+
+const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/generated");
+
+// Every FLAC file in the generated directory.
+fn streams() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "flac"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn open(name: &str) -> Decoder<BufReader<File>, Mono64> {
+    let file = File::open(format!("{}/{}", DIR, name)).unwrap();
+    Decoder::new(BufReader::new(file)).unwrap()
+}
+
+#[test]
+fn md5() {
+    let paths = streams();
+    assert!(paths.len() >= 8);
+    for path in paths {
+        let file = File::open(&path).unwrap();
+        let report = Decoder::<_, Mono64>::new(BufReader::new(file))
+            .and_then(|decoder| decoder.verify())
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert!(report.is_ok(), "{}: {:?}", path.display(), report.corrupted);
+        assert_eq!(report.md5_matches(), Some(true), "{}", path.display());
+        assert_eq!(report.samples, report.info.samples, "{}", path.display());
+    }
+}
+
+#[test]
+fn stream_info() {
+    let info = |name| open(name).stream_info().clone();
+    let expected = [
+        ("bits_32.flac", 96000, 2, 32, 5184),
+        ("block_sizes.flac", 37800, 1, 16, 145_131),
+        ("channels_3.flac", 11025, 3, 12, 2880),
+        ("channels_8.flac", 12000, 8, 20, 4096),
+        ("metadata.flac", 32010, 1, 4, 12288),
+        ("small_blocks.flac", 8000, 1, 8, 33605),
+        ("subframes.flac", 44100, 2, 16, 19584),
+        ("wasted_bits.flac", 48000, 2, 24, 12288),
+    ];
+    for (name, sample_rate, channels, bits, samples) in expected {
+        let info = info(name);
+        assert_eq!(info.sample_rate, sample_rate, "{}", name);
+        assert_eq!(info.channels, channels, "{}", name);
+        assert_eq!(info.bits_per_sample, bits, "{}", name);
+        assert_eq!(info.samples, samples, "{}", name);
+    }
+}
+
+#[test]
+fn metadata() {
+    let decoder = open("metadata.flac");
+    let blocks = decoder.metadata();
+    assert_eq!(blocks.len(), 6);
+    assert!(
+        matches!(&blocks[0], Block::SeekTable(points) if points.len() == 2)
+    );
+    match &blocks[1] {
+        Block::VorbisComment(comments) => {
+            assert_eq!(comments.vendor, "flak test stream");
+            assert_eq!(comments.comments.len(), 3);
+        }
+        block => panic!("{:?}", block),
+    }
+    match &blocks[2] {
+        Block::Application(application) => {
+            assert_eq!(&application.id, b"flak");
+            assert_eq!(application.data.len(), 10);
+        }
+        block => panic!("{:?}", block),
+    }
+    match &blocks[3] {
+        Block::CueSheet(cue_sheet) => {
+            assert_eq!(cue_sheet.catalog, "1234567890123");
+            assert_eq!(cue_sheet.tracks.len(), 2);
+            assert_eq!(cue_sheet.tracks[0].isrc, "ABCDE1234567");
+            assert_eq!(cue_sheet.tracks[0].indices.len(), 2);
+            assert_eq!(cue_sheet.tracks[1].number, 255);
+        }
+        block => panic!("{:?}", block),
+    }
+    match &blocks[4] {
+        Block::Picture(picture) => {
+            assert_eq!(picture.mime, "image/png");
+            assert_eq!(picture.description, "cover");
+        }
+        block => panic!("{:?}", block),
+    }
+    assert_eq!(blocks[5], Block::Padding(100));
+    assert_eq!(decoder.count(), 12288);
+}
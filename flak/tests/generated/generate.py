@@ -0,0 +1,584 @@
+# Flak
+# Copyright © 2021 Jeron Aldaron Lau.
+#
+# Licensed under the Boost Software License, Version 1.0
+# (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+# LICENSE_BOOST_1_0.txt)
+
+"""Generate the FLAC test streams in this directory.
+
+The FLAC bitstream is written here from scratch (not with flak's encoder),
+so that decoding the files checks flak against an independent reading of
+the format.  Run `python3 generate.py` from this directory; the output is
+deterministic.
+"""
+
+import hashlib
+import math
+import random
+
+# Subframe types.
+CONSTANT = "constant"
+VERBATIM = "verbatim"
+FIXED = "fixed"
+LPC = "lpc"
+
+# Residual coding of a subframe: (partition order, method, escape), where
+# method 0 has 4-bit Rice parameters and method 1 has 5-bit ones, and
+# escape lists the partitions stored as raw bits.
+RICE = (0, 0, ())
+
+
+class Bits:
+    """Big endian bit writer."""
+
+    def __init__(self):
+        self.bytes = bytearray()
+        self.acc = 0
+        self.count = 0
+
+    def write(self, value, bits):
+        assert 0 <= value < 1 << bits or bits == 0, (value, bits)
+        for i in reversed(range(bits)):
+            self.acc = self.acc << 1 | (value >> i & 1)
+            self.count += 1
+            if self.count == 8:
+                self.bytes.append(self.acc)
+                self.acc = 0
+                self.count = 0
+
+    def signed(self, value, bits):
+        assert -(1 << (bits - 1)) <= value < 1 << (bits - 1), (value, bits)
+        self.write(value & ((1 << bits) - 1), bits)
+
+    def unary(self, zeros):
+        for _ in range(zeros):
+            self.write(0, 1)
+        self.write(1, 1)
+
+    def align(self):
+        while self.count != 0:
+            self.write(0, 1)
+
+
+def crc8(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            crc = (crc << 1 ^ 0x07) & 0xFF if crc & 0x80 else crc << 1
+    return crc
+
+
+def crc16(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = (crc << 1 ^ 0x8005) & 0xFFFF if crc & 0x8000 else crc << 1
+    return crc
+
+
+def coded_number(bits, number):
+    """Write a frame or sample number in the extended UTF-8 coding."""
+    if number < 0x80:
+        bits.write(number, 8)
+        return
+    length = 2
+    while number >= 1 << (5 * length + 1):
+        length += 1
+    bits.write((0xFF00 >> length) & 0xFF | number >> (6 * (length - 1)), 8)
+    for i in reversed(range(length - 1)):
+        bits.write(0x80 | (number >> (6 * i) & 0x3F), 8)
+
+
+def fixed_residual(samples, order):
+    residual = list(samples)
+    for _ in range(order):
+        residual = [b - a for a, b in zip(residual, residual[1:])]
+    return residual
+
+
+def lpc_residual(samples, coefs, shift):
+    order = len(coefs)
+    residual = []
+    for i in range(order, len(samples)):
+        sum_ = sum(c * samples[i - 1 - j] for j, c in enumerate(coefs))
+        residual.append(samples[i] - (sum_ >> shift))
+    return residual
+
+
+def rice_parameter(values, limit):
+    """Pick the Rice parameter that codes the values in the fewest bits."""
+    best = None
+    for k in range(limit):
+        size = sum(((v << 1 ^ v >> 63) & (2**64 - 1)) >> k for v in values)
+        size += len(values) * (k + 1)
+        if best is None or size < best[0]:
+            best = (size, k)
+    return best[1]
+
+
+def write_residual(bits, residual, block_size, order, coding):
+    partition_order, method, escape = coding
+    bits.write(method, 2)
+    bits.write(partition_order, 4)
+    limit = 15 if method == 0 else 31
+    partitions = 1 << partition_order
+    length = block_size >> partition_order
+    assert length > order
+    start = 0
+    for partition in range(partitions):
+        count = length - order if partition == 0 else length
+        values = residual[start:start + count]
+        start += count
+        if partition in escape:
+            width = max(v.bit_length() for v in values) + 1
+            width = 0 if all(v == 0 for v in values) else width
+            bits.write(limit, 4 + method)
+            bits.write(width, 5)
+            for value in values:
+                if width != 0:
+                    bits.signed(value, width)
+            continue
+        k = rice_parameter(values, limit)
+        bits.write(k, 4 + method)
+        for value in values:
+            folded = value << 1 if value >= 0 else (-value << 1) - 1
+            bits.unary(folded >> k)
+            bits.write(folded & ((1 << k) - 1), k)
+    assert start == len(residual)
+
+
+def write_subframe(bits, samples, sample_bits, kind, wasted=0):
+    """Write a subframe.  `kind` is (type, parameters, residual coding)."""
+    bits.write(0, 1)
+    kind, parameters, coding = kind
+    types = {CONSTANT: 0, VERBATIM: 1}
+    if kind == FIXED:
+        type_ = 8 | parameters
+    elif kind == LPC:
+        type_ = 32 | (len(parameters[0]) - 1)
+    else:
+        type_ = types[kind]
+    bits.write(type_, 6)
+    if wasted:
+        assert all(sample % (1 << wasted) == 0 for sample in samples)
+        bits.write(1, 1)
+        bits.unary(wasted - 1)
+        samples = [sample >> wasted for sample in samples]
+        sample_bits -= wasted
+    else:
+        bits.write(0, 1)
+    if kind == CONSTANT:
+        assert len(set(samples)) == 1
+        bits.signed(samples[0], sample_bits)
+    elif kind == VERBATIM:
+        for sample in samples:
+            bits.signed(sample, sample_bits)
+    elif kind == FIXED:
+        order = parameters
+        for sample in samples[:order]:
+            bits.signed(sample, sample_bits)
+        residual = fixed_residual(samples, order)
+        write_residual(bits, residual, len(samples), order, coding)
+    else:
+        coefs, precision, shift = parameters
+        for sample in samples[:len(coefs)]:
+            bits.signed(sample, sample_bits)
+        bits.write(precision - 1, 4)
+        bits.signed(shift, 5)
+        for coef in coefs:
+            bits.signed(coef, precision)
+        residual = lpc_residual(samples, coefs, shift)
+        write_residual(bits, residual, len(samples), len(coefs), coding)
+
+
+BLOCK_SIZES = {192: 1, 576: 2, 1152: 3, 2304: 4, 4608: 5}
+BLOCK_SIZES.update({256 << i: 8 + i for i in range(8)})
+SAMPLE_RATES = {
+    88200: 1, 176400: 2, 192000: 3, 8000: 4, 16000: 5, 22050: 6, 24000: 7,
+    32000: 8, 44100: 9, 48000: 10, 96000: 11,
+}
+SAMPLE_SIZES = {8: 1, 12: 2, 16: 4, 20: 5, 24: 6, 32: 7}
+ASSIGNMENTS = {"left_side": 8, "side_right": 9, "mid_side": 10}
+
+
+class Frame:
+    """Settings for one FLAC frame."""
+
+    def __init__(self, kinds, assignment=None, wasted=(), **header):
+        # One subframe kind for each channel.
+        self.kinds = kinds
+        # Stereo decorrelation, or None for independent channels.
+        self.assignment = assignment
+        # Wasted bits for each channel.
+        self.wasted = wasted or [0] * len(kinds)
+        # Header coding: `rate` and `size` are "info" to leave them to
+        # STREAMINFO, "code" for the table code, or an explicit coding (12,
+        # 13 or 14 for the sample rate); `block` is "code", 6 or 7.
+        self.header = header
+
+
+def write_frame(stream, frame, channels, number, variable):
+    """Write a FLAC frame of `channels` (lists of samples)."""
+    bits = Bits()
+    size = len(channels[0])
+    rate = stream["rate"]
+    sample_bits = stream["bits"]
+    header = frame.header
+    bits.write(0x3FFE, 14)
+    bits.write(0, 1)
+    bits.write(1 if variable else 0, 1)
+    block = header.get("block", "code")
+    block_code = BLOCK_SIZES[size] if block == "code" else block
+    bits.write(block_code, 4)
+    rate_coding = header.get("rate", "code")
+    if rate_coding == "info":
+        rate_code = 0
+    elif rate_coding == "code":
+        rate_code = SAMPLE_RATES[rate]
+    else:
+        rate_code = rate_coding
+    bits.write(rate_code, 4)
+    if frame.assignment is None:
+        bits.write(len(channels) - 1, 4)
+    else:
+        bits.write(ASSIGNMENTS[frame.assignment], 4)
+    size_coding = header.get("size", "code")
+    bits.write(0 if size_coding == "info" else SAMPLE_SIZES[sample_bits], 3)
+    bits.write(0, 1)
+    coded_number(bits, number)
+    if block_code == 6:
+        bits.write(size - 1, 8)
+    elif block_code == 7:
+        bits.write(size - 1, 16)
+    if rate_code == 12:
+        bits.write(rate // 1000, 8)
+    elif rate_code == 13:
+        bits.write(rate, 16)
+    elif rate_code == 14:
+        bits.write(rate // 10, 16)
+    bits.write(crc8(bits.bytes), 8)
+
+    subframes = list(channels)
+    widths = [sample_bits] * len(channels)
+    if frame.assignment is not None:
+        left, right = channels
+        side = [l - r for l, r in zip(left, right)]
+        if frame.assignment == "left_side":
+            subframes, widths = [left, side], [sample_bits, sample_bits + 1]
+        elif frame.assignment == "side_right":
+            subframes, widths = [side, right], [sample_bits + 1, sample_bits]
+        else:
+            mid = [(l + r) >> 1 for l, r in zip(left, right)]
+            subframes, widths = [mid, side], [sample_bits, sample_bits + 1]
+    for samples, width, kind, wasted in zip(
+        subframes, widths, frame.kinds, frame.wasted
+    ):
+        write_subframe(bits, samples, width, kind, wasted)
+    bits.align()
+    bits.write(crc16(bits.bytes), 16)
+    return bytes(bits.bytes)
+
+
+def metadata_block(kind, data, last):
+    return bytes([(0x80 if last else 0) | kind]) + len(data).to_bytes(3, "big") + data
+
+
+def write_stream(path, stream, frames, blocks, metadata=()):
+    """Write a FLAC file of frames, given the samples of each frame as lists
+    of channels."""
+    channels = len(blocks[0])
+    sample_bits = stream["bits"]
+    variable = stream.get("variable", False)
+    encoded = []
+    number = 0
+    for frame, block in zip(frames, blocks):
+        encoded.append(write_frame(stream, frame, block, number, variable))
+        number += len(block[0]) if variable else 1
+    sizes = [len(block[0]) for block in blocks]
+    md5 = hashlib.md5()
+    width = (sample_bits + 7) // 8
+    for block in blocks:
+        for i in range(len(block[0])):
+            for channel in block:
+                md5.update(channel[i].to_bytes(width, "little", signed=True))
+    info = Bits()
+    if variable:
+        info.write(stream.get("min_block", 16), 16)
+        info.write(max(sizes), 16)
+    else:
+        # The last block may be shorter.
+        info.write(sizes[0], 16)
+        info.write(sizes[0], 16)
+    info.write(min(len(frame) for frame in encoded), 24)
+    info.write(max(len(frame) for frame in encoded), 24)
+    info.write(stream["rate"], 20)
+    info.write(channels - 1, 3)
+    info.write(sample_bits - 1, 5)
+    info.write(sum(sizes), 36)
+    data = bytes(info.bytes) + md5.digest()
+    blocks_ = [(0, data)] + list(metadata)
+    output = bytearray(b"fLaC")
+    for i, (kind, data) in enumerate(blocks_):
+        output += metadata_block(kind, data, i == len(blocks_) - 1)
+    for frame in encoded:
+        output += frame
+    with open(path, "wb") as file:
+        file.write(output)
+
+
+def signal(rng, length, bits, amplitude, noise, phase=0.0, wasted=0):
+    """A sine wave with noise, within `bits` bits."""
+    low, high = -(1 << (bits - 1)), (1 << (bits - 1)) - 1
+    samples = []
+    for i in range(length):
+        value = amplitude * math.sin(i * 0.01 + phase)
+        value += rng.randint(-noise, noise)
+        value = max(low, min(high, int(value)))
+        samples.append(value >> wasted << wasted)
+    return samples
+
+
+def fixed_coefs(order, shift):
+    """LPC coefficients of a FIXED predictor, scaled by `shift`."""
+    coefs = {1: [1], 2: [2, -1], 3: [3, -3, 1], 4: [4, -6, 4, -1]}[order]
+    return [c << shift for c in coefs]
+
+
+def lpc_kind(order, precision, shift, coding=RICE, base=2):
+    """An LPC subframe with a FIXED-like predictor, padded with small taps."""
+    coefs = fixed_coefs(min(base, order), shift)
+    extra = [((i * 5) % 7 - 3) for i in range(order - len(coefs))]
+    coefs = coefs + extra
+    limit = 1 << (precision - 1)
+    assert all(-limit <= c < limit for c in coefs), (coefs, precision)
+    return (LPC, (coefs, precision, shift), coding)
+
+
+def subframes():
+    """Every subframe type, predictor order and residual coding."""
+    rng = random.Random(1)
+    stream = {"rate": 44100, "bits": 16}
+    kinds = [
+        (CONSTANT, None, None),
+        (VERBATIM, None, None),
+        (FIXED, 0, (2, 0, ())),
+        (FIXED, 1, (0, 0, ())),
+        (FIXED, 2, (3, 1, ())),
+        (FIXED, 3, (4, 0, (1,))),
+        (FIXED, 4, (6, 1, (0, 5))),
+        lpc_kind(1, 12, 9, (1, 0, ()), base=1),
+        lpc_kind(2, 15, 11, (5, 0, ())),
+        lpc_kind(3, 8, 3, (2, 1, ()), base=3),
+        lpc_kind(4, 15, 10, (0, 0, ()), base=4),
+        lpc_kind(8, 14, 11, (3, 0, (0,))),
+        lpc_kind(12, 15, 12, (4, 1, ())),
+        lpc_kind(16, 6, 0, (2, 0, ()), base=2),
+        lpc_kind(24, 15, 12, (3, 0, ())),
+        lpc_kind(32, 15, 12, (5, 1, (31,))),
+    ]
+    frames, blocks = [], []
+    for i, kind in enumerate(kinds):
+        left = signal(rng, 1152, 16, 3000, 60, phase=i)
+        right = signal(rng, 1152, 16, 2000, 40, phase=i * 2)
+        if kind[0] == CONSTANT:
+            left, right = [-1234] * 1152, [32767] * 1152
+        frames.append(Frame([kind, kind]))
+        blocks.append([left, right])
+    # Residuals that need large Rice parameters, and all-zero escapes.
+    left = signal(rng, 1152, 16, 0, 30000)
+    right = [0] * 1152
+    frames.append(Frame([(FIXED, 0, (1, 1, ())), (FIXED, 1, (2, 0, (0, 1, 2, 3)))]))
+    blocks.append([left, right])
+    write_stream("subframes.flac", stream, frames, blocks)
+
+
+def block_sizes():
+    """A variable block size stream, with every block size coding."""
+    rng = random.Random(2)
+    stream = {"rate": 37800, "bits": 16, "variable": True}
+    sizes = [
+        (192, "code"), (576, "code"), (1152, "code"), (2304, "code"),
+        (4608, "code"), (256, "code"), (512, "code"), (1024, "code"),
+        (2048, "code"), (4096, "code"), (8192, "code"), (16384, "code"),
+        (32768, "code"), (17, 6), (256, 6), (100, 7), (4095, 7),
+        (65535, 7), (16, 6), (1000, 7),
+    ]
+    frames, blocks = [], []
+    for i, (size, coding) in enumerate(sizes):
+        samples = signal(rng, size, 16, 200, 3, phase=i)
+        # Partition orders up to what the block size allows.
+        order = 0
+        while size % (2 << order) == 0 and size >> (order + 1) > 2 and order < 8:
+            order += 1
+        kind = (FIXED, 2, (order, 0, ()))
+        frames.append(Frame([kind], block=coding, rate="info"))
+        blocks.append([samples])
+    write_stream("block_sizes.flac", stream, frames, blocks)
+
+
+def small_blocks():
+    """A fixed block size stream of 16 sample frames, with frame numbers
+    needing up to three bytes, and a shorter last frame."""
+    rng = random.Random(3)
+    stream = {"rate": 8000, "bits": 8}
+    frames, blocks = [], []
+    for i in range(2100):
+        samples = signal(rng, 16, 8, 100, 2, phase=i * 0.16)
+        kind = [(FIXED, 1, RICE), (VERBATIM, None, None)][i % 2]
+        frames.append(Frame([kind], block=6))
+        blocks.append([samples])
+    frames.append(Frame([(FIXED, 2, RICE)], block=6))
+    blocks.append([signal(rng, 5, 8, 100, 2)])
+    write_stream("small_blocks.flac", stream, frames, blocks)
+
+
+def bits_32():
+    """32 bits per sample, with every stereo decorrelation (a 33-bit side
+    channel)."""
+    rng = random.Random(4)
+    stream = {"rate": 96000, "bits": 32}
+    top = (1 << 31) - 1
+    frames, blocks = [], []
+    for assignment in [None, "left_side", "side_right", "mid_side"]:
+        # Full scale, stored verbatim.
+        left = [rng.randint(-top - 1, top) for _ in range(576)]
+        right = [rng.randint(-top - 1, top) for _ in range(576)]
+        left[0], right[0] = -top - 1, top
+        verbatim = (VERBATIM, None, None)
+        frames.append(Frame([verbatim, verbatim], assignment))
+        blocks.append([left, right])
+        # Predicted, with residuals within 32 bits.
+        left = signal(rng, 576, 32, 1 << 28, 1 << 20)
+        right = signal(rng, 576, 32, 1 << 27, 1 << 20, phase=1.0)
+        kinds = [(FIXED, 2, (3, 1, ())), lpc_kind(8, 15, 12, (2, 1, ()))]
+        frames.append(Frame(kinds, assignment))
+        blocks.append([left, right])
+    constant = (CONSTANT, None, None)
+    frames.append(Frame([constant, constant]))
+    blocks.append([[-top - 1] * 576, [top] * 576])
+    write_stream("bits_32.flac", stream, frames, blocks)
+
+
+def wasted_bits():
+    """Subframes with wasted (always zero) low bits."""
+    rng = random.Random(5)
+    stream = {"rate": 48000, "bits": 24}
+    frames, blocks = [], []
+    for wasted in [1, 3, 8, 12]:
+        left = signal(rng, 2048, 24, 1 << 20, 1 << 14, wasted=wasted)
+        right = signal(rng, 2048, 24, 1 << 19, 1 << 14, wasted=wasted + 2)
+        kinds = [(FIXED, 2, (2, 0, ())), lpc_kind(4, 15, 12, (3, 1, ()))]
+        frames.append(Frame(kinds, wasted=[wasted, wasted + 2]))
+        blocks.append([left, right])
+    # A side channel with wasted bits, and a constant one.
+    left = signal(rng, 2048, 24, 1 << 20, 1 << 10, wasted=4)
+    right = [x + (1 << 8) for x in left]
+    kinds = [(FIXED, 1, RICE), (CONSTANT, None, None)]
+    frames.append(Frame(kinds, "left_side", wasted=[4, 8]))
+    blocks.append([left, right])
+    left = signal(rng, 2048, 24, 1 << 20, 1 << 10, wasted=5)
+    right = signal(rng, 2048, 24, 1 << 20, 1 << 10, phase=2.0, wasted=5)
+    kinds = [(FIXED, 2, (4, 0, ())), (VERBATIM, None, None)]
+    frames.append(Frame(kinds, "mid_side", wasted=[4, 5]))
+    blocks.append([left, right])
+    write_stream("wasted_bits.flac", stream, frames, blocks)
+
+
+def channels_8():
+    """8 channels of 20 bits, at 12 kHz (coded in kHz)."""
+    rng = random.Random(6)
+    stream = {"rate": 12000, "bits": 20}
+    kinds = [
+        (FIXED, 0, RICE), (FIXED, 1, (2, 0, ())), (FIXED, 2, (3, 1, ())),
+        (FIXED, 3, RICE), (FIXED, 4, (1, 0, ())), lpc_kind(6, 14, 11),
+        (VERBATIM, None, None), (CONSTANT, None, None),
+    ]
+    frames, blocks = [], []
+    for i in range(4):
+        block = [
+            signal(rng, 1024, 20, 1 << (12 + c % 6), 1 << 4, phase=c + i)
+            for c in range(7)
+        ]
+        block.append([i * 37 - 50] * 1024)
+        frames.append(Frame(kinds, rate=12))
+        blocks.append(block)
+    write_stream("channels_8.flac", stream, frames, blocks)
+
+
+def channels_3():
+    """3 channels of 12 bits, at 11025 Hz (coded in Hz)."""
+    rng = random.Random(7)
+    stream = {"rate": 11025, "bits": 12}
+    kinds = [(FIXED, 2, RICE), lpc_kind(3, 10, 7, base=3), (FIXED, 1, RICE)]
+    frames, blocks = [], []
+    for i in range(5):
+        block = [
+            signal(rng, 576, 12, 1500, 5, phase=c * 0.5 + i) for c in range(3)
+        ]
+        frames.append(Frame(kinds, rate=13))
+        blocks.append(block)
+    write_stream("channels_3.flac", stream, frames, blocks)
+
+
+def metadata():
+    """4 bits per sample, at 32010 Hz (coded in tens of Hz), with every
+    kind of metadata block."""
+    rng = random.Random(8)
+    stream = {"rate": 32010, "bits": 4}
+    frames, blocks = [], []
+    for i in range(3):
+        samples = signal(rng, 4096, 4, 6, 1, phase=i)
+        frames.append(Frame([(FIXED, 1, RICE)], rate=14, size="info"))
+        blocks.append([samples])
+
+    def string(text):
+        data = text.encode()
+        return len(data).to_bytes(4, "little") + data
+
+    padding = (1, bytes(100))
+    application = (2, b"flak" + bytes(range(10)))
+    # A seek point to the first frame, and a placeholder.
+    seek_table = (3, bytes(8) + bytes(8) + (4096).to_bytes(2, "big")
+                  + b"\xFF" * 8 + bytes(10))
+    comments = ["TITLE=Conformance", "ARTIST=flak", "title=lower case"]
+    vorbis_comment = (4, string("flak test stream")
+                      + len(comments).to_bytes(4, "little")
+                      + b"".join(string(comment) for comment in comments))
+    cue_sheet = bytearray()
+    cue_sheet += b"1234567890123".ljust(128, b"\0")
+    cue_sheet += (0).to_bytes(8, "big")
+    cue_sheet += bytes([0]) + bytes(258)
+    cue_sheet += bytes([2])
+    # One audio track with two indices, then the lead-out track.
+    cue_sheet += (0).to_bytes(8, "big") + bytes([1]) + b"ABCDE1234567"
+    cue_sheet += bytes([0]) + bytes(13) + bytes([2])
+    cue_sheet += (0).to_bytes(8, "big") + bytes([0]) + bytes(3)
+    cue_sheet += (4096).to_bytes(8, "big") + bytes([1]) + bytes(3)
+    cue_sheet += (12288).to_bytes(8, "big") + bytes([255]) + bytes(12)
+    cue_sheet += bytes([0]) + bytes(13) + bytes([0])
+    picture = bytearray()
+    picture += (3).to_bytes(4, "big")
+    picture += (9).to_bytes(4, "big") + b"image/png"
+    picture += (5).to_bytes(4, "big") + b"cover"
+    for value in [1, 1, 24, 0]:
+        picture += value.to_bytes(4, "big")
+    picture += (8).to_bytes(4, "big") + b"\x89PNG\r\n\x1a\n"
+    blocks_ = [
+        seek_table, vorbis_comment, application, (5, bytes(cue_sheet)),
+        (6, bytes(picture)), padding,
+    ]
+    write_stream("metadata.flac", stream, frames, blocks, blocks_)
+
+
+if __name__ == "__main__":
+    subframes()
+    block_sizes()
+    small_blocks()
+    bits_32()
+    wasted_bits()
+    channels_8()
+    channels_3()
+    metadata()
@@ -0,0 +1,171 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::Cursor;
+
+use fon::chan::Ch64;
+use fon::mono::Mono64;
+
+use flak::metadata::{
+    self, Application, Block, CueIndex, CueSheet, CueTrack, Picture, SeekPoint,
+    VorbisComment,
+};
+use flak::Decoder;
+
+// Encode a short ramp with some metadata blocks.
+fn encode(blocks: Vec<Block>) -> Vec<u8> {
+    let frames: Vec<Mono64> = (0..10_000)
+        .map(|i| Mono64::new(Ch64::new(f64::from(i % 200) / 100.0 - 1.0)))
+        .collect();
+    common::encode(frames, 44100, 16, blocks)
+}
+
+// Decode the metadata blocks and audio.
+fn decode(flac: &[u8]) -> (Vec<Block>, Vec<Mono64>) {
+    let decoder = Decoder::<_, Mono64>::new(flac).unwrap();
+    let blocks = decoder.metadata().to_vec();
+    (blocks, decoder.map(|frame| frame.unwrap()).collect())
+}
+
+fn tags() -> VorbisComment {
+    let mut tags = VorbisComment::new("test");
+    tags.add("TITLE", "Ramp");
+    tags.add("ARTIST", "A");
+    tags.add("artist", "B");
+    tags
+}
+
+#[test]
+fn blocks() {
+    let blocks = vec![
+        Block::VorbisComment(tags()),
+        Block::SeekTable(vec![
+            SeekPoint {
+                sample: 0,
+                offset: 0,
+                samples: 4096,
+            },
+            SeekPoint {
+                sample: SeekPoint::PLACEHOLDER,
+                offset: 0,
+                samples: 0,
+            },
+        ]),
+        Block::Application(Application {
+            id: *b"test",
+            data: vec![1, 2, 3],
+        }),
+        Block::CueSheet(CueSheet {
+            catalog: "1234567890123".to_string(),
+            lead_in: 88200,
+            cd: true,
+            tracks: vec![
+                CueTrack {
+                    offset: 0,
+                    number: 1,
+                    isrc: "USABC1234567".to_string(),
+                    audio: true,
+                    pre_emphasis: false,
+                    indices: vec![CueIndex {
+                        offset: 0,
+                        number: 1,
+                    }],
+                },
+                CueTrack {
+                    offset: 9996,
+                    number: 170,
+                    ..CueTrack::default()
+                },
+            ],
+        }),
+        Block::Picture(Picture {
+            picture_type: 3,
+            mime: "image/png".to_string(),
+            description: "Cover".to_string(),
+            width: 1,
+            height: 1,
+            depth: 24,
+            colors: 0,
+            data: vec![0x89, b'P', b'N', b'G'],
+        }),
+        Block::Unknown(42, vec![7; 5]),
+        Block::Padding(100),
+    ];
+    let flac = encode(blocks.clone());
+    let (decoded, _) = decode(&flac);
+    assert_eq!(decoded, blocks);
+    assert_eq!(blocks[3].len(), 396 + 36 * 2 + 12);
+
+    let tags = tags();
+    assert_eq!(tags.get("Artist").collect::<Vec<_>>(), ["A", "B"]);
+    let mut tags = tags;
+    tags.set("artist", "C");
+    assert_eq!(tags.get("ARTIST").collect::<Vec<_>>(), ["C"]);
+    tags.remove("title");
+    assert_eq!(tags.comments.len(), 1);
+}
+
+#[test]
+fn errors() {
+    // A comment count beyond the end of the block.
+    let mut tags = VorbisComment::new("test");
+    tags.add("TITLE", "Ramp");
+    let mut flac = encode(vec![Block::VorbisComment(tags)]);
+    let at = flac.windows(10).position(|w| w == b"TITLE=Ramp").unwrap();
+    flac[at - 8] = 2;
+    assert!(matches!(
+        Decoder::<_, Mono64>::new(&flac[..]),
+        Err(flak::Error::Metadata)
+    ));
+}
+
+#[test]
+fn rewrite() {
+    let flac = encode(vec![Block::VorbisComment(tags()), Block::Padding(64)]);
+    let (_, audio) = decode(&flac);
+
+    // Fits in the padding.
+    let mut file = Cursor::new(flac.clone());
+    let mut tags = tags();
+    tags.set("TITLE", "Longer title");
+    let blocks = vec![Block::VorbisComment(tags.clone())];
+    assert!(!metadata::rewrite(&mut file, &blocks).unwrap());
+    let file = file.into_inner();
+    assert_eq!(file.len(), flac.len());
+    let (decoded, decoded_audio) = decode(&file);
+    assert_eq!(decoded[0], blocks[0]);
+    assert_eq!(decoded[1], Block::Padding(64 - 8));
+    assert_eq!(decoded_audio, audio);
+
+    // Exactly fills the space, with no padding left.
+    let mut file = Cursor::new(flac.clone());
+    tags.set("TITLE", "Ramp".to_string() + &"!".repeat(68));
+    let blocks = vec![Block::VorbisComment(tags.clone())];
+    assert!(!metadata::rewrite(&mut file, &blocks).unwrap());
+    let file = file.into_inner();
+    assert_eq!(file.len(), flac.len());
+    assert_eq!(decode(&file), (blocks, audio.clone()));
+
+    // Doesn't fit, so the audio moves.
+    let mut file = Cursor::new(flac.clone());
+    let picture = Picture {
+        picture_type: 3,
+        mime: "image/png".to_string(),
+        data: vec![0; 1000],
+        ..Picture::default()
+    };
+    let blocks = vec![Block::Picture(picture), Block::Padding(10)];
+    assert!(metadata::rewrite(&mut file, &blocks).unwrap());
+    let file = file.into_inner();
+    let (decoded, decoded_audio) = decode(&file);
+    assert_eq!(decoded[0], blocks[0]);
+    assert_eq!(decoded[1], Block::Padding(metadata::DEFAULT_PADDING));
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded_audio, audio);
+}
@@ -0,0 +1,133 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::Cursor;
+
+use fon::stereo::Stereo64;
+use fon::{Audio, Frame};
+
+use flak::metadata::{Block, VorbisComment};
+use flak::{Decoder, Encoder, Error};
+
+use common::{chan, int};
+
+// Sine tones, as 16-bit integer samples.
+fn samples(len: usize) -> Vec<[i64; 2]> {
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / 44100.0 * std::f64::consts::TAU;
+            let left = (t * 440.0).sin() * 20000.0;
+            [left as i64, (left * 0.5 + 1000.0) as i64]
+        })
+        .collect()
+}
+
+fn audio(samples: &[[i64; 2]]) -> Audio<Stereo64> {
+    let frames: Vec<_> = samples
+        .iter()
+        .map(|[l, r]| Stereo64::new(chan(*l, 16), chan(*r, 16)))
+        .collect();
+    Audio::with_frames(44100, frames)
+}
+
+// Split an Ogg stream into its pages.
+fn pages(ogg: &[u8]) -> Vec<&[u8]> {
+    let mut pages = Vec::new();
+    let mut rest = ogg;
+    while !rest.is_empty() {
+        assert_eq!(&rest[..4], b"OggS");
+        let segments = usize::from(rest[26]);
+        let body: usize = rest[27..27 + segments]
+            .iter()
+            .map(|v| usize::from(*v))
+            .sum();
+        let (page, next) = rest.split_at(27 + segments + body);
+        pages.push(page);
+        rest = next;
+    }
+    pages
+}
+
+#[test]
+fn roundtrip() {
+    let samples = samples(30_000);
+    let mut comments = VorbisComment::new("flak test");
+    comments.add("TITLE", "Ogg");
+    let mut encoder =
+        Encoder::<_, Stereo64>::new(Cursor::new(Vec::new()), 44100, 16)
+            .with_metadata(vec![
+                Block::VorbisComment(comments.clone()),
+                Block::Padding(100),
+            ])
+            .with_ogg(0xF1AC);
+    encoder.encode(&audio(&samples)).unwrap();
+    let ogg = encoder.finish().unwrap().into_inner();
+
+    let pages = pages(&ogg);
+    assert_eq!(pages[0][5], 0x02);
+    assert_eq!(&pages[0][28..33], b"\x7FFLAC");
+    assert_eq!(pages.last().unwrap()[5] & 0x04, 0x04);
+    let mut granule = [0; 8];
+    granule.copy_from_slice(&pages.last().unwrap()[6..14]);
+    assert_eq!(u64::from_le_bytes(granule), 30_000);
+
+    let decoder = Decoder::<_, Stereo64>::new(&ogg[..]).unwrap();
+    assert_eq!(decoder.stream_info().samples, 30_000);
+    assert_ne!(decoder.stream_info().md5, [0; 16]);
+    assert_eq!(
+        decoder.metadata(),
+        &[Block::VorbisComment(comments), Block::Padding(100),]
+    );
+    let decoded: Vec<[i64; 2]> = decoder
+        .map(|frame| {
+            let channels = frame.unwrap().channels().to_vec();
+            [int(channels[0], 16), int(channels[1], 16)]
+        })
+        .collect();
+    assert_eq!(decoded, samples);
+
+    let report = Decoder::<_, Stereo64>::new(&ogg[..])
+        .unwrap()
+        .verify()
+        .unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.md5_matches(), Some(true));
+}
+
+#[test]
+fn unfinished() {
+    // Without seeking back, the STREAMINFO is left unknown.
+    let mut encoder = Encoder::<_, Stereo64>::new(Vec::new(), 44100, 16)
+        .with_metadata(Vec::new())
+        .with_ogg(1);
+    encoder.encode(&audio(&samples(5000))).unwrap();
+    let ogg = encoder.into_inner().unwrap();
+    let decoder = Decoder::<_, Stereo64>::new(&ogg[..]).unwrap();
+    assert_eq!(decoder.stream_info().samples, 0);
+    assert_eq!(decoder.count(), 5000);
+
+    // A stream with no audio ends on its header pages.
+    let encoder =
+        Encoder::<_, Stereo64>::new(Cursor::new(Vec::new()), 44100, 16)
+            .with_ogg(1);
+    let ogg = encoder.finish().unwrap().into_inner();
+    assert_eq!(pages(&ogg).last().unwrap()[5] & 0x04, 0x04);
+    assert_eq!(Decoder::<_, Stereo64>::new(&ogg[..]).unwrap().count(), 0);
+}
+
+#[test]
+fn seek() {
+    let mut encoder =
+        Encoder::<_, Stereo64>::new(Cursor::new(Vec::new()), 44100, 16)
+            .with_ogg(1);
+    encoder.encode(&audio(&samples(5000))).unwrap();
+    let ogg = Cursor::new(encoder.finish().unwrap().into_inner());
+    let mut decoder = Decoder::<_, Stereo64>::new(ogg).unwrap();
+    assert!(matches!(decoder.seek(100), Err(Error::Io(_))));
+}
@@ -0,0 +1,149 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::Cursor;
+
+use fon::chan::Ch64;
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::surround::Surround64;
+use fon::{Audio, Frame};
+
+use flak::{Decoder, Encoder};
+
+use common::{chan, int};
+
+// Xorshift random numbers, so failures are reproducible from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // A random number in `low..=high`.
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        let span = (high - low) as u64 + 1;
+        low + (self.next() % span) as i64
+    }
+}
+
+// Random integer samples of one kind of signal, interleaved.
+fn signal(rng: &mut Rng, len: usize, channels: usize, bits: u32) -> Vec<i64> {
+    let (min, max) = (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1);
+    let kind = rng.range(0, 4);
+    let noise = rng.range(0, max.min(1 << 12));
+    let step = rng.range(1, 500) as f64 / 10_000.0;
+    let mut samples = Vec::with_capacity(len * channels);
+    for i in 0..len {
+        for c in 0..channels {
+            let sample = match kind {
+                // White noise over the full range.
+                0 => rng.range(min, max),
+                // A sine wave with some noise.
+                1 => {
+                    let t = i as f64 * step + c as f64;
+                    (t.sin() * max as f64 * 0.8) as i64 + rng.range(0, noise)
+                }
+                // Silence, then full scale.
+                2 if (i / 1000) % 2 == 0 => 0,
+                2 => [min, max][c % 2],
+                // A square wave.
+                3 => [min, max][(i / (c + 2)) % 2] / 2,
+                // A channel of a constant.
+                _ => c as i64 - 1,
+            };
+            samples.push(sample.clamp(min, max));
+        }
+    }
+    samples
+}
+
+// Encode random audio and check it decodes to the same samples.
+fn roundtrip<F: Frame<Chan = Ch64>>(rng: &mut Rng) {
+    let channels = F::CHAN_COUNT;
+    let bits = rng.range(4, 32) as u32;
+    let level = rng.range(0, 8) as u8;
+    let len = match rng.range(0, 3) {
+        0 => rng.range(0, 20) as usize,
+        1 => (4096 * rng.range(1, 2) + rng.range(-1, 1)) as usize,
+        _ => rng.range(0, 12_000) as usize,
+    };
+    let samples = signal(rng, len, channels, bits);
+    let frames: Vec<F> = samples
+        .chunks(channels)
+        .map(|chunk| {
+            let mut frame = F::default();
+            for (out, sample) in frame.channels_mut().iter_mut().zip(chunk) {
+                *out = chan(*sample, bits);
+            }
+            frame
+        })
+        .collect();
+    let audio = Audio::with_frames(48000, frames);
+    let mut encoder =
+        Encoder::<_, F>::new(Cursor::new(Vec::new()), 48000, bits as u8)
+            .with_level(level);
+    assert_eq!(encoder.encode(&audio).unwrap(), len);
+    let flac = encoder.finish().unwrap().into_inner();
+
+    let case = format!("{}ch {}-bit level {} ×{}", channels, bits, level, len);
+    let decoded: Vec<i64> = Decoder::<_, F>::new(&flac[..])
+        .unwrap()
+        .flat_map(|frame| {
+            let frame = frame.unwrap();
+            let channels: Vec<i64> =
+                frame.channels().iter().map(|c| int(*c, bits)).collect();
+            channels
+        })
+        .collect();
+    assert!(decoded == samples, "{}", case);
+    let report = Decoder::<_, F>::new(&flac[..]).unwrap().verify().unwrap();
+    assert_eq!(report.md5_matches(), Some(true), "{}", case);
+
+    // Damaged streams never panic.
+    let mut damaged = flac;
+    for _ in 0..rng.range(1, 4) {
+        let index = rng.range(0, damaged.len() as i64 - 1) as usize;
+        damaged[index] ^= 1 << rng.range(0, 7);
+    }
+    if let Ok(decoder) = Decoder::<_, F>::new(&damaged[..]) {
+        let _ = decoder.verify();
+    }
+    if let Ok(decoder) = Decoder::<_, F>::new(&damaged[..]) {
+        decoder.with_resync().take(len + 1).for_each(drop);
+    }
+}
+
+#[test]
+fn mono() {
+    let mut rng = Rng(0x5EED_0001);
+    for _ in 0..24 {
+        roundtrip::<Mono64>(&mut rng);
+    }
+}
+
+#[test]
+fn stereo() {
+    let mut rng = Rng(0x5EED_0002);
+    for _ in 0..24 {
+        roundtrip::<Stereo64>(&mut rng);
+    }
+}
+
+#[test]
+fn surround() {
+    let mut rng = Rng(0x5EED_0006);
+    for _ in 0..12 {
+        roundtrip::<Surround64>(&mut rng);
+    }
+}
@@ -0,0 +1,91 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::Cursor;
+
+use fon::chan::Ch64;
+use fon::mono::Mono64;
+
+use flak::metadata::{Block, SeekPoint};
+use flak::Decoder;
+
+const LEN: u64 = 200_000;
+
+// Encode noisy audio, which compresses poorly so there's plenty to bisect.
+fn encode(blocks: Vec<Block>) -> Vec<u8> {
+    let mut seed = 1u32;
+    let frames: Vec<Mono64> = (0..LEN)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            Mono64::new(Ch64::new(f64::from(seed >> 16) / 32768.0 - 1.0))
+        })
+        .collect();
+    common::encode(frames, 44100, 16, blocks)
+}
+
+// Seek around the stream, checking against decoding from the start.
+fn check(flac: &[u8]) {
+    let all: Vec<Mono64> = Decoder::<_, Mono64>::new(flac)
+        .unwrap()
+        .map(|frame| frame.unwrap())
+        .collect();
+    assert_eq!(all.len() as u64, LEN);
+    let mut decoder = Decoder::<_, Mono64>::new(Cursor::new(flac)).unwrap();
+    for frame in [123_457, 0, 1, 4095, 4096, 100_000, 199_999, 50_000] {
+        decoder.seek(frame).unwrap();
+        assert_eq!(decoder.position(), frame);
+        let decoded: Vec<Mono64> =
+            decoder.by_ref().take(10).map(|f| f.unwrap()).collect();
+        let end = (frame as usize + 10).min(all.len());
+        assert_eq!(decoded, all[frame as usize..end], "seek to {}", frame);
+    }
+    decoder.seek(LEN).unwrap();
+    assert!(decoder.next().is_none());
+    decoder.seek(LEN + 1000).unwrap();
+    assert!(decoder.next().is_none());
+    decoder.seek(LEN - 1).unwrap();
+    assert_eq!(decoder.count(), 1);
+}
+
+#[test]
+fn bisection() {
+    let flac = encode(Vec::new());
+    assert!(flac.len() > 300_000);
+    check(&flac);
+}
+
+#[test]
+fn seek_table() {
+    let points = (0..LEN + 20_000)
+        .step_by(10_000)
+        .map(|sample| SeekPoint {
+            sample,
+            offset: 0,
+            samples: 0,
+        })
+        .collect();
+    let flac = encode(vec![Block::SeekTable(points)]);
+    let decoder = Decoder::<_, Mono64>::new(&flac[..]).unwrap();
+    let points = match &decoder.metadata()[0] {
+        Block::SeekTable(points) => points.clone(),
+        block => panic!("{:?}", block),
+    };
+    assert_eq!(points.len(), 22);
+    assert_eq!(points[0].offset, 0);
+    for pair in points[..20].windows(2) {
+        assert_eq!(pair[0].sample % 4096, 0);
+        assert!(pair[0].sample < pair[1].sample);
+        assert!(pair[0].offset < pair[1].offset);
+        assert_eq!(pair[0].samples, 4096);
+    }
+    // Points past the end aren't used.
+    assert_eq!(points[19].sample, 190_000 / 4096 * 4096);
+    assert_eq!(points[20].sample, SeekPoint::PLACEHOLDER);
+    check(&flac);
+}
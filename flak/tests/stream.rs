@@ -0,0 +1,111 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::{self, Cursor, Read};
+
+use fon::chan::Ch64;
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+
+use flak::metadata;
+use flak::{Decoder, RawFrame};
+
+// A pipe, giving one byte at a time.
+struct Pipe<'a>(&'a [u8]);
+
+impl Read for Pipe<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+// Encode 10 frames of stereo audio, without metadata blocks.
+fn encode() -> Vec<u8> {
+    let frames: Vec<Stereo64> = (0..40_000)
+        .map(|i| {
+            let t = f64::from(i) / 48000.0 * std::f64::consts::TAU;
+            let left = (t * 440.0).sin() * 0.5;
+            Stereo64::new(Ch64::new(left), Ch64::new(left * 0.5 - 0.2))
+        })
+        .collect();
+    common::encode(frames, 48000, 24, Vec::new())
+}
+
+#[test]
+fn raw_frames() {
+    let flac = encode();
+    let decoded: Vec<Stereo64> = Decoder::new(&flac[..])
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    // As stored in Matroska: the marker and metadata, then each frame.
+    let (info, blocks) = metadata::parse(&flac[..42]).unwrap();
+    assert_eq!(info.sample_rate, 48000);
+    assert!(blocks.is_empty());
+    let mut rest = &flac[42..];
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        let frame = RawFrame::parse(rest, &info).unwrap();
+        assert_eq!(frame.sample, frames.len() as u64);
+        assert_eq!(frame.sample_rate, 48000);
+        assert_eq!(frame.bits_per_sample, 24);
+        assert_eq!(frame.channels(), 2);
+        assert_eq!(frame.channel(1).len(), frame.len());
+        frames.extend(frame.frames::<Stereo64>());
+        rest = &rest[frame.size..];
+    }
+    assert_eq!(frames, decoded);
+
+    let mono = RawFrame::parse(&flac[42..], &info)
+        .unwrap()
+        .frames::<Mono64>();
+    assert_eq!(mono.len(), 4096);
+
+    let mut corrupt = flac[42..].to_vec();
+    corrupt[100] ^= 0x01;
+    assert!(RawFrame::parse(&corrupt, &info).is_err());
+    assert!(RawFrame::parse(&[], &info).is_err());
+}
+
+#[test]
+fn no_metadata() {
+    let flac = encode();
+    let decoded: Vec<Stereo64> = Decoder::new(&flac[..])
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    let (info, _) = metadata::parse(&flac[..42]).unwrap();
+    let mut offset = 42;
+    for _ in 0..3 {
+        offset += RawFrame::parse(&flac[offset..], &info).unwrap().size;
+    }
+
+    // Join the stream at the fourth frame, from a pipe.
+    let decoder = Decoder::<_, Stereo64>::new(Pipe(&flac[offset..])).unwrap();
+    let info = decoder.stream_info();
+    assert_eq!(info.sample_rate, 48000);
+    assert_eq!(info.channels, 2);
+    assert_eq!(info.bits_per_sample, 24);
+    assert_eq!(info.samples, 0);
+    assert!(decoder.metadata().is_empty());
+    assert_eq!(decoder.position(), 3 * 4096);
+    let frames: Vec<Stereo64> = decoder.map(Result::unwrap).collect();
+    assert_eq!(frames, decoded[3 * 4096..]);
+
+    // Seeking works from the first frame of the stream.
+    let mut decoder =
+        Decoder::<_, Stereo64>::new(Cursor::new(&flac[offset..])).unwrap();
+    decoder.seek(5 * 4096 + 7).unwrap();
+    assert_eq!(decoder.position(), 5 * 4096 + 7);
+    assert_eq!(decoder.next().unwrap().unwrap(), decoded[5 * 4096 + 7]);
+}
@@ -0,0 +1,160 @@
+// Flak
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::Cursor;
+
+use fon::chan::Ch64;
+use fon::stereo::Stereo64;
+
+use flak::metadata::{Block, SeekPoint};
+use flak::{Corruption, Damage, Decoder, Report};
+
+// Encode 10 frames of stereo audio, returning the offsets of each frame
+// from the start of the stream.
+fn encode() -> (Vec<u8>, Vec<u64>) {
+    let frames: Vec<Stereo64> = (0..40_960)
+        .map(|i| {
+            let t = f64::from(i) / 44100.0 * std::f64::consts::TAU;
+            let left = (t * 440.0).sin() * 0.5;
+            Stereo64::new(Ch64::new(left), Ch64::new(left * 0.8 + 0.1))
+        })
+        .collect();
+    let points = (0..10)
+        .map(|i| SeekPoint {
+            sample: i * 4096,
+            offset: 0,
+            samples: 0,
+        })
+        .collect();
+    let flac =
+        common::encode(frames, 44100, 16, vec![Block::SeekTable(points)]);
+    let first_frame = 4 + 38 + 4 + 18 * 10;
+    let offsets =
+        match &Decoder::<_, Stereo64>::new(&flac[..]).unwrap().metadata()[0] {
+            Block::SeekTable(points) => {
+                points.iter().map(|p| first_frame + p.offset).collect()
+            }
+            block => panic!("{:?}", block),
+        };
+    (flac, offsets)
+}
+
+fn verify(flac: &[u8]) -> Report {
+    Decoder::<_, Stereo64>::new(flac).unwrap().verify().unwrap()
+}
+
+#[test]
+fn intact() {
+    let (flac, _) = encode();
+    let report = verify(&flac);
+    assert!(report.is_ok());
+    assert_eq!(report.frames, 10);
+    assert_eq!(report.samples, 40_960);
+    assert_eq!(report.md5_matches(), Some(true));
+    assert!(report.corrupted.is_empty());
+}
+
+#[test]
+fn corrupted() {
+    let (mut flac, offsets) = encode();
+    // Audio data of frame 2, the header CRC-8 of frame 5, and the sync code
+    // of frame 7.
+    flac[offsets[2] as usize + 100] ^= 0x04;
+    flac[offsets[5] as usize + 5] ^= 0x01;
+    flac[offsets[7] as usize] = 0;
+    let report = verify(&flac);
+    assert!(!report.is_ok());
+    assert_eq!(report.frames, 7);
+    assert_eq!(report.samples, 7 * 4096);
+    assert_eq!(report.md5_matches(), Some(false));
+    assert_eq!(report.corrupted.len(), 3);
+    assert_eq!(report.corrupted[0].offset, offsets[2]);
+    assert_eq!(report.corrupted[0].sample, Some(2 * 4096));
+    assert!(matches!(
+        report.corrupted[0].damage,
+        Damage::FrameCrc | Damage::Subframe
+    ));
+    assert_eq!(
+        report.corrupted[1],
+        Corruption {
+            offset: offsets[5],
+            sample: None,
+            damage: Damage::HeaderCrc,
+        }
+    );
+    assert_eq!(
+        report.corrupted[2],
+        Corruption {
+            offset: offsets[7],
+            sample: None,
+            damage: Damage::Header,
+        }
+    );
+
+    // Cut off partway through the last frame.
+    let (flac, offsets) = encode();
+    let report = verify(&flac[..offsets[9] as usize + 50]);
+    assert_eq!(report.frames, 9);
+    assert_eq!(
+        report.corrupted,
+        [Corruption {
+            offset: offsets[9],
+            sample: Some(9 * 4096),
+            damage: Damage::Truncated,
+        }]
+    );
+}
+
+#[test]
+fn resync() {
+    let (flac, offsets) = encode();
+    let intact: Vec<Stereo64> = Decoder::<_, Stereo64>::new(&flac[..])
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    let mut damaged = flac.clone();
+    damaged[offsets[2] as usize + 100] ^= 0x04;
+    damaged[offsets[5] as usize + 5] ^= 0x01;
+    let damaged = &damaged[..offsets[9] as usize + 50];
+
+    // Without resynchronizing, decoding stops at the first corrupted frame.
+    let mut decoder = Decoder::<_, Stereo64>::new(damaged).unwrap();
+    assert!(decoder.by_ref().take(2 * 4096).all(|frame| frame.is_ok()));
+    assert!(decoder.next().unwrap().is_err());
+
+    let mut decoder =
+        Decoder::<_, Stereo64>::new(damaged).unwrap().with_resync();
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.next() {
+        frames.push(frame.unwrap());
+        assert_eq!(decoder.position(), frames.len() as u64);
+    }
+    assert_eq!(frames.len(), 9 * 4096);
+    let silence = Stereo64::default();
+    for (i, (frame, intact)) in frames.iter().zip(&intact).enumerate() {
+        match i / 4096 {
+            2 | 5 => assert_eq!(*frame, silence, "{}", i),
+            _ => assert_eq!(frame, intact, "{}", i),
+        }
+    }
+    let corrupted: Vec<u64> =
+        decoder.corrupted().iter().map(|c| c.offset).collect();
+    assert_eq!(corrupted, [offsets[2], offsets[5], offsets[9]]);
+
+    // Seeking into a gap leaves the rest of the silence.
+    let mut decoder = Decoder::<_, Stereo64>::new(Cursor::new(damaged))
+        .unwrap()
+        .with_resync();
+    decoder.seek(5 * 4096 + 100).unwrap();
+    assert_eq!(decoder.position(), 5 * 4096 + 100);
+    let frames: Vec<Stereo64> = decoder.map(Result::unwrap).collect();
+    assert_eq!(frames.len(), 4 * 4096 - 100);
+    assert_eq!(frames[4095 - 100], silence);
+    assert_eq!(frames[4096 - 100], intact[6 * 4096]);
+}
@@ -42,9 +42,6 @@ extern "C" {
         data: *mut u8,
         max_data_bytes: i32,
     ) -> i32;
-    fn ogg_stream_init(os: *mut OggStreamState, serialno: i32) -> i32;
-    fn ogg_stream_packetin(os: *mut OggStreamState, op: *mut OggPacket) -> i32;
-    fn ogg_stream_pageout(os: *mut OggStreamState, og: *mut OggPage) -> i32;
 }
 
 const NB_FRAMES: usize = 8;
@@ -56,116 +53,6 @@ const DETECT_SIZE: usize = 100;
 const MAX_NEURONS: usize = 32;
 const LEAK_BANDS: usize = 19;
 
-#[repr(C)]
-pub struct OggStreamState {
-    body_data: *mut u8,   /* bytes from packet bodies */
-    body_storage: isize,  /* storage elements allocated */
-    body_fill: isize,     /* elements stored; fill mark */
-    body_returned: isize, /* elements of fill returned */
-
-    lacing_vals: *mut i32, /* The values that will go to the segment table */
-    granule_vals: *mut i64, /* granulepos values for headers. Not compact
-                           this way, but it is simple coupled to the
-                           lacing fifo */
-    lacing_storage: isize,
-    lacing_fill: isize,
-    lacing_packet: isize,
-    lacing_returned: isize,
-
-    header: [u8; 282], /* working space for header encode */
-    header_fill: i32,
-
-    e_o_s: i32, /* set when we have buffered the last packet in the
-                logical bitstream */
-    b_o_s: i32, /* set after we've written the initial page
-                of a logical bitstream */
-    serialno: isize,
-    pageno: i32,
-    packetno: i64, /* sequence number for decode; the framing
-                   knows where there's a hole in the data,
-                   but we need coupling so that the codec
-                   (which is in a seperate abstraction
-                   layer) also knows about the gap */
-    granulepos: i64,
-}
-
-#[repr(C)]
-struct OggPacket {
-    packet: *mut u8,
-    bytes: isize,
-    b_o_s: isize,
-    e_o_s: isize,
-
-    granulepos: i64,
-    packetno: i64,
-}
-
-#[repr(C)]
-struct OggPage {
-    pub header: *mut u8,
-    pub header_len: isize,
-    pub body: *mut u8,
-    pub body_len: isize,
-}
-
-impl OggStreamState {
-    /// Create a new Ogg Stream container.
-    pub fn new() -> Result<Self, ()> {
-        let mut ogg_stream_state = std::mem::MaybeUninit::uninit();
-
-        unsafe {
-            let e = ogg_stream_init(ogg_stream_state.as_mut_ptr(), 0);
-            if e != 0 {
-                Err(())
-            } else {
-                Ok(ogg_stream_state.assume_init())
-            }
-        }
-    }
-
-    /// Encode bitstream with Container.
-    pub fn encode(
-        &mut self,
-        data: &mut [u8],
-        last_sample_pos: i64,
-        packet_no: i64,
-        begin: bool,
-        end: bool,
-    ) {
-        // Create The OggPacket from Opus data.
-        let mut packet = OggPacket {
-            packet: data.as_mut_ptr(),
-            bytes: data.len() as isize,
-            b_o_s: if begin { 1 } else { 0 },
-            e_o_s: if end { 1 } else { 0 },
-            granulepos: last_sample_pos,
-            packetno: packet_no,
-        };
-
-        // Add OggPacket to OggStreamState
-        unsafe {
-            if ogg_stream_packetin(self, &mut packet) != 0 {
-                panic!("Failed encoding OggPacket into OggStreamState");
-            }
-        }
-    }
-
-    pub fn drain(&mut self) -> Option<(&[u8], &[u8])> {
-        // Create an Ogg Page if enough data is available.
-        let mut page = std::mem::MaybeUninit::uninit();
-        unsafe {
-            if ogg_stream_pageout(self, page.as_mut_ptr()) == 0 {
-                None
-            } else {
-                let page = page.assume_init();
-                let head = std::slice::from_raw_parts(page.header, page.header_len as usize);
-                let body = std::slice::from_raw_parts(page.body, page.body_len as usize);
-                Some((head, body))
-            }
-        }
-    }
-}
-
 #[repr(C)]
 struct AnalysisInfo {
     valid: i32,
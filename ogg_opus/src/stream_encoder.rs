@@ -1,3 +1,5 @@
+use ogg_page::Packer;
+
 use crate::ffi::*;
 
 unsafe impl Send for StreamEncoder {}
@@ -5,12 +7,12 @@ unsafe impl Send for StreamEncoder {}
 /// An Ogg Opus Stream Encoder.
 pub struct StreamEncoder {
     encoder: *mut OpusEncoder,
-    stream: OggStreamState,
+    stream: Packer,
     last_sample_pos: i64,
-    packet_no: i64,
-    begin: bool,
     headers: Vec<u8>,
     temp: Vec<u8>,
+    // The last page drained.
+    page: Vec<u8>,
 }
 
 impl StreamEncoder {
@@ -37,14 +39,9 @@ impl StreamEncoder {
         // FIXME: Do we add this garbage octet to finish page?  Probably not.
         // self.temp.push(0);
 
-        self.packet_no += 1;
-
-        self.stream
-            .encode(&mut self.temp, 0, self.packet_no, false, false);
-        let (head, body) = self.stream.drain().unwrap();
-
-        self.headers.extend(head);
-        self.headers.extend(body);
+        // The first packet gets a page of its own.
+        self.stream.packet(&self.temp, 0, false);
+        self.headers.extend(self.stream.take().unwrap());
     }
 
     // Generate the comments header.
@@ -64,13 +61,11 @@ impl StreamEncoder {
         // 4. User comment list length.  FIXME: Add user comments.
         self.temp.extend(&(0u32).to_le_bytes());
 
-        self.packet_no += 1;
-
-        self.stream
-            .encode(&mut self.temp, 0, self.packet_no, false, false);
-        while let Some((head, body)) = self.stream.drain() {
-            self.headers.extend(head);
-            self.headers.extend(body);
+        // Audio starts on a new page.
+        self.stream.packet(&self.temp, 0, false);
+        self.stream.flush();
+        while let Some(page) = self.stream.take() {
+            self.headers.extend(page);
         }
     }
 
@@ -88,7 +83,7 @@ impl StreamEncoder {
 
         // Shouldn't fail.
         let encoder = OpusEncoder::new(48000, 2, false /*not VOIP*/).unwrap();
-        let stream = OggStreamState::new().unwrap();
+        let stream = Packer::new(0);
 
         /*        let comments = Comments::new();
         comments.add("ARTIST", "Someone");
@@ -107,8 +102,7 @@ impl StreamEncoder {
             stream,
             encoder,
             last_sample_pos: -1,
-            packet_no: -1,
-            begin: true,
+            page: vec![],
         };
 
         // Generate header packets.
@@ -120,19 +114,14 @@ impl StreamEncoder {
     /// Encode an Opus packet into a page (get with `while let` on `.page()`).
     pub fn encode(&mut self, samples: &[i16; 1920 * 2]) {
         self.last_sample_pos += 1920;
-        self.packet_no += 1;
 
         let mut packet = [0u8; 4000]; // This is the recommended size by xiph
         let packet_len = OpusEncoder::encode(self.encoder, samples, &mut packet);
-        self.stream.encode(
-            &mut packet[..packet_len],
-            self.last_sample_pos,
-            self.packet_no,
-            self.begin,
+        self.stream.packet(
+            &packet[..packet_len],
+            self.last_sample_pos as u64,
             false, /*not the end*/
         );
-
-        self.begin = false;
     }
 
     /// Get generated header
@@ -142,8 +131,9 @@ impl StreamEncoder {
 
     /// Drain opus pages
     pub fn page(&mut self) -> Option<(&[u8], &[u8])> {
-        let ret = self.stream.drain()?;
+        self.page = self.stream.take()?;
 
-        Some(ret)
+        // Split after the segment table.
+        Some(self.page.split_at(27 + usize::from(self.page[26])))
     }
 }
@@ -0,0 +1,113 @@
+use ogg_opus::StreamEncoder;
+use ogg_page::{Unpacker, NO_GRANULE};
+
+/// Number of samples (for each channel) in each Opus packet.
+const PACKET: u64 = 1920;
+
+/// Beginning of stream page flag.
+const BOS: u8 = 0x02;
+
+/// End of stream page flag.
+const EOS: u8 = 0x04;
+
+/// Header of an Ogg page, with the number of packets ending on the page.
+struct Page {
+    flags: u8,
+    granule: u64,
+    packets: u64,
+}
+
+// Split a physical bitstream into its pages.
+fn pages(mut bytes: &[u8]) -> Vec<Page> {
+    let mut pages = Vec::new();
+    while !bytes.is_empty() {
+        assert_eq!(&bytes[..4], b"OggS");
+        let mut granule = [0; 8];
+        granule.copy_from_slice(&bytes[6..14]);
+        let lacing = &bytes[27..][..bytes[26].into()];
+        let len: usize = lacing.iter().map(|value| usize::from(*value)).sum();
+        pages.push(Page {
+            flags: bytes[5],
+            granule: u64::from_le_bytes(granule),
+            packets: lacing.iter().filter(|value| **value < 255).count() as u64,
+        });
+        bytes = &bytes[27 + lacing.len() + len..];
+    }
+    pages
+}
+
+// Make some noise, so the packets fill several pages.
+fn noise(seed: &mut u32) -> [i16; 1920 * 2] {
+    let mut samples = [0; 1920 * 2];
+    for sample in samples.iter_mut() {
+        *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        *sample = (*seed >> 16) as i16;
+    }
+    samples
+}
+
+#[test]
+fn headers() {
+    let encoder = StreamEncoder::new();
+    let head = pages(encoder.head());
+    // The identification header is alone on the beginning of stream page,
+    // and the comment header is flushed so audio starts on a new page.
+    assert_eq!(head.len(), 2);
+    assert_eq!(head[0].flags, BOS);
+    assert_eq!(head[1].flags, 0);
+    for page in &head {
+        assert_eq!((page.granule, page.packets), (0, 1));
+    }
+
+    let mut unpacker = Unpacker::new(encoder.head());
+    let ident = unpacker.packet().unwrap().unwrap();
+    assert_eq!(ident.len(), 19);
+    assert_eq!(&ident[..8], b"OpusHead");
+    assert_eq!(ident[9], 2);
+    assert_eq!(u16::from_le_bytes([ident[10], ident[11]]), 3840);
+    let rate = u32::from_le_bytes([ident[12], ident[13], ident[14], ident[15]]);
+    assert_eq!(rate, 48_000);
+    let comment = unpacker.packet().unwrap().unwrap();
+    assert_eq!(&comment[..8], b"OpusTags");
+    assert_eq!(&comment[8..12], &13u32.to_le_bytes());
+    assert_eq!(&comment[12..25], b"rust-ogg_opus");
+    assert!(unpacker.packet().unwrap().is_none());
+}
+
+#[test]
+fn pages_and_granules() {
+    let mut encoder = StreamEncoder::new();
+    let mut stream = encoder.head().to_vec();
+    let mut seed = 0;
+    for _ in 0..50 {
+        encoder.encode(&noise(&mut seed));
+        while let Some((header, body)) = encoder.page() {
+            stream.extend(header);
+            stream.extend(body);
+        }
+    }
+
+    // Granule positions count the samples up to the last packet ending on
+    // each page, and the stream is never ended.
+    let audio = pages(&stream[encoder.head().len()..]);
+    assert!(audio.len() > 1);
+    let mut packets = 0;
+    for page in &audio {
+        assert_eq!(page.flags & (BOS | EOS), 0);
+        if page.packets == 0 {
+            assert_eq!(page.granule, NO_GRANULE);
+        } else {
+            packets += page.packets;
+            assert_eq!(page.granule, packets * PACKET - 1);
+        }
+    }
+
+    let mut unpacker = Unpacker::new(&stream[..]);
+    let mut count = 0;
+    while let Some(packet) = unpacker.packet().unwrap() {
+        assert!(!packet.is_empty());
+        count += 1;
+    }
+    assert_eq!(count, 2 + packets);
+    assert_eq!(unpacker.granule(), packets * PACKET - 1);
+}
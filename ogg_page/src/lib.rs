@@ -0,0 +1,23 @@
+// Ogg Page
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+//
+//! Safe, pure Rust Ogg container framing (RFC 3533).
+//!
+//! A [`Packer`] lays out the packets of a logical bitstream into pages, and
+//! an [`Unpacker`] reads the packets of a logical bitstream back out of
+//! pages, checking each page's CRC-32.  Codec mappings (like Ogg Opus and Ogg
+//! FLAC) are left to the codec crates.
+
+mod crc;
+mod packer;
+mod unpacker;
+
+pub use packer::Packer;
+pub use unpacker::Unpacker;
+
+/// Granule position of a page where no packet ends.
+pub const NO_GRANULE: u64 = u64::MAX;
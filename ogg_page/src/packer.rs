@@ -0,0 +1,145 @@
+// Ogg Page
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::collections::VecDeque;
+
+use crate::crc::crc32;
+use crate::NO_GRANULE;
+
+/// Page bodies are ended once they reach this many bytes.
+const PAGE: usize = 4096;
+
+/// Maximum number of segments (lacing values) in a page.
+const SEGMENTS: usize = 255;
+
+/// Lays out the packets of a logical bitstream into pages.
+///
+/// The first packet always gets a page of its own (the beginning of stream
+/// page), as Ogg codec mappings require.  After that, a page is finished
+/// once about 4 KiB of packets are waiting, or when flushed.
+#[derive(Debug)]
+pub struct Packer {
+    serial: u32,
+    // Sequence number of the next page.
+    sequence: u32,
+    // Packets not yet all in pages, with their granule positions.
+    packets: VecDeque<(Vec<u8>, u64)>,
+    // Number of segments of the first packet already in pages.
+    done: usize,
+    // Whether the last packet has been added.
+    end: bool,
+    // Finished pages.
+    pages: VecDeque<Vec<u8>>,
+}
+
+impl Packer {
+    /// Create a packer for the logical bitstream with a serial number.
+    pub fn new(serial: u32) -> Self {
+        Self {
+            serial,
+            sequence: 0,
+            packets: VecDeque::new(),
+            done: 0,
+            end: false,
+            pages: VecDeque::new(),
+        }
+    }
+
+    /// Add a packet, with the granule position at its end.  If `end` is set,
+    /// it's the last packet, and all packets are put into pages, with the
+    /// last page marked as the end of stream.
+    ///
+    /// # Panics
+    /// If a packet is added after the last packet.
+    pub fn packet(&mut self, packet: &[u8], granule: u64, end: bool) {
+        assert!(!self.end, "Packet added after the end of the stream");
+        let first = self.sequence == 0;
+        self.packets.push_back((packet.to_vec(), granule));
+        self.end = end;
+        if first || end {
+            self.flush();
+        }
+        while self.page(false) {}
+    }
+
+    /// Put all waiting packets into pages, so the next packet starts a new
+    /// page.
+    pub fn flush(&mut self) {
+        while self.page(true) {}
+    }
+
+    /// Take the next finished page, if there is one.
+    pub fn take(&mut self) -> Option<Vec<u8>> {
+        self.pages.pop_front()
+    }
+
+    // Finish a page, if there's enough waiting (or anything, when flushing).
+    fn page(&mut self, flush: bool) -> bool {
+        if self.packets.is_empty() {
+            return false;
+        }
+        if !flush {
+            let segments: usize = self
+                .packets
+                .iter()
+                .map(|(packet, _)| packet.len() / 255 + 1)
+                .sum();
+            let bytes: usize =
+                self.packets.iter().map(|(packet, _)| packet.len()).sum();
+            let done = self.done * 255;
+            if segments - self.done < SEGMENTS && bytes - done < PAGE {
+                return false;
+            }
+        }
+        let continued = self.done != 0;
+        let mut lacing = Vec::new();
+        let mut body = Vec::new();
+        let mut granule = NO_GRANULE;
+        while let Some((packet, end)) = self.packets.front() {
+            let segments = packet.len() / 255 + 1;
+            while self.done < segments
+                && lacing.len() < SEGMENTS
+                && body.len() < PAGE
+            {
+                let start = self.done * 255;
+                let stop = packet.len().min(start + 255);
+                lacing.push((stop - start) as u8);
+                body.extend_from_slice(&packet[start..stop]);
+                self.done += 1;
+            }
+            if self.done < segments {
+                break;
+            }
+            granule = *end;
+            self.packets.pop_front();
+            self.done = 0;
+            if lacing.len() == SEGMENTS || body.len() >= PAGE {
+                break;
+            }
+        }
+        let last = self.end && self.packets.is_empty();
+        let flags = u8::from(continued)
+            | u8::from(self.sequence == 0) << 1
+            | u8::from(last) << 2;
+        let mut page = Vec::with_capacity(27 + lacing.len() + body.len());
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(flags);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&self.serial.to_le_bytes());
+        page.extend_from_slice(&self.sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(&body);
+        let crc = crc32(0, &page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        self.pages.push_back(page);
+        self.sequence += 1;
+        true
+    }
+}
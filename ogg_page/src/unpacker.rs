@@ -0,0 +1,130 @@
+// Ogg Page
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Read, Result};
+
+use crate::crc::crc32;
+
+/// Reads the packets of a logical bitstream out of pages.
+///
+/// Packets come from the first logical bitstream in the physical bitstream;
+/// pages of other (multiplexed) logical bitstreams are skipped, and reading
+/// ends at its end of stream page.
+#[derive(Debug)]
+pub struct Unpacker<R: Read> {
+    reader: R,
+    // Serial number of the logical bitstream, once its first page is read.
+    serial: Option<u32>,
+    // Granule position of the last page read.
+    granule: u64,
+    // Bytes of a packet continuing on the next page.
+    partial: Vec<u8>,
+    // Packets read, but not yet taken.
+    packets: VecDeque<Vec<u8>>,
+    // Whether the end of stream page has been read.
+    end: bool,
+}
+
+impl<R: Read> Unpacker<R> {
+    /// Create an unpacker for a physical bitstream.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            serial: None,
+            granule: 0,
+            partial: Vec::new(),
+            packets: VecDeque::new(),
+            end: false,
+        }
+    }
+
+    /// Get the serial number of the logical bitstream, once read.
+    pub fn serial(&self) -> Option<u32> {
+        self.serial
+    }
+
+    /// Get the granule position of the last page read.
+    pub fn granule(&self) -> u64 {
+        self.granule
+    }
+
+    /// Read the next packet, returning `None` at the end of the logical
+    /// bitstream.  Pages that are invalid, or whose CRC-32 doesn't match,
+    /// return an [`InvalidData`](ErrorKind::InvalidData) error.
+    pub fn packet(&mut self) -> Result<Option<Vec<u8>>> {
+        while self.packets.is_empty() {
+            if self.end || !self.page()? {
+                return Ok(None);
+            }
+        }
+        Ok(self.packets.pop_front())
+    }
+
+    // Read the next page, returning false at the end of the reader.
+    fn page(&mut self) -> Result<bool> {
+        let mut header = [0; 27];
+        if !self.fill(&mut header)? {
+            return Ok(false);
+        }
+        if &header[..4] != b"OggS" || header[4] != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid Ogg page"));
+        }
+        let mut lacing = vec![0; header[26].into()];
+        self.reader.read_exact(&mut lacing)?;
+        let len = lacing.iter().map(|value| usize::from(*value)).sum();
+        let mut body = vec![0; len];
+        self.reader.read_exact(&mut body)?;
+        let crc = u32::from_le_bytes([
+            header[22], header[23], header[24], header[25],
+        ]);
+        header[22..26].copy_from_slice(&[0; 4]);
+        let expected = crc32(crc32(crc32(0, &header), &lacing), &body);
+        if crc != expected {
+            return Err(Error::new(ErrorKind::InvalidData, "Ogg CRC mismatch"));
+        }
+        let serial = u32::from_le_bytes([
+            header[14], header[15], header[16], header[17],
+        ]);
+        if *self.serial.get_or_insert(serial) != serial {
+            return Ok(true);
+        }
+        let mut granule = [0; 8];
+        granule.copy_from_slice(&header[6..14]);
+        self.granule = u64::from_le_bytes(granule);
+        // A packet left unfinished on the last page is lost.
+        if header[5] & 0x01 == 0 {
+            self.partial.clear();
+        }
+        let mut start = 0;
+        for value in lacing {
+            let stop = start + usize::from(value);
+            self.partial.extend_from_slice(&body[start..stop]);
+            start = stop;
+            if value < 255 {
+                self.packets.push_back(std::mem::take(&mut self.partial));
+            }
+        }
+        self.end = header[5] & 0x04 != 0;
+        Ok(true)
+    }
+
+    // Fill a buffer, returning false if the reader ends before any bytes.
+    fn fill(&mut self, buffer: &mut [u8]) -> Result<bool> {
+        let mut len = 0;
+        while len < buffer.len() {
+            match self.reader.read(&mut buffer[len..]) {
+                Ok(0) if len == 0 => return Ok(false),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => len += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+}
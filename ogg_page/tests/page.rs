@@ -0,0 +1,90 @@
+// Ogg Page
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::io::ErrorKind;
+
+use ogg_page::{Packer, Unpacker, NO_GRANULE};
+
+// Packets with lengths on either side of segment and page boundaries.
+fn packets() -> Vec<Vec<u8>> {
+    [20, 0, 1, 254, 255, 256, 510, 3000, 5000, 70_000, 100]
+        .iter()
+        .enumerate()
+        .map(|(i, len)| (0..*len).map(|j| (i * 7 + j) as u8).collect())
+        .collect()
+}
+
+// Pack the packets, returning the pages.
+fn pack(serial: u32) -> Vec<Vec<u8>> {
+    let mut packer = Packer::new(serial);
+    let packets = packets();
+    for (i, packet) in packets.iter().enumerate() {
+        packer.packet(packet, i as u64 * 10, i == packets.len() - 1);
+    }
+    std::iter::from_fn(|| packer.take()).collect()
+}
+
+#[test]
+fn roundtrip() {
+    let pages = pack(0x1234_5678);
+    // The first packet is alone on the first page.
+    assert_eq!(pages[0][5], 0x02);
+    assert_eq!(pages[0][26], 1);
+    assert_eq!(pages[0].len(), 27 + 1 + 20);
+    assert_eq!(pages.last().unwrap()[5] & 0x04, 0x04);
+    for (i, page) in pages.iter().enumerate() {
+        assert_eq!(&page[..4], b"OggS");
+        assert_eq!(page[14..18], 0x1234_5678u32.to_le_bytes());
+        assert_eq!(page[18..22], (i as u32).to_le_bytes());
+    }
+    // The 70000 byte packet spans pages that no packet ends on.
+    assert!(pages
+        .iter()
+        .any(|page| page[6..14] == NO_GRANULE.to_le_bytes()
+            && page[5] & 0x01 != 0));
+
+    let stream: Vec<u8> = pages.concat();
+    let mut unpacker = Unpacker::new(&stream[..]);
+    for packet in packets() {
+        assert_eq!(unpacker.packet().unwrap(), Some(packet));
+    }
+    assert_eq!(unpacker.packet().unwrap(), None);
+    assert_eq!(unpacker.serial(), Some(0x1234_5678));
+    assert_eq!(unpacker.granule(), 100);
+}
+
+#[test]
+fn multiplexed() {
+    // Interleave the pages of two logical bitstreams.
+    let (first, second) = (pack(1), pack(2));
+    let mut stream = Vec::new();
+    for (a, b) in first.iter().zip(&second) {
+        stream.extend_from_slice(a);
+        stream.extend_from_slice(b);
+    }
+    let mut unpacker = Unpacker::new(&stream[..]);
+    for packet in packets() {
+        assert_eq!(unpacker.packet().unwrap(), Some(packet));
+    }
+    assert_eq!(unpacker.packet().unwrap(), None);
+}
+
+#[test]
+fn corrupt() {
+    let mut stream = pack(1).concat();
+    stream[100] ^= 1;
+    let mut unpacker = Unpacker::new(&stream[..]);
+    assert_eq!(unpacker.packet().unwrap().unwrap().len(), 20);
+    let error = unpacker.packet().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let stream = pack(1).concat();
+    let mut unpacker = Unpacker::new(&stream[..60]);
+    unpacker.packet().unwrap();
+    let error = unpacker.packet().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,125 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Encoder throughput for each PCM format, writing to an unbuffered file.
+//!
+//! Run with `cargo bench`.  Besides throughput, the number of `write()` calls
+//! is reported: the encoder should make one per block of samples rather than
+//! one per sample.
+
+use std::fs::File;
+use std::io::{Result, Write};
+use std::time::Instant;
+
+use fon::chan::Ch32;
+use fon::stereo::Stereo32;
+use fon::Audio;
+use raw_audio::pcm::*;
+use raw_audio::Encoder;
+
+/// Number of frames to encode (10 seconds at 48 kHz).
+const FRAMES: usize = 480_000;
+
+/// File writer that counts calls to `write()`.
+struct Counted {
+    file: File,
+    writes: usize,
+}
+
+impl Write for Counted {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.writes += 1;
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+fn bench<P: Pcm>(name: &str, pcm: P, audio: &Audio<Stereo32>) -> Result<()> {
+    let path = std::env::temp_dir().join("raw_audio_bench.pcm");
+    let file = File::create(&path)?;
+    let start = Instant::now();
+    let mut encoder = Encoder::new(Counted { file, writes: 0 }, pcm);
+    encoder.encode(audio)?;
+    let counted = encoder.into_inner()?;
+    let elapsed = start.elapsed().as_secs_f64();
+    let bytes = counted.file.metadata()?.len() as f64;
+    println!(
+        "{:<10} {:>8.1} MB/s {:>8.2} Mframes/s {:>8} writes",
+        name,
+        bytes / elapsed / 1_000_000.0,
+        FRAMES as f64 / elapsed / 1_000_000.0,
+        counted.writes,
+    );
+    std::fs::remove_file(path)
+}
+
+fn main() -> Result<()> {
+    let frames: Vec<_> = (0..FRAMES)
+        .map(|i| {
+            let t = i as f32 / 48_000.0;
+            let left = (t * 440.0 * std::f32::consts::TAU).sin() * 0.5;
+            let right = (t * 660.0 * std::f32::consts::TAU).sin() * 0.5;
+            Stereo32::new(Ch32::new(left), Ch32::new(right))
+        })
+        .collect();
+    let audio = Audio::with_frames(48_000, frames);
+
+    bench("U8", U8, &audio)?;
+    bench("S8", S8, &audio)?;
+    bench("MuLaw", MuLaw, &audio)?;
+    bench("ALaw", ALaw, &audio)?;
+    bench("U16Le", U16Le, &audio)?;
+    bench("U16Be", U16Be, &audio)?;
+    bench("S16Le", S16Le, &audio)?;
+    bench("S16Be", S16Be, &audio)?;
+    bench("U24Le", U24Le, &audio)?;
+    bench("U24Be", U24Be, &audio)?;
+    bench("S24Le", S24Le, &audio)?;
+    bench("S24Be", S24Be, &audio)?;
+    bench("U32Le", U32Le, &audio)?;
+    bench("U32Be", U32Be, &audio)?;
+    bench("S32Le", S32Le, &audio)?;
+    bench("S32Be", S32Be, &audio)?;
+    bench("S24_32Le", S24_32Le, &audio)?;
+    bench("S24_32Be", S24_32Be, &audio)?;
+    bench("S24_32RLe", S24_32RLe, &audio)?;
+    bench("S24_32RBe", S24_32RBe, &audio)?;
+    bench("U20Le", U20Le, &audio)?;
+    bench("U20Be", U20Be, &audio)?;
+    bench("S20Le", S20Le, &audio)?;
+    bench("S20Be", S20Be, &audio)?;
+    bench("U12Le", U12Le, &audio)?;
+    bench("U12Be", U12Be, &audio)?;
+    bench("S12Le", S12Le, &audio)?;
+    bench("S12Be", S12Be, &audio)?;
+    bench("U4Le", U4Le, &audio)?;
+    bench("U4Be", U4Be, &audio)?;
+    bench("S4Le", S4Le, &audio)?;
+    bench("S4Be", S4Be, &audio)?;
+    bench("F16Le", F16Le, &audio)?;
+    bench("F16Be", F16Be, &audio)?;
+    bench("BF16Le", BF16Le, &audio)?;
+    bench("BF16Be", BF16Be, &audio)?;
+    bench("F32Le", F32Le, &audio)?;
+    bench("F32Be", F32Be, &audio)?;
+    bench("F64Le", F64Le, &audio)?;
+    bench("F64Be", F64Be, &audio)?;
+    bench("ImaAdpcmLe", ImaAdpcmLe, &audio)?;
+    bench("ImaAdpcmBe", ImaAdpcmBe, &audio)?;
+    bench("G726_16Le", G726_16Le, &audio)?;
+    bench("G726_16Be", G726_16Be, &audio)?;
+    bench("G726_24Le", G726_24Le, &audio)?;
+    bench("G726_24Be", G726_24Be, &audio)?;
+    bench("G726_32Le", G726_32Le, &audio)?;
+    bench("G726_32Be", G726_32Be, &audio)?;
+    bench("G726_40Le", G726_40Le, &audio)?;
+    bench("G726_40Be", G726_40Be, &audio)?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Conversion between byte slices and frame slices, without I/O.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use fon::chan::{Ch64, Channel};
+use fon::Frame;
+
+use crate::pcm::Pcm;
+use crate::Clip;
+
+/// Amounts converted by [`decode_into()`] or [`encode_into()`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Converted {
+    /// Number of bytes read or written.
+    pub bytes: usize,
+    /// Number of frames written or read.
+    pub frames: usize,
+}
+
+/// Codec state of one stream converted by [`decode_into()`] or
+/// [`encode_into()`], kept from one call to the next.  Creating it allocates,
+/// so do that before starting the audio thread, and use a separate one for
+/// each stream and direction.
+pub struct Context<F: Frame, P: Pcm> {
+    // Codec state of each channel.
+    states: Vec<P::State>,
+    _phantom: PhantomData<(F, P)>,
+}
+
+impl<F: Frame, P: Pcm> Context<F, P> {
+    /// Create codec state for the start of a stream.
+    pub fn new(pcm: P) -> Self {
+        let _ = pcm;
+        Self {
+            states: vec![P::State::default(); F::CHAN_COUNT],
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Decode interleaved RAW audio from `bytes` into `frames`, without
+/// allocating or performing I/O, so it can run on an audio thread.
+///
+/// As many frames as fit in both slices are decoded.  For formats that pack
+/// multiple samples together, only runs of whole frames that end on a group
+/// boundary are decoded; the rest are left for the next call, which
+/// continues the stream with the codec state in `context`.
+pub fn decode_into<F: Frame, P: Pcm>(
+    context: &mut Context<F, P>,
+    bytes: &[u8],
+    frames: &mut [F],
+) -> Converted {
+    let (block_frames, block_bytes) = block::<F, P>();
+    let blocks = (bytes.len() / block_bytes).min(frames.len() / block_frames);
+    let converted = Converted {
+        bytes: blocks * block_bytes,
+        frames: blocks * block_frames,
+    };
+    let mut group = [F::Chan::default(); 8];
+    let group = &mut group[..P::SAMPLES];
+    let mut samples = frames[..converted.frames]
+        .iter_mut()
+        .flat_map(|frame| frame.channels_mut().iter_mut());
+    for (i, bytes) in bytes[..converted.bytes].chunks(P::SIZE).enumerate() {
+        let channel = i * P::SAMPLES % F::CHAN_COUNT;
+        P::decode_with(&mut context.states, channel, bytes, group);
+        for (decoded, sample) in group.iter().zip(&mut samples) {
+            *sample = *decoded;
+        }
+    }
+    converted
+}
+
+/// Encode `frames` into interleaved RAW audio in `bytes`, without allocating
+/// or performing I/O.  Samples out of range are clamped, like with
+/// [`Clip::Hard`].
+///
+/// As many frames as fit in both slices are encoded.  For formats that pack
+/// multiple samples together, only runs of whole frames that end on a group
+/// boundary are encoded; the rest are left for the next call, which
+/// continues the stream with the codec state in `context`.
+pub fn encode_into<F: Frame, P: Pcm>(
+    context: &mut Context<F, P>,
+    frames: &[F],
+    bytes: &mut [u8],
+) -> Converted {
+    let (block_frames, block_bytes) = block::<F, P>();
+    let blocks = (bytes.len() / block_bytes).min(frames.len() / block_frames);
+    let converted = Converted {
+        bytes: blocks * block_bytes,
+        frames: blocks * block_frames,
+    };
+    let mut group = [F::Chan::default(); 8];
+    let group = &mut group[..P::SAMPLES];
+    let mut samples = frames[..converted.frames]
+        .iter()
+        .flat_map(|frame| frame.channels().iter());
+    let chunks = bytes[..converted.bytes].chunks_mut(P::SIZE);
+    for (i, bytes) in chunks.enumerate() {
+        for (sample, chan) in group.iter_mut().zip(&mut samples) {
+            *sample = match Clip::Hard.apply(chan.to_f64()) {
+                Ok(Some(clipped)) => F::Chan::from(Ch64::new(clipped)),
+                _ => *chan,
+            };
+        }
+        let channel = i * P::SAMPLES % F::CHAN_COUNT;
+        P::encode_with(&mut context.states, channel, group, bytes);
+    }
+    converted
+}
+
+// Get the number of frames and bytes in the smallest run of whole frames that
+// fills whole groups of samples.
+fn block<F: Frame, P: Pcm>() -> (usize, usize) {
+    let samples = crate::lcm(F::CHAN_COUNT, P::SAMPLES);
+    (samples / F::CHAN_COUNT, samples / P::SAMPLES * P::SIZE)
+}
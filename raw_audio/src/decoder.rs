@@ -5,16 +5,27 @@
 // (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
 // LICENSE_BOOST_1_0.txt)
 
-use std::convert::TryInto;
-use std::io::Read;
-use std::marker::PhantomData;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::marker::PhantomData;
 
-use fon::chan::{Ch16, Ch32, Ch64, Ch8};
+use fon::chan::{Ch16, Ch32, Ch64, Ch8, Channel};
 use fon::Frame;
 
+use crate::g726::{RATE_16, RATE_24, RATE_32, RATE_40};
+use crate::half::{bf16_to_f32, f16_to_f32};
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom};
+use crate::mix::{self, Mixer};
+
+use crate::pcm::seal::Seal;
 use crate::pcm::{
-    ALaw, F32Be, F32Le, F64Be, F64Le, MuLaw, Pcm, S16Be, S16Le, S24Be, S24Le,
-    S32Be, S32Le, U16Be, U16Le, U24Be, U24Le, U32Be, U32Le, S8, U8,
+    ALaw, BF16Be, BF16Le, F16Be, F16Le, F32Be, F32Le, F64Be, F64Le, G726_16Be,
+    G726_16Le, G726_24Be, G726_24Le, G726_32Be, G726_32Le, G726_40Be,
+    G726_40Le, ImaAdpcmBe, ImaAdpcmLe, MuLaw, Pcm, S12Be, S12Le, S16Be, S16Le,
+    S20Be, S20Le, S24Be, S24Le, S24_32Be, S24_32Le, S24_32RBe, S24_32RLe,
+    S32Be, S32Le, S4Be, S4Le, U12Be, U12Le, U16Be, U16Le, U20Be, U20Le, U24Be,
+    U24Le, U32Be, U32Le, U4Be, U4Le, S8, U8,
 };
 
 const ALAW_DECOMP_TABLE: [i16; 256] = [
@@ -57,7 +68,7 @@ const MULAW_DECOMP_TABLE: [i16; 256] = [
     -1052, -988, -924, -876, -844, -812, -780, -748, -716, -684, -652, -620,
     -588, -556, -524, -492, -460, -428, -396, -372, -356, -340, -324, -308,
     -292, -276, -260, -244, -228, -212, -196, -180, -164, -148, -132, -120,
-    -112, -104, -96, -88, -80, -72, -64, -56, -48, -40, -32, -24, -16, -8, -1,
+    -112, -104, -96, -88, -80, -72, -64, -56, -48, -40, -32, -24, -16, -8, 0,
     32124, 31100, 30076, 29052, 28028, 27004, 25980, 24956, 23932, 22908,
     21884, 20860, 19836, 18812, 17788, 16764, 15996, 15484, 14972, 14460,
     13948, 13436, 12924, 12412, 11900, 11388, 10876, 10364, 9852, 9340, 8828,
@@ -70,19 +81,147 @@ const MULAW_DECOMP_TABLE: [i16; 256] = [
     148, 132, 120, 112, 104, 96, 88, 80, 72, 64, 56, 48, 40, 32, 24, 16, 8, 0,
 ];
 
+/// Decoding of RAW samples.
+pub trait Decode: Seal {
+    /// Decode a group of `Pcm::SAMPLES` samples from `Pcm::SIZE` bytes.
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]);
+
+    /// Decode a group of samples with each channel's codec state, where the
+    /// first sample belongs to `channel` and the rest follow in channel order.
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let _ = (states, channel);
+        Self::decode(bytes, samples);
+    }
+}
+
 /// Decoder for RAW audio
-pub struct Decoder<R: Read, F: Frame, P: Pcm>(R, PhantomData<(F, P)>);
+pub struct Decoder<R: Read, F: Frame, P: Pcm> {
+    reader: R,
+    // Number of interleaved channels in the RAW audio.
+    channels: usize,
+    // Bytes of the frame currently being decoded.
+    buffer: Vec<u8>,
+    // Channels of the frame currently being decoded, followed by samples of
+    // the next frame packed in the same bytes.
+    decoded: Vec<F::Chan>,
+    // Number of samples of the next frame already decoded.
+    leftover: usize,
+    // Codec state of each channel.
+    states: Vec<P::State>,
+    // Whether to replace NaN.
+    sanitize: bool,
+    // Number of frames decoded since the start of the audio.
+    position: u64,
+    // Number of bytes read since the start of the audio.
+    consumed: u64,
+    // Mixing from RAW audio channels to `F` channels, if they differ.
+    mixer: Option<Mixer>,
+    _phantom: PhantomData<(F, P)>,
+}
 
 impl<R: Read, F: Frame, P: Pcm> Decoder<R, F, P> {
     /// Create a new RAW audio decoder.
     pub fn new<H: Into<f64>>(reader: R, pcm: P) -> Self {
+        Self::with_channels(reader, pcm, F::CHAN_COUNT)
+    }
+
+    /// Create a new RAW audio decoder for audio with a channel count that may
+    /// differ from `F`'s.  Channels are up/down-mixed following the channel
+    /// order documented at the [crate root](crate); RAW audio with more than
+    /// 8 channels is mapped by channel index.  Use
+    /// [`next_channels()`](Decoder::next_channels) to get every channel
+    /// without mixing.
+    ///
+    /// # Panics
+    /// If `channels` is zero.
+    pub fn with_channels(reader: R, pcm: P, channels: usize) -> Self {
         let _ = pcm;
-        Self(reader, PhantomData)
+        assert_ne!(channels, 0, "RAW audio must have at least one channel");
+        Self {
+            reader,
+            channels,
+            buffer: vec![0; channels.div_ceil(P::SAMPLES) * P::SIZE],
+            decoded: vec![F::Chan::default(); channels + P::SAMPLES - 1],
+            leftover: 0,
+            states: vec![P::State::default(); channels],
+            sanitize: false,
+            position: 0,
+            consumed: 0,
+            mixer: Mixer::new(channels, F::CHAN_COUNT),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Set whether NaN samples of float RAW audio decode as silence (off by
+    /// default).  Otherwise NaN is passed on in `Ch32` and `Ch64` channels;
+    /// other channels can't hold NaN, so it's always silence in those.
+    /// Infinite samples always decode as full scale.
+    pub fn with_sanitize(mut self, sanitize: bool) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Get the number of channels in the RAW audio.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Get the index of the next frame to be decoded.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Decode the next frame without mixing, returning one channel for each
+    /// of the RAW audio's [`channels()`](Decoder::channels).  Returns `None`
+    /// at the end of the audio.
+    pub fn next_channels(&mut self) -> Option<io::Result<&[F::Chan]>> {
+        let channels = self.channels;
+        // Start with samples left over from the previous frame's bytes.
+        let leftover = self.leftover;
+        self.decoded.copy_within(channels..channels + leftover, 0);
+        // Groups of many samples may hold more than one frame.
+        if leftover >= channels {
+            self.leftover = leftover - channels;
+            self.position += 1;
+            return Some(Ok(&self.decoded[..channels]));
+        }
+        let groups = (channels - leftover).div_ceil(P::SAMPLES);
+        let buffer = &mut self.buffer[..groups * P::SIZE];
+        // Detect end of audio at a frame boundary.
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.reader.read(&mut buffer[read..]) {
+                Ok(0) if read == 0 => return None,
+                Ok(0) => return Some(Err(ErrorKind::UnexpectedEof.into())),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        let chunks = self.decoded[leftover..]
+            .chunks_mut(P::SAMPLES)
+            .zip(buffer.chunks(P::SIZE));
+        for (i, (samples, bytes)) in chunks.enumerate() {
+            let channel = (leftover + i * P::SAMPLES) % channels;
+            P::decode_with(&mut self.states, channel, bytes, samples);
+            if self.sanitize {
+                samples.iter_mut().for_each(sanitize);
+            }
+        }
+        self.leftover = leftover + groups * P::SAMPLES - channels;
+        self.consumed += buffer.len() as u64;
+        self.position += 1;
+        Some(Ok(&self.decoded[..channels]))
     }
 
     // FIXME: Is this API needed?
     /*/// Decode the entire file all at once, appending to an audio buffer.
-    pub fn decode(&mut self, audio: &mut Audio<F>) -> std::io::Result<()> {
+    pub fn decode(&mut self, audio: &mut Audio<F>) -> io::Result<()> {
         todo!()
     }
 
@@ -90,399 +229,714 @@ impl<R: Read, F: Frame, P: Pcm> Decoder<R, F, P> {
     pub fn stream<S: Sink<F>>(
         &mut self,
         sink: &mut S,
-    ) -> std::io::Result<usize> {
+    ) -> io::Result<usize> {
         todo!()
     }*/
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U8> {
-    type Item = std::io::Result<F>;
+impl<R: Read + Seek, F: Frame, P: Pcm> Decoder<R, F, P> {
+    /// Get the number of whole frames in the audio, from where the reader was
+    /// when the decoder was created to the end.
+    pub fn frame_count(&mut self) -> io::Result<u64> {
+        let current = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(current))?;
+        let bytes = end - (current - self.consumed);
+        let samples = bytes / P::SIZE as u64 * P::SAMPLES as u64;
+        Ok(samples / self.channels as u64)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len()]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.iter()) {
-            *chan = F::Chan::from(Ch8::new((buffer ^ 0x80) as i8));
-        }
-        Some(Ok(F::from_channels(channels)))
+    /// Move to a frame, so it's decoded next.  Frames are counted from where
+    /// the reader was when the decoder was created.  Seeking past the end is
+    /// allowed; decoding then ends right away.
+    ///
+    /// For formats that pack multiple samples together, decoding restarts at
+    /// the closest earlier group boundary, and ADPCM formats restart from the
+    /// start of the audio, discarding frames up to `frame`.
+    pub fn seek(&mut self, frame: u64) -> io::Result<()> {
+        let start = self.reader.stream_position()? - self.consumed;
+        let channels = self.channels as u64;
+        // Frames and bytes in the smallest run of frames that fills groups.
+        let samples = crate::lcm(self.channels, P::SAMPLES) as u64;
+        let (block_frames, block_bytes) = (
+            samples / channels,
+            samples / P::SAMPLES as u64 * P::SIZE as u64,
+        );
+        // Codec state carries over between samples of ADPCM formats.
+        let block = if P::STATEFUL {
+            for state in self.states.iter_mut() {
+                *state = P::State::default();
+            }
+            0
+        } else {
+            frame / block_frames
+        };
+        self.reader
+            .seek(SeekFrom::Start(start + block * block_bytes))?;
+        self.consumed = block * block_bytes;
+        self.position = block * block_frames;
+        self.leftover = 0;
+        while self.position < frame {
+            match self.next_channels() {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+        self.position = frame;
+        Ok(())
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S8> {
-    type Item = std::io::Result<F>;
+impl<R: Read, F: Frame, P: Pcm> Iterator for Decoder<R, F, P> {
+    type Item = io::Result<F>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len()]) {
+        if let Err(e) = self.next_channels()? {
             return Some(Err(e));
         }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.iter()) {
-            *chan = F::Chan::from(Ch8::new(*buffer as i8));
-        }
-        Some(Ok(F::from_channels(channels)))
+        let channels = &self.decoded[..self.channels];
+        Some(Ok(mix::frame(&self.mixer, channels)))
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, MuLaw> {
-    type Item = std::io::Result<F>;
+// Split a group of two packed `bits`-bit samples, with the first sample in the
+// low bits for little endian packing.
+fn unpack(group: u64, bits: u32, little: bool) -> [u32; 2] {
+    let mask = (1 << bits) - 1;
+    let lo = (group & mask) as u32;
+    let hi = ((group >> bits) & mask) as u32;
+    if little {
+        [lo, hi]
+    } else {
+        [hi, lo]
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len()]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.iter()) {
-            *chan =
-                F::Chan::from(Ch16::new(MULAW_DECOMP_TABLE[*buffer as usize]));
-        }
-        Some(Ok(F::from_channels(channels)))
+// Split ADPCM code words packed into a group of bytes, with the first code word
+// in the low bits for little endian packing.
+fn codes(bytes: &[u8], bits: usize, little: bool) -> impl Iterator<Item = u8> {
+    let count = bytes.len() * 8 / bits;
+    let group = if little {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |group, b| group << 8 | u64::from(*b))
+    } else {
+        bytes
+            .iter()
+            .fold(0u64, |group, b| group << 8 | u64::from(*b))
+    };
+    (0..count).map(move |i| {
+        let shift = if little { i } else { count - 1 - i } * bits;
+        (group >> shift) as u8 & ((1 << bits) - 1)
+    })
+}
+
+// Decode ADPCM code words, each with its channel's state.
+fn adpcm<S, C: Channel>(
+    states: &mut [S],
+    channel: usize,
+    codes: impl Iterator<Item = u8>,
+    samples: &mut [C],
+    decode: impl Fn(&mut S, u8) -> i16,
+) {
+    for ((i, sample), code) in samples.iter_mut().enumerate().zip(codes) {
+        let state = &mut states[(channel + i) % states.len()];
+        *sample = C::from(Ch16::new(decode(state, code)));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, ALaw> {
-    type Item = std::io::Result<F>;
+// Replace a NaN sample with silence.
+fn sanitize<C: Channel>(sample: &mut C) {
+    if sample.to_f64().is_nan() {
+        *sample = C::default();
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len()]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.iter()) {
-            *chan =
-                F::Chan::from(Ch16::new(ALAW_DECOMP_TABLE[*buffer as usize]));
-        }
-        Some(Ok(F::from_channels(channels)))
+// Convert a 32-bit float sample, clamped to -1 to 1 except for NaN, which
+// `sanitize()` may replace later.
+fn float<C: Channel>(sample: f32) -> C {
+    if sample.is_nan() {
+        nan()
+    } else {
+        C::from(Ch32::new(sample))
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U16Le> {
-    type Item = std::io::Result<F>;
+// Convert a 64-bit float sample, like `float()`.
+fn double<C: Channel>(sample: f64) -> C {
+    if sample.is_nan() {
+        nan()
+    } else {
+        C::from(Ch64::new(sample))
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 2]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(2)) {
-            *chan = F::Chan::from(Ch16::new(
-                (u16::from_le_bytes(buffer.try_into().unwrap()) ^ 0x8000u16)
-                    as i16,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+// NaN, or silence for channels that can't hold NaN.
+fn nan<C: Channel>() -> C {
+    let nan = C::from_f64(f64::NAN);
+    if nan.to_f64().is_nan() {
+        nan
+    } else {
+        C::default()
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U16Be> {
-    type Item = std::io::Result<F>;
+// Decode a `bits`-bit two's complement integer sample.
+fn signed<C: Channel>(sample: u32, bits: u32) -> C {
+    let sample = ((sample << (32 - bits)) as i32) >> (32 - bits);
+    let max = (1u32 << (bits - 1)) as f64 - 0.5;
+    C::from(Ch64::new((sample as f64 + 0.5) / max))
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 2]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(2)) {
-            *chan = F::Chan::from(Ch16::new(
-                (u16::from_be_bytes(buffer.try_into().unwrap()) ^ 0x8000u16)
-                    as i16,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+// Decode a `bits`-bit offset binary integer sample.
+fn unsigned<C: Channel>(sample: u32, bits: u32) -> C {
+    signed(sample ^ (1 << (bits - 1)), bits)
+}
+
+impl Decode for U8 {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch8::new((bytes[0] ^ 0x80) as i8));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S16Le> {
-    type Item = std::io::Result<F>;
+impl Decode for S8 {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch8::new(bytes[0] as i8));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 2]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(2)) {
-            *chan = F::Chan::from(Ch16::new(i16::from_le_bytes(
-                buffer.try_into().unwrap(),
-            )));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for MuLaw {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch16::new(MULAW_DECOMP_TABLE[bytes[0] as usize]));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S16Be> {
-    type Item = std::io::Result<F>;
+impl Decode for ALaw {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch16::new(ALAW_DECOMP_TABLE[bytes[0] as usize]));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 2]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(2)) {
-            *chan = F::Chan::from(Ch16::new(i16::from_be_bytes(
-                buffer.try_into().unwrap(),
-            )));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for U16Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch16::new(
+            (u16::from_le_bytes(bytes.try_into().unwrap()) ^ 0x8000u16) as i16,
+        ));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U24Le> {
-    type Item = std::io::Result<F>;
+impl Decode for U16Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch16::new(
+            (u16::from_be_bytes(bytes.try_into().unwrap()) ^ 0x8000u16) as i16,
+        ));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 3]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(3)) {
-            // Sign extend!
-            let buffer = [
-                buffer[0],
-                buffer[1],
-                buffer[2] ^ 0x80,
-                if buffer[2] & 0x80 == 0 { 0xFF } else { 0x00 },
-            ];
-            *chan = F::Chan::from(Ch64::new(
-                (i32::from_le_bytes(buffer) as f64 + 0.5) / 8388607.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for S16Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] =
+            C::from(Ch16::new(i16::from_le_bytes(bytes.try_into().unwrap())));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U24Be> {
-    type Item = std::io::Result<F>;
+impl Decode for S16Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] =
+            C::from(Ch16::new(i16::from_be_bytes(bytes.try_into().unwrap())));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 3]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(3)) {
-            // Sign extend!
-            let buffer = [
-                if buffer[2] & 0x80 == 0 { 0xFF } else { 0x00 },
-                buffer[0] ^ 0x80,
-                buffer[1],
-                buffer[2],
-            ];
-            *chan = F::Chan::from(Ch64::new(
-                (i32::from_le_bytes(buffer) as f64 + 0.5) / 8388607.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for U24Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        // Sign extend!
+        let bytes = [
+            bytes[0],
+            bytes[1],
+            bytes[2] ^ 0x80,
+            if bytes[2] & 0x80 == 0 { 0xFF } else { 0x00 },
+        ];
+        samples[0] = C::from(Ch64::new(
+            (i32::from_le_bytes(bytes) as f64 + 0.5) / 8388607.5,
+        ));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S24Le> {
-    type Item = std::io::Result<F>;
+impl Decode for U24Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        // Sign extend!
+        let bytes = [
+            if bytes[0] & 0x80 == 0 { 0xFF } else { 0x00 },
+            bytes[0] ^ 0x80,
+            bytes[1],
+            bytes[2],
+        ];
+        samples[0] = C::from(Ch64::new(
+            (i32::from_be_bytes(bytes) as f64 + 0.5) / 8388607.5,
+        ));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 3]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(3)) {
-            // Sign extend!
-            let buffer = [
-                buffer[0],
-                buffer[1],
-                buffer[2],
-                if buffer[2] & 0x80 != 0 { 0xFF } else { 0x00 },
-            ];
-            *chan = F::Chan::from(Ch64::new(
-                (i32::from_le_bytes(buffer) as f64 + 0.5) / 8388607.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for S24Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        // Sign extend!
+        let bytes = [
+            bytes[0],
+            bytes[1],
+            bytes[2],
+            if bytes[2] & 0x80 != 0 { 0xFF } else { 0x00 },
+        ];
+        samples[0] = C::from(Ch64::new(
+            (i32::from_le_bytes(bytes) as f64 + 0.5) / 8388607.5,
+        ));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S24Be> {
-    type Item = std::io::Result<F>;
+impl Decode for S24Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        // Sign extend!
+        let bytes = [
+            if bytes[0] & 0x80 != 0 { 0xFF } else { 0x00 },
+            bytes[0],
+            bytes[1],
+            bytes[2],
+        ];
+        samples[0] = C::from(Ch64::new(
+            (i32::from_be_bytes(bytes) as f64 + 0.5) / 8388607.5,
+        ));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 3]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(3)) {
-            // Sign extend!
-            let buffer = [
-                if buffer[2] & 0x80 != 0 { 0xFF } else { 0x00 },
-                buffer[0],
-                buffer[1],
-                buffer[2],
-            ];
-            *chan = F::Chan::from(Ch64::new(
-                (i32::from_le_bytes(buffer) as f64 + 0.5) / 8388607.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for U32Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch64::new(
+            ((u32::from_le_bytes(bytes.try_into().unwrap()) ^ (1 << 31)) as i32
+                as f64
+                + 0.5)
+                / 2147483647.5,
+        ));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U32Le> {
-    type Item = std::io::Result<F>;
+impl Decode for U32Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch64::new(
+            ((u32::from_be_bytes(bytes.try_into().unwrap()) ^ (1 << 31)) as i32
+                as f64
+                + 0.5)
+                / 2147483647.5,
+        ));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 4]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(4)) {
-            *chan = F::Chan::from(Ch64::new(
-                ((u32::from_le_bytes(buffer.try_into().unwrap()) ^ (1 << 31))
-                    as f64
-                    + 0.5)
-                    / 2147483647.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for S32Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch64::new(
+            (i32::from_le_bytes(bytes.try_into().unwrap()) as f64 + 0.5)
+                / 2147483647.5,
+        ));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, U32Be> {
-    type Item = std::io::Result<F>;
+impl Decode for S32Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = C::from(Ch64::new(
+            (i32::from_be_bytes(bytes.try_into().unwrap()) as f64 + 0.5)
+                / 2147483647.5,
+        ));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 4]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(4)) {
-            *chan = F::Chan::from(Ch64::new(
-                ((u32::from_be_bytes(buffer.try_into().unwrap()) ^ (1 << 31))
-                    as f64
-                    + 0.5)
-                    / 2147483647.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for F16Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let half = u16::from_le_bytes(bytes.try_into().unwrap());
+        samples[0] = float(f16_to_f32(half));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S32Le> {
-    type Item = std::io::Result<F>;
+impl Decode for F16Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let half = u16::from_be_bytes(bytes.try_into().unwrap());
+        samples[0] = float(f16_to_f32(half));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 4]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(2)) {
-            *chan = F::Chan::from(Ch64::new(
-                (u32::from_le_bytes(buffer.try_into().unwrap()) as f64 + 0.5)
-                    / 2147483647.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for BF16Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let half = u16::from_le_bytes(bytes.try_into().unwrap());
+        samples[0] = float(bf16_to_f32(half));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, S32Be> {
-    type Item = std::io::Result<F>;
+impl Decode for BF16Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let half = u16::from_be_bytes(bytes.try_into().unwrap());
+        samples[0] = float(bf16_to_f32(half));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 4]) {
-            return Some(Err(e));
-        }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(2)) {
-            *chan = F::Chan::from(Ch64::new(
-                (u32::from_be_bytes(buffer.try_into().unwrap()) as f64 + 0.5)
-                    / 2147483647.5,
-            ));
-        }
-        Some(Ok(F::from_channels(channels)))
+impl Decode for F32Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = float(f32::from_le_bytes(bytes.try_into().unwrap()));
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, F32Le> {
-    type Item = std::io::Result<F>;
+impl Decode for F32Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = float(f32::from_be_bytes(bytes.try_into().unwrap()));
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 4]) {
-            return Some(Err(e));
+impl Decode for F64Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = double(f64::from_le_bytes(bytes.try_into().unwrap()));
+    }
+}
+
+impl Decode for F64Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        samples[0] = double(f64::from_be_bytes(bytes.try_into().unwrap()));
+    }
+}
+
+impl Decode for S24_32Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let sample = u32::from_le_bytes(bytes.try_into().unwrap());
+        samples[0] = signed(sample >> 8, 24);
+    }
+}
+
+impl Decode for S24_32Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let sample = u32::from_be_bytes(bytes.try_into().unwrap());
+        samples[0] = signed(sample >> 8, 24);
+    }
+}
+
+impl Decode for S24_32RLe {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let sample = u32::from_le_bytes(bytes.try_into().unwrap());
+        samples[0] = signed(sample & 0xFF_FFFF, 24);
+    }
+}
+
+impl Decode for S24_32RBe {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let sample = u32::from_be_bytes(bytes.try_into().unwrap());
+        samples[0] = signed(sample & 0xFF_FFFF, 24);
+    }
+}
+
+impl Decode for U20Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = u64::from_le_bytes([
+            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], 0, 0, 0,
+        ]);
+        let group = unpack(group, 20, true);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = unsigned(*packed, 20);
         }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(4)) {
-            *chan = F::Chan::from(Ch32::new(f32::from_le_bytes(
-                buffer.try_into().unwrap(),
-            )));
+    }
+}
+
+impl Decode for U20Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = u64::from_be_bytes([
+            0, 0, 0, bytes[0], bytes[1], bytes[2], bytes[3], bytes[4],
+        ]);
+        let group = unpack(group, 20, false);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = unsigned(*packed, 20);
         }
-        Some(Ok(F::from_channels(channels)))
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, F32Be> {
-    type Item = std::io::Result<F>;
+impl Decode for S20Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = u64::from_le_bytes([
+            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], 0, 0, 0,
+        ]);
+        let group = unpack(group, 20, true);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = signed(*packed, 20);
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 4]) {
-            return Some(Err(e));
+impl Decode for S20Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = u64::from_be_bytes([
+            0, 0, 0, bytes[0], bytes[1], bytes[2], bytes[3], bytes[4],
+        ]);
+        let group = unpack(group, 20, false);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = signed(*packed, 20);
         }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(4)) {
-            *chan = F::Chan::from(Ch32::new(f32::from_be_bytes(
-                buffer.try_into().unwrap(),
-            )));
+    }
+}
+
+impl Decode for U12Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group =
+            u64::from_le_bytes([bytes[0], bytes[1], bytes[2], 0, 0, 0, 0, 0]);
+        let group = unpack(group, 12, true);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = unsigned(*packed, 12);
         }
-        Some(Ok(F::from_channels(channels)))
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, F64Le> {
-    type Item = std::io::Result<F>;
+impl Decode for U12Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group =
+            u64::from_be_bytes([0, 0, 0, 0, 0, bytes[0], bytes[1], bytes[2]]);
+        let group = unpack(group, 12, false);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = unsigned(*packed, 12);
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 8]) {
-            return Some(Err(e));
+impl Decode for S12Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group =
+            u64::from_le_bytes([bytes[0], bytes[1], bytes[2], 0, 0, 0, 0, 0]);
+        let group = unpack(group, 12, true);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = signed(*packed, 12);
         }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(8)) {
-            *chan = F::Chan::from(Ch64::new(f64::from_le_bytes(
-                buffer.try_into().unwrap(),
-            )));
+    }
+}
+
+impl Decode for S12Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group =
+            u64::from_be_bytes([0, 0, 0, 0, 0, bytes[0], bytes[1], bytes[2]]);
+        let group = unpack(group, 12, false);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = signed(*packed, 12);
         }
-        Some(Ok(F::from_channels(channels)))
     }
 }
 
-impl<R: Read, F: Frame> Iterator for Decoder<R, F, F64Be> {
-    type Item = std::io::Result<F>;
+impl Decode for U4Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = bytes[0].into();
+        let group = unpack(group, 4, true);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = unsigned(*packed, 4);
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0u8; 64];
-        let channels = &mut [F::Chan::default(); 8][0..F::CHAN_COUNT];
-        if let Err(e) = self.0.read_exact(&mut buffer[..channels.len() * 8]) {
-            return Some(Err(e));
+impl Decode for U4Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = bytes[0].into();
+        let group = unpack(group, 4, false);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = unsigned(*packed, 4);
+        }
+    }
+}
+
+impl Decode for S4Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = bytes[0].into();
+        let group = unpack(group, 4, true);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = signed(*packed, 4);
         }
-        for (chan, buffer) in channels.iter_mut().zip(buffer.chunks(8)) {
-            *chan = F::Chan::from(Ch64::new(f64::from_be_bytes(
-                buffer.try_into().unwrap(),
-            )));
+    }
+}
+
+impl Decode for S4Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        let group = bytes[0].into();
+        let group = unpack(group, 4, false);
+        for (sample, packed) in samples.iter_mut().zip(group.iter()) {
+            *sample = signed(*packed, 4);
         }
-        Some(Ok(F::from_channels(channels)))
+    }
+}
+
+impl Decode for ImaAdpcmLe {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 4, true);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code)
+        });
+    }
+}
+
+impl Decode for ImaAdpcmBe {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 4, false);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code)
+        });
+    }
+}
+
+impl Decode for G726_16Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 2, true);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_16)
+        });
+    }
+}
+
+impl Decode for G726_16Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 2, false);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_16)
+        });
+    }
+}
+
+impl Decode for G726_24Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 3, true);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_24)
+        });
+    }
+}
+
+impl Decode for G726_24Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 3, false);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_24)
+        });
+    }
+}
+
+impl Decode for G726_32Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 4, true);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_32)
+        });
+    }
+}
+
+impl Decode for G726_32Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 4, false);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_32)
+        });
+    }
+}
+
+impl Decode for G726_40Le {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 5, true);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_40)
+        });
+    }
+}
+
+impl Decode for G726_40Be {
+    fn decode<C: Channel>(bytes: &[u8], samples: &mut [C]) {
+        Self::decode_with(&mut [Default::default()], 0, bytes, samples);
+    }
+
+    fn decode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        bytes: &[u8],
+        samples: &mut [C],
+    ) {
+        let codes = codes(bytes, 5, false);
+        adpcm(states, channel, codes, samples, |state, code| {
+            state.decode(code, &RATE_40)
+        });
     }
 }
@@ -0,0 +1,118 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! TPDF dither with optional noise shaping.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use fon::chan::{Ch64, Channel};
+
+use crate::pcm::Pcm;
+
+/// Noise shaping filter, moving quantization noise towards high frequencies.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Shaping {
+    /// Flat (white) noise (default).
+    #[default]
+    Off,
+    /// First-order error feedback.
+    FirstOrder,
+    /// Second-order error feedback.
+    SecondOrder,
+}
+
+/// Triangular probability density function (TPDF) dither for integer PCM
+/// formats.  Noise comes from a generator seeded with a fixed value, so the
+/// output is reproducible.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dither {
+    seed: u64,
+    shaping: Shaping,
+}
+
+impl Dither {
+    /// Create TPDF dither without noise shaping, seeding the noise generator
+    /// with `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            shaping: Shaping::Off,
+        }
+    }
+
+    /// Set the noise shaping filter.
+    pub fn with_shaping(mut self, shaping: Shaping) -> Self {
+        self.shaping = shaping;
+        self
+    }
+}
+
+/// Dither state of an encoder.
+#[derive(Debug)]
+pub(crate) struct Ditherer {
+    // Noise generator state (SplitMix64).
+    rng: u64,
+    shaping: Shaping,
+    // Last two quantization errors of each channel.
+    errors: Vec<[f64; 2]>,
+}
+
+impl Ditherer {
+    /// Create dither state for `channels` channels.
+    pub(crate) fn new(dither: Dither, channels: usize) -> Self {
+        Self {
+            rng: dither.seed,
+            shaping: dither.shaping,
+            errors: vec![[0.0; 2]; channels],
+        }
+    }
+
+    // Get uniform noise from -0.5 to 0.5.
+    fn uniform(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    }
+
+    /// Dither a sample of `channel` before it's quantized to `P`, which must
+    /// be an integer format.
+    pub(crate) fn apply<P: Pcm, C: Channel>(
+        &mut self,
+        channel: usize,
+        sample: f64,
+    ) -> C {
+        let lsb = 1.0 / (1u64 << (P::BITS - 1)) as f64;
+        let [e1, e2] = self.errors[channel];
+        let wanted = match self.shaping {
+            Shaping::Off => sample,
+            Shaping::FirstOrder => sample - e1,
+            Shaping::SecondOrder => sample - 2.0 * e1 + e2,
+        };
+        let noise = (self.uniform() + self.uniform()) * lsb;
+        let dithered = C::from(Ch64::new((wanted + noise).clamp(-1.0, 1.0)));
+        let error = quantize::<P, C>(dithered).to_f64() - wanted;
+        // Limit errors from clipping so the feedback stays stable.
+        let error = error.clamp(-2.0 * lsb, 2.0 * lsb);
+        self.errors[channel] = [error, e1];
+        dithered
+    }
+}
+
+// Get a sample as it will be decoded after quantizing to `P`.
+fn quantize<P: Pcm, C: Channel>(sample: C) -> C {
+    let mut samples = [sample; 8];
+    let samples = &mut samples[..P::SAMPLES];
+    let mut bytes = [0; 8];
+    let bytes = &mut bytes[..P::SIZE];
+    P::encode(samples, bytes);
+    P::decode(bytes, samples);
+    samples[0]
+}
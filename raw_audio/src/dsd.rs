@@ -0,0 +1,311 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Decoding of 1-bit DSD bitstreams to PCM.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+use core::marker::PhantomData;
+
+use fon::chan::Ch64;
+use fon::Frame;
+
+use crate::io::{self, fill, ErrorKind, Read};
+use crate::mix::{self, Mixer};
+use crate::BLOCK;
+
+/// Bit rate of DSD64 (64 × 44100 Hz), for each channel.
+pub const DSD64: u32 = 2_822_400;
+
+/// Bit rate of DSD128 (128 × 44100 Hz), for each channel.
+pub const DSD128: u32 = 5_644_800;
+
+/// Filter taps for each output sample period.
+const TAPS: usize = 32;
+
+/// Number of output samples the filter lags behind its input.
+const DELAY: usize = TAPS / 2 - 1;
+
+/// Byte of DSD silence (idle pattern), most significant bit first.
+const SILENCE: u8 = 0x69;
+
+/// Order of bits within a byte of DSD.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Oldest bit is the most significant bit, as in DSDIFF (default).
+    #[default]
+    MsbFirst,
+    /// Oldest bit is the least significant bit, as in DSF.
+    LsbFirst,
+}
+
+/// Arrangement of channels in DSD.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// One byte of each channel in turn, as in DSDIFF (default).
+    #[default]
+    Interleaved,
+    /// A block of this many bytes of each channel in turn, as in DSF (4096).
+    Blocks(usize),
+}
+
+/// Decoder for 1-bit DSD audio, converting it to PCM frames with a low-pass
+/// filter and decimation.
+///
+/// DSD at 0 dB SACD (50% modulation) decodes to half of full scale.
+pub struct DsdDecoder<R: Read, F: Frame> {
+    reader: R,
+    // Number of channels in the DSD audio.
+    channels: usize,
+    // Sample rate of the PCM output.
+    hz: u32,
+    order: BitOrder,
+    layout: Layout,
+    // Bytes of each channel for one output sample.
+    step: usize,
+    // Filter output for each byte of history, by byte value.
+    tables: Vec<f32>,
+    // Most recent bytes of each channel, oldest first.
+    history: Vec<u8>,
+    // Bytes read, arranged as in the DSD audio.
+    chunk: Vec<u8>,
+    // Bytes of each channel in `chunk`.
+    available: usize,
+    // Bytes of each channel used from `chunk`.
+    offset: usize,
+    // Number of output samples left to discard while the filter fills.
+    skip: usize,
+    // Number of output samples left after the end of the input, while the
+    // filter empties.
+    tail: Option<usize>,
+    // Output samples of each channel.
+    decoded: Vec<F::Chan>,
+    // Mixing from DSD channels to `F` channels, if they differ.
+    mixer: Option<Mixer>,
+    _phantom: PhantomData<F>,
+}
+
+impl<R: Read, F: Frame> DsdDecoder<R, F> {
+    /// Create a decoder for DSD with `channels` channels at `rate` bits per
+    /// second for each channel (like [`DSD64`] or [`DSD128`]), converting it
+    /// to PCM at `hz`.  Channels are up/down-mixed like with
+    /// [`Decoder::with_channels()`](crate::Decoder::with_channels).
+    ///
+    /// # Panics
+    /// If `channels` is zero, or if `rate` isn't a multiple of 8 × `hz`.
+    pub fn new(reader: R, rate: u32, channels: usize, hz: u32) -> Self {
+        assert_ne!(channels, 0, "DSD audio must have at least one channel");
+        let bits = hz.checked_mul(8).unwrap_or(0);
+        assert!(
+            bits != 0 && rate % bits == 0,
+            "DSD bit rate must be a multiple of 8 × the output sample rate",
+        );
+        let ratio = (rate / hz) as usize;
+        let taps = ratio * TAPS;
+        // Keep well below the output Nyquist frequency, and filter out the
+        // noise DSD pushes above the audio band.
+        let cutoff = (0.4 * f64::from(hz)).min(24_000.0) / f64::from(rate);
+        let tables = tables(&filter(taps, cutoff));
+        Self {
+            reader,
+            channels,
+            hz,
+            order: BitOrder::default(),
+            layout: Layout::default(),
+            step: ratio / 8,
+            tables,
+            history: vec![SILENCE; taps / 8 * channels],
+            chunk: Vec::new(),
+            available: 0,
+            offset: 0,
+            skip: DELAY,
+            tail: None,
+            decoded: vec![F::Chan::default(); channels],
+            mixer: Mixer::new(channels, F::CHAN_COUNT),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Set the order of bits within each byte.
+    pub fn with_order(mut self, order: BitOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Set the arrangement of channels.
+    ///
+    /// # Panics
+    /// If the block size of [`Layout::Blocks`] is zero.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        assert_ne!(layout, Layout::Blocks(0), "DSD blocks can't be empty");
+        self.layout = layout;
+        self
+    }
+
+    /// Get the number of channels in the DSD audio.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Get the sample rate of the decoded PCM.
+    pub fn sample_rate(&self) -> u32 {
+        self.hz
+    }
+
+    /// Decode the next frame without mixing, returning one channel for each
+    /// of the DSD audio's [`channels()`](DsdDecoder::channels).  Returns
+    /// `None` at the end of the audio.
+    pub fn next_channels(&mut self) -> Option<io::Result<&[F::Chan]>> {
+        loop {
+            if self.tail == Some(0) {
+                return None;
+            }
+            if let Err(e) = self.next_sample() {
+                return Some(Err(e));
+            }
+            if self.skip == 0 {
+                return Some(Ok(&self.decoded));
+            }
+            self.skip -= 1;
+        }
+    }
+
+    // Shift the bytes of one output sample into the filter and run it.
+    fn next_sample(&mut self) -> io::Result<()> {
+        let (len, step) = (self.history.len() / self.channels, self.step);
+        for history in self.history.chunks_exact_mut(len) {
+            history.copy_within(step.., 0);
+        }
+        for i in len - step..len {
+            let chunk = self.next_chunk()?;
+            for channel in 0..self.channels {
+                let byte = match (chunk, self.layout) {
+                    (false, _) => SILENCE,
+                    (true, Layout::Interleaved) => {
+                        self.chunk[self.offset * self.channels + channel]
+                    }
+                    (true, Layout::Blocks(size)) => {
+                        self.chunk[channel * size + self.offset]
+                    }
+                };
+                self.history[channel * len + i] =
+                    if chunk && self.order == BitOrder::LsbFirst {
+                        byte.reverse_bits()
+                    } else {
+                        byte
+                    };
+            }
+            if chunk {
+                self.offset += 1;
+            }
+        }
+        if let Some(tail) = &mut self.tail {
+            *tail -= 1;
+        }
+        let histories = self.history.chunks_exact(len);
+        for (decoded, history) in self.decoded.iter_mut().zip(histories) {
+            let sample: f32 = history
+                .iter()
+                .zip(self.tables.chunks_exact(256))
+                .map(|(byte, table)| table[usize::from(*byte)])
+                .sum();
+            *decoded = F::Chan::from(Ch64::new(sample.into()));
+        }
+        Ok(())
+    }
+
+    // Make sure a byte of each channel is available, returning false after
+    // the end of the input.
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        if self.tail.is_some() {
+            return Ok(false);
+        }
+        if self.offset < self.available {
+            return Ok(true);
+        }
+        let size = match self.layout {
+            Layout::Interleaved => BLOCK * self.channels,
+            Layout::Blocks(size) => size * self.channels,
+        };
+        self.chunk.resize(size, 0);
+        let len = fill(&mut self.reader, &mut self.chunk)?;
+        self.offset = 0;
+        self.available = match self.layout {
+            Layout::Interleaved => len / self.channels,
+            Layout::Blocks(_) if len == 0 || len == size => len / self.channels,
+            Layout::Blocks(_) => {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+        };
+        if self.available == 0 {
+            // Empty the filter, counting the partial output sample.
+            self.tail = Some(DELAY);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read, F: Frame> Iterator for DsdDecoder<R, F> {
+    type Item = io::Result<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.next_channels()? {
+            return Some(Err(e));
+        }
+        Some(Ok(mix::frame(&self.mixer, &self.decoded)))
+    }
+}
+
+// Design a low-pass FIR filter (Blackman windowed sinc) with unity gain,
+// with `cutoff` relative to the sample rate.
+fn filter(taps: usize, cutoff: f64) -> Vec<f64> {
+    let center = (taps - 1) as f64 / 2.0;
+    let mut filter: Vec<f64> = (0..taps)
+        .map(|n| {
+            let x = n as f64 - center;
+            let sinc = if x == 0.0 {
+                2.0 * cutoff
+            } else {
+                libm::sin(2.0 * PI * cutoff * x) / (PI * x)
+            };
+            let phase = 2.0 * PI * n as f64 / (taps - 1) as f64;
+            let window =
+                0.42 - 0.5 * libm::cos(phase) + 0.08 * libm::cos(2.0 * phase);
+            sinc * window
+        })
+        .collect();
+    let sum: f64 = filter.iter().sum();
+    filter.iter_mut().for_each(|tap| *tap /= sum);
+    filter
+}
+
+// Build lookup tables of the filter output for every value of each byte of
+// history, with 1 bits as +1 and 0 bits as -1.
+fn tables(filter: &[f64]) -> Vec<f32> {
+    let mut tables = Vec::with_capacity(filter.len() / 8 * 256);
+    for taps in filter.chunks_exact(8) {
+        for byte in 0..256 {
+            let sum: f64 = taps
+                .iter()
+                .enumerate()
+                .map(
+                    |(i, tap)| {
+                        if byte & (0x80 >> i) != 0 {
+                            *tap
+                        } else {
+                            -tap
+                        }
+                    },
+                )
+                .sum();
+            tables.push(sum as f32);
+        }
+    }
+    tables
+}
@@ -5,19 +5,132 @@
 // (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
 // LICENSE_BOOST_1_0.txt)
 
-use std::io::Write;
-use std::marker::PhantomData;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 
-use fon::chan::{Ch16, Ch32, Ch8, Channel};
+use fon::chan::{Ch16, Ch32, Ch64, Ch8, Channel};
 use fon::{Frame, Stream};
 
+use crate::dither::{Dither, Ditherer};
+use crate::g726::{RATE_16, RATE_24, RATE_32, RATE_40};
+use crate::half::{f32_to_bf16, f32_to_f16};
+use crate::io::{self, ErrorKind, Write};
+use crate::pcm::seal::Seal;
 use crate::pcm::{
-    ALaw, F32Be, F32Le, F64Be, F64Le, MuLaw, Pcm, S16Be, S16Le, S24Be, S24Le,
-    S32Be, S32Le, U16Be, U16Le, U24Be, U24Le, U32Be, U32Le, S8, U8,
+    ALaw, BF16Be, BF16Le, F16Be, F16Le, F32Be, F32Le, F64Be, F64Le, G726_16Be,
+    G726_16Le, G726_24Be, G726_24Le, G726_32Be, G726_32Le, G726_40Be,
+    G726_40Le, ImaAdpcmBe, ImaAdpcmLe, MuLaw, Pcm, S12Be, S12Le, S16Be, S16Le,
+    S20Be, S20Le, S24Be, S24Le, S24_32Be, S24_32Le, S24_32RBe, S24_32RLe,
+    S32Be, S32Le, S4Be, S4Le, U12Be, U12Le, U16Be, U16Le, U20Be, U20Le, U24Be,
+    U24Le, U32Be, U32Le, U4Be, U4Le, S8, U8,
 };
+use crate::BLOCK;
 
-/// Encoder for RAW Audio
-pub struct Encoder<W: Write, F: Frame, P: Pcm>(W, PhantomData<(F, P)>);
+/// Encoding of RAW samples.
+pub trait Encode: Seal {
+    /// Encode a group of `Pcm::SAMPLES` samples into `Pcm::SIZE` bytes.
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]);
+
+    /// Encode a group of samples with each channel's codec state, where the
+    /// first sample belongs to `channel` and the rest follow in channel order.
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let _ = (states, channel);
+        Self::encode(samples, bytes);
+    }
+}
+
+/// How samples outside of -1 to 1 (and NaN) are encoded.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Clip {
+    /// Clamp to full scale (default).  NaN becomes silence.
+    #[default]
+    Hard,
+    /// Smoothly compress peaks above 0.9 towards full scale, changing samples
+    /// that are close to full scale as well.  NaN becomes silence.
+    Soft,
+    /// Wrap around like integer overflow.  NaN and infinity become silence.
+    Wrap,
+    /// Fail with [`ErrorKind::InvalidData`].
+    Error,
+}
+
+impl Clip {
+    // Level above which soft clipping starts.
+    const KNEE: f64 = 0.9;
+
+    // Get the sample to encode, or `None` if it must be left as is.
+    pub(crate) fn apply(self, sample: f64) -> io::Result<Option<f64>> {
+        let limit = if self == Clip::Soft { Self::KNEE } else { 1.0 };
+        if sample.abs() <= limit {
+            return Ok(None);
+        }
+        if sample.is_nan() && self != Clip::Error {
+            return Ok(Some(0.0));
+        }
+        Ok(Some(match self {
+            Clip::Hard => sample.clamp(-1.0, 1.0),
+            Clip::Soft => {
+                let over = (sample.abs() - Self::KNEE) / (1.0 - Self::KNEE);
+                let soft = Self::KNEE + (1.0 - Self::KNEE) * tanh(over);
+                soft.copysign(sample)
+            }
+            Clip::Wrap if sample.is_infinite() => 0.0,
+            Clip::Wrap => {
+                let wrapped = (sample + 1.0) % 2.0;
+                if wrapped < 0.0 {
+                    wrapped + 1.0
+                } else {
+                    wrapped - 1.0
+                }
+            }
+            Clip::Error => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "Sample out of range",
+                ))
+            }
+        }))
+    }
+}
+
+#[cfg(feature = "std")]
+fn tanh(x: f64) -> f64 {
+    x.tanh()
+}
+
+#[cfg(not(feature = "std"))]
+fn tanh(x: f64) -> f64 {
+    libm::tanh(x)
+}
+
+/// Encoder for RAW Audio.  Encoded bytes are buffered and written in large
+/// blocks; call [`flush()`](Encoder::flush) or
+/// [`into_inner()`](Encoder::into_inner) to write out the rest, otherwise it
+/// is lost when the encoder is dropped.
+pub struct Encoder<W: Write, F: Frame, P: Pcm> {
+    writer: W,
+    // Encoded bytes not yet written.
+    buffer: Vec<u8>,
+    // Samples waiting to be packed together with following samples.
+    pending: Vec<F::Chan>,
+    // Channel of the first pending sample.
+    channel: usize,
+    // Codec state of each channel.
+    states: Vec<P::State>,
+    // Policy for out-of-range samples.
+    clip: Clip,
+    // Number of out-of-range samples.
+    clipped: u64,
+    // Dither state, for integer formats.
+    dither: Option<Ditherer>,
+    _phantom: PhantomData<(F, P)>,
+}
 
 // 32-bit Linear PCM channel.
 fn pcm_chan_32<C: Channel>(chan: C) -> i32 {
@@ -40,364 +153,720 @@ impl<W: Write, F: Frame, P: Pcm> Encoder<W, F, P> {
     /// Create a new raw Audio encoder.
     pub fn new(writer: W, pcm: P) -> Self {
         let _ = pcm;
-        Self(writer, PhantomData)
+        Self {
+            writer,
+            buffer: Vec::with_capacity(BLOCK * P::SIZE),
+            pending: Vec::with_capacity(P::SAMPLES),
+            channel: 0,
+            states: vec![P::State::default(); F::CHAN_COUNT],
+            clip: Clip::default(),
+            clipped: 0,
+            dither: None,
+            _phantom: PhantomData,
+        }
     }
-}
 
-impl<W: Write, F: Frame> Encoder<W, F, U8> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch8 = chan.into();
-                let chan: i8 = chan.into();
-                self.0.write_all(&[chan as u8 ^ 0x80])?;
-            }
-        }
-        Ok(())
+    /// Set how samples outside of -1 to 1 (and NaN) are encoded.
+    pub fn with_clip(mut self, clip: Clip) -> Self {
+        self.clip = clip;
+        self
     }
-}
 
-impl<W: Write, F: Frame> Encoder<W, F, S8> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch8 = chan.into();
-                let chan: i8 = chan.into();
-                self.0.write_all(&chan.to_le_bytes())?;
-            }
+    /// Dither samples when quantizing to an integer format (`U8`, `S16Le`,
+    /// etc.).  Has no effect on other formats.
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        if P::BITS != 0 {
+            self.dither = Some(Ditherer::new(dither, F::CHAN_COUNT));
         }
-        Ok(())
+        self
+    }
+
+    /// Get the number of samples encoded so far that were outside of -1 to 1
+    /// (or NaN).  With [`Clip::Error`], the failing sample is counted too.
+    pub fn clipped(&self) -> u64 {
+        self.clipped
+    }
+
+    /// Append encoded data from a stream to the output, returning the number
+    /// of frames written.  This can be called multiple times to encode as
+    /// needed instead of all at once.  Never returns for infinite streams;
+    /// use [`encode_frames()`](Encoder::encode_frames) for those.
+    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> io::Result<usize> {
+        self.encode_frames(&mut stream.into_iter(), usize::MAX)
     }
-}
 
-impl<W: Write, F: Frame> Encoder<W, F, MuLaw> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch16 = chan.into();
-                let chan: i16 = chan.into();
-                // reduce to 14 bits.
-                let chan: u8 = match chan >> 2 {
-                    x if x <= -8159 => 0x00,
-                    x if x <= -4064 => ((x + 8159) >> 8) as u8,
-                    x if x <= -2016 => 0x10 | ((x + 4063) >> 7) as u8,
-                    x if x <= -992 => 0x20 | ((x + 2015) >> 6) as u8,
-                    x if x <= -480 => 0x30 | ((x + 991) >> 5) as u8,
-                    x if x <= -224 => 0x40 | ((x + 479) >> 4) as u8,
-                    x if x <= -96 => 0x50 | ((x + 223) >> 3) as u8,
-                    x if x <= -32 => 0x60 | ((x + 95) >> 2) as u8,
-                    x if x <= -1 => 0x70 | ((x + 31) >> 1) as u8,
-                    x if x <= 30 => 0xF0 | ((30 - x) >> 1) as u8,
-                    x if x <= 94 => 0xE0 | ((94 - x) >> 2) as u8,
-                    x if x <= 222 => 0xD0 | ((222 - x) >> 3) as u8,
-                    x if x <= 478 => 0xC0 | ((478 - x) >> 4) as u8,
-                    x if x <= 990 => 0xB0 | ((990 - x) >> 5) as u8,
-                    x if x <= 2014 => 0xA0 | ((2014 - x) >> 6) as u8,
-                    x if x <= 4062 => 0x90 | ((4062 - x) >> 7) as u8,
-                    x if x <= 8158 => 0x80 | ((8158 - x) >> 8) as u8,
-                    _ => 0x80,
+    /// Append at most `frames` frames of encoded data from an iterator (for
+    /// example a [`Stream`]'s) to the output, returning the number of frames
+    /// written (fewer at the end of the iterator).  Streams with an unknown
+    /// or infinite length, like live input, can be encoded a chunk at a time
+    /// this way, each call continuing where the last one stopped.
+    pub fn encode_frames<I: Iterator<Item = F>>(
+        &mut self,
+        iter: &mut I,
+        frames: usize,
+    ) -> io::Result<usize> {
+        let mut written = 0;
+        for frame in iter.take(frames) {
+            for (i, chan) in frame.channels().iter().cloned().enumerate() {
+                let sample = chan.to_f64();
+                if !(-1.0..=1.0).contains(&sample) {
+                    self.clipped += 1;
+                }
+                let clipped = self.clip.apply(sample)?;
+                let chan = match (&mut self.dither, clipped) {
+                    (Some(dither), clipped) => {
+                        dither.apply::<P, _>(i, clipped.unwrap_or(sample))
+                    }
+                    (None, Some(clipped)) => F::Chan::from(Ch64::new(clipped)),
+                    (None, None) => chan,
                 };
-                self.0.write_all(&[chan])?;
+                self.pending.push(chan);
+                if self.pending.len() == P::SAMPLES {
+                    self.encode_pending();
+                }
+            }
+            if self.buffer.len() >= BLOCK * P::SIZE {
+                self.write_buffer()?;
             }
+            written += 1;
         }
-        Ok(())
+        Ok(written)
     }
-}
 
-impl<W: Write, F: Frame> Encoder<W, F, ALaw> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch16 = chan.into();
-                let chan: i16 = chan.into();
-
-                const C_CLIP: i16 = 32635;
-                const LOG_TABLE: [u8; 128] = [
-                    1, 1, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5,
-                    5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6,
-                    6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
-                    6, 6, 6, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
-                    7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
-                    7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
-                    7, 7, 7, 7, 7, 7, 7, 7,
-                ];
-
-                let mut chan = if chan == -32768 { -32767 } else { chan };
-                let sign = ((!chan) >> 8) as u8 & 0x80;
-                if sign == 0 {
-                    chan = -chan;
-                }
-                if chan > C_CLIP {
-                    chan = C_CLIP;
-                }
-                let mut chan: u8 = if chan >= 256 {
-                    let exponent = LOG_TABLE[((chan >> 8) & 0x7F) as usize];
-                    let mantissa = ((chan >> (exponent + 3)) & 0x0F) as u8;
-                    (exponent << 4) | mantissa
-                } else {
-                    (chan >> 4) as u8
-                };
-                chan ^= sign ^ 0x55;
+    /// Write out buffered bytes and flush the writer.  For formats that pack
+    /// multiple samples together, samples of an incomplete group stay
+    /// buffered until the group is filled.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        self.writer.flush()
+    }
 
-                self.0.write_all(&[chan])?;
-            }
+    /// Finish encoding, write out buffered bytes and return the writer.  For
+    /// formats that pack multiple samples together, a partially filled group
+    /// of samples at the end of the audio is padded with silence.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            self.pending.resize(P::SAMPLES, F::Chan::default());
+            self.encode_pending();
         }
+        self.write_buffer()?;
+        Ok(self.writer)
+    }
+
+    // Encode a full group of pending samples into the buffer.
+    fn encode_pending(&mut self) {
+        let start = self.buffer.len();
+        self.buffer.resize(start + P::SIZE, 0);
+        let bytes = &mut self.buffer[start..];
+        P::encode_with(&mut self.states, self.channel, &self.pending, bytes);
+        self.pending.clear();
+        self.channel = (self.channel + P::SAMPLES) % F::CHAN_COUNT;
+    }
+
+    // Write out buffered bytes.
+    fn write_buffer(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.buffer)?;
+        self.buffer.clear();
         Ok(())
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, U16Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch16 = chan.into();
-                let chan: i16 = chan.into();
-                self.0.write_all(&(chan ^ 0x8000u16 as i16).to_le_bytes())?;
-            }
-        }
-        Ok(())
+// Encode samples into ADPCM code words, each with its channel's state.
+fn adpcm<S, C: Channel>(
+    states: &mut [S],
+    channel: usize,
+    samples: &[C],
+    encode: impl Fn(&mut S, i16) -> u8,
+) -> [u8; 8] {
+    let mut codes = [0; 8];
+    for (i, (code, chan)) in codes.iter_mut().zip(samples).enumerate() {
+        let chan: Ch16 = (*chan).into();
+        let state = &mut states[(channel + i) % states.len()];
+        *code = encode(state, chan.into());
     }
+    codes
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, U16Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch16 = chan.into();
-                let chan: i16 = chan.into();
-                self.0.write_all(&(chan ^ 0x8000u16 as i16).to_be_bytes())?;
-            }
-        }
-        Ok(())
+// Pack ADPCM code words into a group of bytes, with the first code word in the
+// low bits for little endian packing.
+fn pack_codes(codes: [u8; 8], bits: usize, little: bool, bytes: &mut [u8]) {
+    let count = bytes.len() * 8 / bits;
+    let mut group = 0u64;
+    for (i, code) in codes[..count].iter().enumerate() {
+        let shift = if little { i } else { count - 1 - i } * bits;
+        group |= u64::from(*code) << shift;
+    }
+    let len = bytes.len();
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        let i = if little { i } else { len - 1 - i };
+        *byte = (group >> (i * 8)) as u8;
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, S16Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch16 = chan.into();
-                let chan: i16 = chan.into();
-                self.0.write_all(&chan.to_le_bytes())?;
-            }
-        }
-        Ok(())
+// Quantize a channel to a `bits`-bit two's complement integer.
+fn signed<C: Channel>(chan: C, bits: u32) -> u32 {
+    (pcm_chan_32(chan) >> (32 - bits)) as u32 & ((1 << bits) - 1)
+}
+
+// Quantize a channel to a `bits`-bit offset binary integer.
+fn unsigned<C: Channel>(chan: C, bits: u32) -> u32 {
+    signed(chan, bits) ^ (1 << (bits - 1))
+}
+
+// Pack two `bits`-bit samples into a group, with the first sample in the low
+// bits for little endian packing.
+fn pack(samples: [u32; 2], bits: u32, little: bool) -> u64 {
+    let [first, second] = samples;
+    if little {
+        u64::from(first) | u64::from(second) << bits
+    } else {
+        u64::from(first) << bits | u64::from(second)
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, S16Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch16 = chan.into();
-                let chan: i16 = chan.into();
-                self.0.write_all(&chan.to_be_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for U8 {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch8 = chan.into();
+        let chan: i8 = chan.into();
+        bytes[0] = chan as u8 ^ 0x80;
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, U24Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan).to_le_bytes();
-                self.0.write_all(&[chan[0], chan[1], chan[2] ^ 0x80])?;
-            }
-        }
-        Ok(())
+impl Encode for S8 {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch8 = chan.into();
+        let chan: i8 = chan.into();
+        bytes[0] = chan as u8;
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, U24Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan).to_be_bytes();
-                self.0.write_all(&[chan[1] ^ 0x80, chan[2], chan[3]])?;
-            }
-        }
-        Ok(())
+impl Encode for MuLaw {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch16 = chan.into();
+        let chan: i16 = chan.into();
+        // reduce to 14 bits.
+        let chan: u8 = match chan >> 2 {
+            x if x <= -8159 => 0x00,
+            x if x <= -4064 => ((x + 8159) >> 8) as u8,
+            x if x <= -2016 => 0x10 | ((x + 4063) >> 7) as u8,
+            x if x <= -992 => 0x20 | ((x + 2015) >> 6) as u8,
+            x if x <= -480 => 0x30 | ((x + 991) >> 5) as u8,
+            x if x <= -224 => 0x40 | ((x + 479) >> 4) as u8,
+            x if x <= -96 => 0x50 | ((x + 223) >> 3) as u8,
+            x if x <= -32 => 0x60 | ((x + 95) >> 2) as u8,
+            x if x <= -1 => 0x70 | ((x + 31) >> 1) as u8,
+            x if x <= 30 => 0xF0 | ((30 - x) >> 1) as u8,
+            x if x <= 94 => 0xE0 | ((94 - x) >> 2) as u8,
+            x if x <= 222 => 0xD0 | ((222 - x) >> 3) as u8,
+            x if x <= 478 => 0xC0 | ((478 - x) >> 4) as u8,
+            x if x <= 990 => 0xB0 | ((990 - x) >> 5) as u8,
+            x if x <= 2014 => 0xA0 | ((2014 - x) >> 6) as u8,
+            x if x <= 4062 => 0x90 | ((4062 - x) >> 7) as u8,
+            x if x <= 8158 => 0x80 | ((8158 - x) >> 8) as u8,
+            _ => 0x80,
+        };
+        bytes[0] = chan;
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, S24Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan).to_le_bytes();
-                self.0.write_all(&[chan[0], chan[1], chan[2]])?;
-            }
+impl Encode for ALaw {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch16 = chan.into();
+        let chan: i16 = chan.into();
+
+        const C_CLIP: i16 = 32635;
+        const LOG_TABLE: [u8; 128] = [
+            1, 1, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5, 5, 5,
+            5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+            6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 7, 7,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        ];
+
+        let mut chan = if chan == -32768 { -32767 } else { chan };
+        let sign = ((!chan) >> 8) as u8 & 0x80;
+        if sign == 0 {
+            chan = -chan;
         }
-        Ok(())
+        if chan > C_CLIP {
+            chan = C_CLIP;
+        }
+        let mut chan: u8 = if chan >= 256 {
+            let exponent = LOG_TABLE[((chan >> 8) & 0x7F) as usize];
+            let mantissa = ((chan >> (exponent + 3)) & 0x0F) as u8;
+            (exponent << 4) | mantissa
+        } else {
+            (chan >> 4) as u8
+        };
+        chan ^= sign ^ 0x55;
+
+        bytes[0] = chan;
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, S24Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan).to_be_bytes();
-                self.0.write_all(&[chan[1], chan[2], chan[3]])?;
-            }
-        }
-        Ok(())
+impl Encode for U16Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch16 = chan.into();
+        let chan: i16 = chan.into();
+        bytes.copy_from_slice(&(chan ^ 0x8000u16 as i16).to_le_bytes());
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, U32Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan) ^ (1 << 31);
-                self.0.write_all(&chan.to_le_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for U16Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch16 = chan.into();
+        let chan: i16 = chan.into();
+        bytes.copy_from_slice(&(chan ^ 0x8000u16 as i16).to_be_bytes());
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, U32Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan) ^ (1 << 31);
-                self.0.write_all(&chan.to_be_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for S16Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch16 = chan.into();
+        let chan: i16 = chan.into();
+        bytes.copy_from_slice(&chan.to_le_bytes());
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, S32Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan);
-                self.0.write_all(&chan.to_le_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for S16Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch16 = chan.into();
+        let chan: i16 = chan.into();
+        bytes.copy_from_slice(&chan.to_be_bytes());
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, S32Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                let chan = pcm_chan_32(*chan);
-                self.0.write_all(&chan.to_be_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for U24Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan = pcm_chan_32(chan).to_le_bytes();
+        bytes.copy_from_slice(&[chan[1], chan[2], chan[3] ^ 0x80]);
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, F32Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch32 = chan.into();
-                let chan: f32 = chan.into();
-                self.0.write_all(&chan.to_le_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for U24Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan = pcm_chan_32(chan).to_be_bytes();
+        bytes.copy_from_slice(&[chan[0] ^ 0x80, chan[1], chan[2]]);
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, F32Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels().iter().cloned() {
-                let chan: Ch32 = chan.into();
-                let chan: f32 = chan.into();
-                self.0.write_all(&chan.to_be_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for S24Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan = pcm_chan_32(chan).to_le_bytes();
+        bytes.copy_from_slice(&[chan[1], chan[2], chan[3]]);
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, F64Le> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                self.0.write_all(&chan.to_f64().to_le_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for S24Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan = pcm_chan_32(chan).to_be_bytes();
+        bytes.copy_from_slice(&[chan[0], chan[1], chan[2]]);
     }
 }
 
-impl<W: Write, F: Frame> Encoder<W, F, F64Be> {
-    /// Append encoded data from a stream to the output.  This can be called
-    /// multiple times to encode as needed instead of all at once.
-    pub fn encode<S: Stream<F>>(&mut self, stream: S) -> std::io::Result<()> {
-        assert!(stream.len().is_some());
-        for frame in stream.into_iter() {
-            for chan in frame.channels() {
-                self.0.write_all(&chan.to_f64().to_be_bytes())?;
-            }
-        }
-        Ok(())
+impl Encode for U32Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan = pcm_chan_32(chan) ^ (1 << 31);
+        bytes.copy_from_slice(&chan.to_le_bytes());
+    }
+}
+
+impl Encode for U32Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan = pcm_chan_32(chan) ^ (1 << 31);
+        bytes.copy_from_slice(&chan.to_be_bytes());
+    }
+}
+
+impl Encode for S32Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        bytes.copy_from_slice(&pcm_chan_32(chan).to_le_bytes());
+    }
+}
+
+impl Encode for S32Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        bytes.copy_from_slice(&pcm_chan_32(chan).to_be_bytes());
+    }
+}
+
+impl Encode for F16Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan: Ch32 = samples[0].into();
+        let chan: f32 = chan.into();
+        bytes.copy_from_slice(&f32_to_f16(chan).to_le_bytes());
+    }
+}
+
+impl Encode for F16Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan: Ch32 = samples[0].into();
+        let chan: f32 = chan.into();
+        bytes.copy_from_slice(&f32_to_f16(chan).to_be_bytes());
+    }
+}
+
+impl Encode for BF16Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan: Ch32 = samples[0].into();
+        let chan: f32 = chan.into();
+        bytes.copy_from_slice(&f32_to_bf16(chan).to_le_bytes());
+    }
+}
+
+impl Encode for BF16Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan: Ch32 = samples[0].into();
+        let chan: f32 = chan.into();
+        bytes.copy_from_slice(&f32_to_bf16(chan).to_be_bytes());
+    }
+}
+
+impl Encode for F32Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch32 = chan.into();
+        let chan: f32 = chan.into();
+        bytes.copy_from_slice(&chan.to_le_bytes());
+    }
+}
+
+impl Encode for F32Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        let chan: Ch32 = chan.into();
+        let chan: f32 = chan.into();
+        bytes.copy_from_slice(&chan.to_be_bytes());
+    }
+}
+
+impl Encode for F64Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        bytes.copy_from_slice(&chan.to_f64().to_le_bytes());
+    }
+}
+
+impl Encode for F64Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = samples[0];
+        bytes.copy_from_slice(&chan.to_f64().to_be_bytes());
+    }
+}
+
+impl Encode for S24_32Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = pcm_chan_32(samples[0]) & !0xFF;
+        bytes.copy_from_slice(&chan.to_le_bytes());
+    }
+}
+
+impl Encode for S24_32Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = pcm_chan_32(samples[0]) & !0xFF;
+        bytes.copy_from_slice(&chan.to_be_bytes());
+    }
+}
+
+impl Encode for S24_32RLe {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = pcm_chan_32(samples[0]) >> 8;
+        bytes.copy_from_slice(&chan.to_le_bytes());
+    }
+}
+
+impl Encode for S24_32RBe {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let chan = pcm_chan_32(samples[0]) >> 8;
+        bytes.copy_from_slice(&chan.to_be_bytes());
+    }
+}
+
+impl Encode for U20Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [unsigned(samples[0], 20), unsigned(samples[1], 20)];
+        let group = pack(group, 20, true).to_le_bytes();
+        bytes.copy_from_slice(&group[..5]);
+    }
+}
+
+impl Encode for U20Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [unsigned(samples[0], 20), unsigned(samples[1], 20)];
+        let group = pack(group, 20, false).to_be_bytes();
+        bytes.copy_from_slice(&group[8 - 5..]);
+    }
+}
+
+impl Encode for S20Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [signed(samples[0], 20), signed(samples[1], 20)];
+        let group = pack(group, 20, true).to_le_bytes();
+        bytes.copy_from_slice(&group[..5]);
+    }
+}
+
+impl Encode for S20Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [signed(samples[0], 20), signed(samples[1], 20)];
+        let group = pack(group, 20, false).to_be_bytes();
+        bytes.copy_from_slice(&group[8 - 5..]);
+    }
+}
+
+impl Encode for U12Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [unsigned(samples[0], 12), unsigned(samples[1], 12)];
+        let group = pack(group, 12, true).to_le_bytes();
+        bytes.copy_from_slice(&group[..3]);
+    }
+}
+
+impl Encode for U12Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [unsigned(samples[0], 12), unsigned(samples[1], 12)];
+        let group = pack(group, 12, false).to_be_bytes();
+        bytes.copy_from_slice(&group[8 - 3..]);
+    }
+}
+
+impl Encode for S12Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [signed(samples[0], 12), signed(samples[1], 12)];
+        let group = pack(group, 12, true).to_le_bytes();
+        bytes.copy_from_slice(&group[..3]);
+    }
+}
+
+impl Encode for S12Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [signed(samples[0], 12), signed(samples[1], 12)];
+        let group = pack(group, 12, false).to_be_bytes();
+        bytes.copy_from_slice(&group[8 - 3..]);
+    }
+}
+
+impl Encode for U4Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [unsigned(samples[0], 4), unsigned(samples[1], 4)];
+        bytes[0] = pack(group, 4, true) as u8;
+    }
+}
+
+impl Encode for U4Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [unsigned(samples[0], 4), unsigned(samples[1], 4)];
+        bytes[0] = pack(group, 4, false) as u8;
+    }
+}
+
+impl Encode for S4Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [signed(samples[0], 4), signed(samples[1], 4)];
+        bytes[0] = pack(group, 4, true) as u8;
+    }
+}
+
+impl Encode for S4Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        let group = [signed(samples[0], 4), signed(samples[1], 4)];
+        bytes[0] = pack(group, 4, false) as u8;
+    }
+}
+
+impl Encode for ImaAdpcmLe {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample)
+        });
+        pack_codes(codes, 4, true, bytes);
+    }
+}
+
+impl Encode for ImaAdpcmBe {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample)
+        });
+        pack_codes(codes, 4, false, bytes);
+    }
+}
+
+impl Encode for G726_16Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_16)
+        });
+        pack_codes(codes, 2, true, bytes);
+    }
+}
+
+impl Encode for G726_16Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_16)
+        });
+        pack_codes(codes, 2, false, bytes);
+    }
+}
+
+impl Encode for G726_24Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_24)
+        });
+        pack_codes(codes, 3, true, bytes);
+    }
+}
+
+impl Encode for G726_24Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_24)
+        });
+        pack_codes(codes, 3, false, bytes);
+    }
+}
+
+impl Encode for G726_32Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_32)
+        });
+        pack_codes(codes, 4, true, bytes);
+    }
+}
+
+impl Encode for G726_32Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_32)
+        });
+        pack_codes(codes, 4, false, bytes);
+    }
+}
+
+impl Encode for G726_40Le {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_40)
+        });
+        pack_codes(codes, 5, true, bytes);
+    }
+}
+
+impl Encode for G726_40Be {
+    fn encode<C: Channel>(samples: &[C], bytes: &mut [u8]) {
+        Self::encode_with(&mut [Default::default()], 0, samples, bytes);
+    }
+
+    fn encode_with<C: Channel>(
+        states: &mut [Self::State],
+        channel: usize,
+        samples: &[C],
+        bytes: &mut [u8],
+    ) {
+        let codes = adpcm(states, channel, samples, |state, sample| {
+            state.encode(sample, &RATE_40)
+        });
+        pack_codes(codes, 5, false, bytes);
     }
 }
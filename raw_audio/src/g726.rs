@@ -0,0 +1,406 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! ITU-T G.726 ADPCM codec (16, 24, 32 and 40 kbit/s), following the ITU-T
+//! reference algorithm.  Internal values are 16-bit like the reference, so
+//! arithmetic wraps the same way.
+
+/// Tables for one bit rate.
+pub(crate) struct Rate {
+    // Bits per code word.
+    bits: u8,
+    // Quantizer decision levels.
+    quantizer: &'static [i16],
+    // Reconstructed normalized log magnitudes, by code word.
+    dqln: &'static [i16],
+    // Log scale factor multipliers, by code word.
+    wi: &'static [i32],
+    // Stationarity values, by code word.
+    fi: &'static [i16],
+}
+
+/// 16 kbit/s (2-bit code words).
+pub(crate) const RATE_16: Rate = Rate {
+    bits: 2,
+    quantizer: &[261],
+    dqln: &[116, 365, 365, 116],
+    wi: &[-704, 14048, 14048, -704],
+    fi: &[0x000, 0xE00, 0xE00, 0x000],
+};
+
+/// 24 kbit/s (3-bit code words).
+pub(crate) const RATE_24: Rate = Rate {
+    bits: 3,
+    quantizer: &[8, 218, 331],
+    dqln: &[-2048, 135, 273, 373, 373, 273, 135, -2048],
+    wi: &[-128, 960, 4384, 18624, 18624, 4384, 960, -128],
+    fi: &[0x000, 0x200, 0x400, 0xE00, 0xE00, 0x400, 0x200, 0x000],
+};
+
+/// 32 kbit/s (4-bit code words).
+pub(crate) const RATE_32: Rate = Rate {
+    bits: 4,
+    quantizer: &[-124, 80, 178, 246, 300, 349, 400],
+    dqln: &[
+        -2048, 4, 135, 213, 273, 323, 373, 425, 425, 373, 323, 273, 213, 135,
+        4, -2048,
+    ],
+    wi: &[
+        -384, 576, 1312, 2048, 3584, 6336, 11360, 35904, 35904, 11360, 6336,
+        3584, 2048, 1312, 576, -384,
+    ],
+    fi: &[
+        0x000, 0x000, 0x000, 0x200, 0x200, 0x200, 0x600, 0xE00, 0xE00, 0x600,
+        0x200, 0x200, 0x200, 0x000, 0x000, 0x000,
+    ],
+};
+
+/// 40 kbit/s (5-bit code words).
+pub(crate) const RATE_40: Rate = Rate {
+    bits: 5,
+    quantizer: &[
+        -122, -16, 68, 139, 198, 250, 298, 339, 378, 413, 445, 475, 502, 528,
+        553,
+    ],
+    dqln: &[
+        -2048, -66, 28, 104, 169, 224, 274, 318, 358, 395, 429, 459, 488, 514,
+        539, 566, 566, 539, 514, 488, 459, 429, 395, 358, 318, 274, 224, 169,
+        104, 28, -66, -2048,
+    ],
+    wi: &[
+        448, 448, 768, 1248, 1280, 1312, 1856, 3200, 4512, 5728, 7008, 8960,
+        11456, 14080, 16928, 22272, 22272, 16928, 14080, 11456, 8960, 7008,
+        5728, 4512, 3200, 1856, 1312, 1280, 1248, 768, 448, 448,
+    ],
+    fi: &[
+        0x000, 0x000, 0x000, 0x000, 0x000, 0x200, 0x200, 0x200, 0x200, 0x200,
+        0x400, 0x600, 0x800, 0xA00, 0xC00, 0xC00, 0xC00, 0xC00, 0xA00, 0x800,
+        0x600, 0x400, 0x200, 0x200, 0x200, 0x200, 0x200, 0x000, 0x000, 0x000,
+        0x000, 0x000,
+    ],
+};
+
+// Number of bits needed to represent `value` (0 for 0).
+fn log2(value: i32) -> i32 {
+    32 - value.leading_zeros() as i32
+}
+
+// Find the quantizer interval of `value`.
+fn quan(value: i32, table: &[i16]) -> usize {
+    table
+        .iter()
+        .position(|level| value < i32::from(*level))
+        .unwrap_or(table.len())
+}
+
+// Multiply a predictor coefficient by a value in the internal floating point
+// format (4-bit exponent, 6-bit mantissa).
+fn fmult(an: i32, srn: i16) -> i32 {
+    let srn = i32::from(srn);
+    let anmag = if an > 0 { an } else { (-an) & 0x1FFF };
+    let anexp = log2(anmag) - 6;
+    let anmant = if anmag == 0 {
+        32
+    } else if anexp >= 0 {
+        anmag >> anexp
+    } else {
+        anmag << -anexp
+    };
+    let wanexp = anexp + ((srn >> 6) & 0xF) - 13;
+    let wanmant = (anmant * (srn & 0o77) + 0x30) >> 4;
+    let retval = if wanexp >= 0 {
+        (wanmant << wanexp) & 0x7FFF
+    } else {
+        wanmant >> -wanexp
+    };
+    if (an ^ srn) < 0 {
+        -retval
+    } else {
+        retval
+    }
+}
+
+// Convert to the internal floating point format.
+fn float(value: i32) -> i16 {
+    let mag = value.abs();
+    let exp = log2(mag);
+    let float = (exp << 6) + ((mag << 6) >> exp);
+    if value < 0 {
+        (float - 0x400) as i16
+    } else {
+        float as i16
+    }
+}
+
+/// Codec state for one channel.
+#[derive(Clone, Debug)]
+pub struct State {
+    // Locked (steady state) step size multiplier.
+    yl: i32,
+    // Unlocked (non-steady state) step size multiplier.
+    yu: i16,
+    // Short term energy estimate.
+    dms: i16,
+    // Long term energy estimate.
+    dml: i16,
+    // Linear weighting coefficient of `yl` and `yu`.
+    ap: i16,
+    // Pole coefficients of the prediction filter.
+    a: [i16; 2],
+    // Zero coefficients of the prediction filter.
+    b: [i16; 6],
+    // Signs of the previous two partially reconstructed samples.
+    pk: [bool; 2],
+    // Previous 6 quantized difference samples (internal floating point).
+    dq: [i16; 6],
+    // Previous 2 reconstructed samples (internal floating point).
+    sr: [i16; 2],
+    // Delayed tone detect.
+    td: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            yl: 34816,
+            yu: 544,
+            dms: 0,
+            dml: 0,
+            ap: 0,
+            a: [0; 2],
+            b: [0; 6],
+            pk: [false; 2],
+            dq: [32; 6],
+            sr: [32; 2],
+            td: false,
+        }
+    }
+}
+
+impl State {
+    // Get the zero and pole signal estimates.
+    fn predict(&self) -> (i32, i32) {
+        let sezi = self
+            .b
+            .iter()
+            .zip(self.dq.iter())
+            .map(|(b, dq)| fmult(i32::from(*b) >> 2, *dq))
+            .sum::<i32>();
+        let sei = sezi
+            + fmult(i32::from(self.a[1]) >> 2, self.sr[1])
+            + fmult(i32::from(self.a[0]) >> 2, self.sr[0]);
+        (sezi >> 1, sei >> 1)
+    }
+
+    // Get the quantizer step size.
+    fn step_size(&self) -> i32 {
+        let yu = i32::from(self.yu);
+        if self.ap >= 256 {
+            return yu;
+        }
+        let y = self.yl >> 6;
+        let dif = yu - y;
+        let al = i32::from(self.ap) >> 2;
+        if dif > 0 {
+            y + ((dif * al) >> 6)
+        } else if dif < 0 {
+            y + ((dif * al + 0x3F) >> 6)
+        } else {
+            y
+        }
+    }
+
+    // Quantize the difference signal `d` into a code word.
+    fn quantize(d: i32, y: i32, rate: &Rate) -> u8 {
+        let dqm = d.abs();
+        let exp = log2(dqm >> 1);
+        let mant = ((dqm << 7) >> exp) & 0x7F;
+        let dln = (exp << 7) + mant - (y >> 2);
+        let i = quan(dln, rate.quantizer) as u8;
+        let states = 1u8 << rate.bits;
+        if d < 0 {
+            states - 1 - i
+        } else if i == 0 && rate.bits != 2 {
+            // Zero is not a valid code word with an odd number of levels.
+            states - 1
+        } else {
+            i
+        }
+    }
+
+    // Reconstruct the quantized difference signal.
+    fn reconstruct(sign: bool, dqln: i16, y: i32) -> i32 {
+        let dql = i32::from(dqln) + (y >> 2);
+        if dql < 0 {
+            return if sign { -0x8000 } else { 0 };
+        }
+        let dex = (dql >> 7) & 15;
+        let dqt = 128 + (dql & 127);
+        let dq = (dqt << 7) >> (14 - dex);
+        if sign {
+            dq - 0x8000
+        } else {
+            dq
+        }
+    }
+
+    /// Decode a code word into a 14-bit sample.
+    fn step(&mut self, code: u8, rate: &Rate) -> i32 {
+        let code = usize::from(code) & ((1 << rate.bits) - 1);
+        let (sez, se) = self.predict();
+        let y = self.step_size();
+        let sign = code & (1 << (rate.bits - 1)) != 0;
+        let dq = Self::reconstruct(sign, rate.dqln[code], y);
+        let sr = if dq < 0 { se - (dq & 0x3FFF) } else { se + dq };
+        let dqsez = sr - se + sez;
+        self.update(rate, y, rate.wi[code], rate.fi[code], dq, sr, dqsez);
+        sr
+    }
+
+    /// Decode a code word into a 16-bit sample.
+    pub(crate) fn decode(&mut self, code: u8, rate: &Rate) -> i16 {
+        (self.step(code, rate) << 2).clamp(-32768, 32767) as i16
+    }
+
+    /// Encode a 16-bit sample into a code word.
+    pub(crate) fn encode(&mut self, sample: i16, rate: &Rate) -> u8 {
+        let (_, se) = self.predict();
+        let d = (i32::from(sample) >> 2) - se;
+        let code = Self::quantize(d, self.step_size(), rate);
+        // Keep the predictor in sync with the decoder.
+        self.step(code, rate);
+        code
+    }
+
+    // Update the state after a sample.
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &mut self,
+        rate: &Rate,
+        y: i32,
+        wi: i32,
+        fi: i16,
+        dq: i32,
+        sr: i32,
+        dqsez: i32,
+    ) {
+        let pk0 = dqsez < 0;
+        let mag = dq & 0x7FFF;
+
+        // Tone transition detection.
+        let ylint = self.yl >> 15;
+        let ylfrac = (self.yl >> 10) & 0x1F;
+        let thr1 = (32 + ylfrac) << ylint;
+        let thr2 = if ylint > 9 { 31 << 10 } else { thr1 };
+        let dqthr = (thr2 + (thr2 >> 1)) >> 1;
+        let tr = self.td && mag > dqthr;
+
+        // Quantizer scale factor adaptation.
+        self.yu = (y + ((wi - y) >> 5)).clamp(544, 5120) as i16;
+        self.yl += i32::from(self.yu) + ((-self.yl) >> 6);
+
+        // Adaptive predictor coefficients.
+        let mut a2p = 0;
+        if tr {
+            self.a = [0; 2];
+            self.b = [0; 6];
+        } else {
+            let pks1 = pk0 ^ self.pk[0];
+            let a1 = i32::from(self.a[0]);
+            a2p = i32::from(self.a[1]) - (i32::from(self.a[1]) >> 7);
+            if dqsez != 0 {
+                let fa1 = if pks1 { a1 } else { -a1 };
+                if fa1 < -8191 {
+                    a2p -= 0x100;
+                } else if fa1 > 8191 {
+                    a2p += 0xFF;
+                } else {
+                    a2p += fa1 >> 5;
+                }
+                a2p = if pk0 ^ self.pk[1] {
+                    if a2p <= -12160 {
+                        -12288
+                    } else if a2p >= 12416 {
+                        12288
+                    } else {
+                        a2p - 0x80
+                    }
+                } else if a2p <= -12416 {
+                    -12288
+                } else if a2p >= 12160 {
+                    12288
+                } else {
+                    a2p + 0x80
+                };
+            }
+            self.a[1] = a2p as i16;
+
+            let mut a1 = a1 - (a1 >> 8);
+            if dqsez != 0 {
+                a1 += if pks1 { -192 } else { 192 };
+            }
+            let a1ul = 15360 - a2p;
+            self.a[0] = a1.clamp(-a1ul, a1ul) as i16;
+
+            let shift = if rate.bits == 5 { 9 } else { 8 };
+            for (b, dqn) in self.b.iter_mut().zip(self.dq.iter()) {
+                let mut bn = i32::from(*b);
+                bn -= bn >> shift;
+                if mag != 0 {
+                    if (dq ^ i32::from(*dqn)) >= 0 {
+                        bn += 128;
+                    } else {
+                        bn -= 128;
+                    }
+                }
+                *b = bn as i16;
+            }
+        }
+
+        // Delay lines.
+        self.dq.copy_within(0..5, 1);
+        self.dq[0] = if mag == 0 {
+            if dq >= 0 {
+                0x20
+            } else {
+                -992
+            }
+        } else {
+            float(if dq >= 0 { mag } else { -mag })
+        };
+        self.sr[1] = self.sr[0];
+        self.sr[0] = if sr == 0 {
+            0x20
+        } else if sr > -32768 {
+            float(sr)
+        } else {
+            -992
+        };
+        self.pk[1] = self.pk[0];
+        self.pk[0] = pk0;
+
+        // Tone detection.
+        self.td = !tr && a2p < -11776;
+
+        // Adaptation speed control.
+        let fi = i32::from(fi);
+        let dms = i32::from(self.dms);
+        let dml = i32::from(self.dml);
+        self.dms = (dms + ((fi - dms) >> 5)) as i16;
+        self.dml = (dml + (((fi << 2) - dml) >> 7)) as i16;
+        let (dms, dml) = (i32::from(self.dms), i32::from(self.dml));
+        let ap = i32::from(self.ap);
+        self.ap = if tr {
+            256
+        } else if y < 1536 || self.td || ((dms << 2) - dml).abs() >= (dml >> 3)
+        {
+            ap + ((0x200 - ap) >> 4)
+        } else {
+            ap + ((-ap) >> 4)
+        } as i16;
+    }
+}
@@ -0,0 +1,119 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Software conversion of 16-bit floating point formats.
+
+/// Convert IEEE 754 half precision (binary16) to `f32`.
+pub(crate) fn f16_to_f32(half: u16) -> f32 {
+    let sign = u32::from(half & 0x8000) << 16;
+    let exp = u32::from(half >> 10) & 0x1F;
+    let man = u32::from(half & 0x03FF);
+    let bits = match exp {
+        0 if man == 0 => sign,
+        // Subnormal: man × 2⁻²⁴ is exactly representable as `f32`.
+        0 => sign | (man as f32 / (1 << 24) as f32).to_bits(),
+        0x1F => sign | 0x7F80_0000 | (man << 13),
+        _ => sign | ((exp + 127 - 15) << 23) | (man << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// Convert `f32` to IEEE 754 half precision (binary16), rounding to nearest
+/// even.  Values out of range become infinity.
+pub(crate) fn f32_to_f16(float: f32) -> u16 {
+    let bits = float.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xFF) as i32;
+    let man = bits & 0x7F_FFFF;
+    // Infinity and NaN (keeping NaN quiet).
+    if exp == 0xFF {
+        let nan = if man != 0 {
+            0x0200 | (man >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7C00 | nan;
+    }
+    let exp = exp - 127 + 15;
+    if exp >= 0x1F {
+        return sign | 0x7C00;
+    }
+    // Subnormal (or zero) half precision, including the implicit bit.
+    let (half, shift) = if exp <= 0 {
+        if exp < -10 {
+            return sign;
+        }
+        (0, (14 - exp) as u32)
+    } else {
+        ((exp as u32) << 10, 13)
+    };
+    let man = if exp <= 0 { man | 0x80_0000 } else { man };
+    let half = half | (man >> shift);
+    let rem = man & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    // A carry out of the mantissa correctly increments the exponent.
+    let half = if rem > halfway || (rem == halfway && half & 1 == 1) {
+        half + 1
+    } else {
+        half
+    };
+    sign | half as u16
+}
+
+/// Convert bfloat16 to `f32`.
+pub(crate) fn bf16_to_f32(half: u16) -> f32 {
+    f32::from_bits(u32::from(half) << 16)
+}
+
+/// Convert `f32` to bfloat16, rounding to nearest even.
+pub(crate) fn f32_to_bf16(float: f32) -> u16 {
+    let bits = float.to_bits();
+    if float.is_nan() {
+        return (bits >> 16) as u16 | 0x0040;
+    }
+    let round = 0x7FFF + ((bits >> 16) & 1);
+    (bits.wrapping_add(round) >> 16) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f16() {
+        for (half, float) in [
+            (0x0000, 0.0),
+            (0x3C00, 1.0),
+            (0xBC00, -1.0),
+            (0x3800, 0.5),
+            (0x7BFF, 65504.0),
+            (0x0001, 5.960_464_5e-8),
+            (0x0400, 6.103_515_6e-5),
+            (0x7C00, f32::INFINITY),
+        ]
+        .iter()
+        .cloned()
+        {
+            assert_eq!(f16_to_f32(half), float);
+            assert_eq!(f32_to_f16(float), half);
+        }
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        // Round to nearest even.
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3C00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3C02);
+        assert_eq!(f32_to_f16(65520.0), 0x7C00);
+    }
+
+    #[test]
+    fn bf16() {
+        assert_eq!(bf16_to_f32(0x3F80), 1.0);
+        assert_eq!(f32_to_bf16(-2.0), 0xC000);
+        assert_eq!(f32_to_bf16(1.0 + 1.0 / 256.0), 0x3F80);
+        assert_eq!(f32_to_bf16(1.0 + 3.0 / 256.0), 0x3F82);
+        assert!(bf16_to_f32(f32_to_bf16(f32::NAN)).is_nan());
+    }
+}
@@ -0,0 +1,76 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! IMA (DVI) ADPCM codec.
+
+const INDEX_TABLE: [i8; 16] =
+    [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i16; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41,
+    45, 50, 55, 60, 66, 73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209,
+    230, 253, 279, 307, 337, 371, 408, 449, 494, 544, 598, 658, 724, 796, 876,
+    963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272, 2499, 2749,
+    3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630,
+    9493, 10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623,
+    27086, 29794, 32767,
+];
+
+/// Codec state for one channel.
+#[derive(Clone, Debug, Default)]
+pub struct State {
+    // Last decoded sample.
+    predictor: i16,
+    // Index into `STEP_TABLE`.
+    index: u8,
+}
+
+impl State {
+    /// Decode a 4-bit code into a 16-bit sample.
+    pub(crate) fn decode(&mut self, code: u8) -> i16 {
+        let step = i32::from(STEP_TABLE[usize::from(self.index)]);
+        let mut diff = step >> 3;
+        if code & 1 != 0 {
+            diff += step >> 2;
+        }
+        if code & 2 != 0 {
+            diff += step >> 1;
+        }
+        if code & 4 != 0 {
+            diff += step;
+        }
+        if code & 8 != 0 {
+            diff = -diff;
+        }
+        let predictor = i32::from(self.predictor) + diff;
+        self.predictor = predictor.clamp(-32768, 32767) as i16;
+        let index = self.index as i8 + INDEX_TABLE[usize::from(code & 0xF)];
+        self.index = index.clamp(0, 88) as u8;
+        self.predictor
+    }
+
+    /// Encode a 16-bit sample into a 4-bit code.
+    pub(crate) fn encode(&mut self, sample: i16) -> u8 {
+        let mut diff = i32::from(sample) - i32::from(self.predictor);
+        let mut code = 0;
+        if diff < 0 {
+            code = 8;
+            diff = -diff;
+        }
+        let mut step = i32::from(STEP_TABLE[usize::from(self.index)]);
+        for bit in [4, 2, 1].iter() {
+            if diff >= step {
+                code |= bit;
+                diff -= step;
+            }
+            step >>= 1;
+        }
+        // Keep the predictor in sync with the decoder.
+        self.decode(code);
+        code
+    }
+}
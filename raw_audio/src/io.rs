@@ -0,0 +1,206 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! I/O traits used by [`Decoder`](crate::Decoder) and
+//! [`Encoder`](crate::Encoder).
+//!
+//! With the `std` feature (default) these are re-exported from `std::io`.
+//! Without it, minimal replacements are provided, implemented for byte slices
+//! and `Vec<u8>`; implement [`Read`], [`Write`] or [`Seek`] for other sources
+//! and sinks (a UART, a DMA buffer, …).
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::{
+    Error, ErrorKind, Read, Result, Seek, SeekFrom, Write,
+};
+
+/// Read until `buf` is full or the end of the reader, returning the number of
+/// bytes read.
+pub(crate) fn fill<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// Kind of I/O error, a subset of `std::io::ErrorKind`.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        /// Data is not valid for the operation.
+        InvalidData,
+        /// A parameter was incorrect.
+        InvalidInput,
+        /// The end of the input was reached early.
+        UnexpectedEof,
+        /// Zero bytes were written.
+        WriteZero,
+        /// The operation was interrupted and can be retried.
+        Interrupted,
+        /// Any other error.
+        Other,
+    }
+
+    /// An I/O error.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: &'static str,
+    }
+
+    impl Error {
+        /// Create an error of a kind with a message.
+        pub fn new(kind: ErrorKind, message: &'static str) -> Self {
+            Self { kind, message }
+        }
+
+        /// Get the kind of error.
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self::new(kind, "")
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.message.is_empty() {
+                write!(f, "{:?}", self.kind)
+            } else {
+                f.write_str(self.message)
+            }
+        }
+    }
+
+    /// Result of an I/O operation.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// A source of bytes.
+    pub trait Read {
+        /// Read some bytes into `buf`, returning how many were read (0 at the
+        /// end of the input).
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+    }
+
+    /// Position to seek to.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum SeekFrom {
+        /// Bytes from the start.
+        Start(u64),
+        /// Bytes from the end.
+        End(i64),
+        /// Bytes from the current position.
+        Current(i64),
+    }
+
+    /// A source or sink with a movable position.
+    pub trait Seek {
+        /// Move to a position, returning the new position from the start.
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+        /// Get the current position from the start.
+        fn stream_position(&mut self) -> Result<u64> {
+            self.seek(SeekFrom::Current(0))
+        }
+    }
+
+    impl<S: Seek + ?Sized> Seek for &mut S {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            (**self).seek(pos)
+        }
+    }
+
+    /// A sink for bytes.
+    pub trait Write {
+        /// Write some bytes from `buf`, returning how many were written.
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        /// Flush buffered bytes to their destination.
+        fn flush(&mut self) -> Result<()>;
+
+        /// Write all of `buf`.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(n) => buf = &buf[n..],
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let len = buf.len().min(self.len());
+            let (head, tail) = self.split_at(len);
+            buf[..len].copy_from_slice(head);
+            *self = tail;
+            Ok(len)
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let len = buf.len().min(self.len());
+            let (head, tail) = core::mem::take(self).split_at_mut(len);
+            head.copy_from_slice(&buf[..len]);
+            *self = tail;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+}
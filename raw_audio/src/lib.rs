@@ -7,7 +7,9 @@
 //
 //! Crate for loading various RAW audio formats into fon types (*.pcm file
 //! extension should be preferred to *.raw, as *.raw can be used for video as
-//! well).  RAW audio formats are always interleaved.
+//! well).  RAW audio formats are usually interleaved; planar (non-interleaved)
+//! audio can be decoded and encoded with [`PlanarDecoder`] and
+//! [`PlanarEncoder`].
 //!
 //! Channel order is assumed to follow the FLAC channel order (following
 //! SMPTE/ITU-R recommendations):
@@ -23,14 +25,65 @@
 //!  - 8 Channels: Surround 7.1 (F.Left, F.Right, F.Center, LFE, B.Left,
 //!    B.Right, S.Left, S.Right)
 //!
+//! The channel count of RAW audio doesn't have to match the channel count of
+//! the [`Frame`](fon::Frame) type it's decoded into (see
+//! [`Decoder::with_channels()`]).  Channels are up/down-mixed by speaker
+//! position for the channel counts above; other channel counts are mapped by
+//! channel index.
+//!
 //! For supported RAW Sample Formats see the [pcm](crate::pcm) module.
+//!
+//! The default `std` feature can be disabled to build this crate as `no_std`
+//! with `alloc`.  The [`Decoder`] and [`Encoder`] then read and write through
+//! the minimal traits in the [`io`] module instead of `std::io`, and the
+//! planar types are unavailable.  The fon dependency still needs `std`
+//! though, so this doesn't build for targets without it (like
+//! microcontrollers) yet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
+mod convert;
 mod decoder;
+mod dither;
+mod dsd;
 mod encoder;
+mod g726;
+mod half;
+mod ima;
+pub mod io;
+mod mix;
 pub mod pcm;
+#[cfg(feature = "std")]
+mod planar;
+mod probe;
 
+pub use convert::{decode_into, encode_into, Context, Converted};
 pub use decoder::Decoder;
-pub use encoder::Encoder;
+pub use dither::{Dither, Shaping};
+pub use dsd::{BitOrder, DsdDecoder, Layout, DSD128, DSD64};
+pub use encoder::{Clip, Encoder};
+pub use mix::Mixer;
+#[cfg(feature = "std")]
+pub use planar::{PlanarDecoder, PlanarEncoder, Plane};
+pub use probe::{probe, Format, Guess};
+
+/// Number of groups of samples read or written at once (for each channel when
+/// planar).
+const BLOCK: usize = 1024;
+
+/// Get the number of samples in the smallest run of whole frames of
+/// `channels` channels that fills whole groups of `samples` samples.
+fn lcm(channels: usize, samples: usize) -> usize {
+    let (mut a, mut b) = (channels, samples);
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    channels / a * samples
+}
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,180 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Up/down-mixing between channel counts (FLAC/SMPTE channel order).
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::FRAC_1_SQRT_2;
+
+use fon::chan::{Ch64, Channel};
+use fon::Frame;
+
+/// Speaker position of a channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Position {
+    FrontL,
+    FrontR,
+    FrontC,
+    Lfe,
+    BackL,
+    BackR,
+    BackC,
+    SideL,
+    SideR,
+}
+
+use Position::*;
+
+/// Get the speaker positions for a channel count, if it has a known layout.
+fn layout(channels: usize) -> Option<&'static [Position]> {
+    Some(match channels {
+        1 => &[FrontC],
+        2 => &[FrontL, FrontR],
+        3 => &[FrontL, FrontR, FrontC],
+        4 => &[FrontL, FrontR, BackL, BackR],
+        5 => &[FrontL, FrontR, FrontC, BackL, BackR],
+        6 => &[FrontL, FrontR, FrontC, Lfe, BackL, BackR],
+        7 => &[FrontL, FrontR, FrontC, Lfe, BackC, SideL, SideR],
+        8 => &[FrontL, FrontR, FrontC, Lfe, BackL, BackR, SideL, SideR],
+        _ => return None,
+    })
+}
+
+/// Add a channel at `pos` to the closest available speakers of `output`.
+fn route(pos: Position, gain: f64, output: &[Position], row: &mut [f64]) {
+    if let Some(i) = output.iter().position(|p| *p == pos) {
+        row[i] += gain;
+        return;
+    }
+    let has = |p| output.contains(&p);
+    match pos {
+        FrontC => {
+            route(FrontL, gain * FRAC_1_SQRT_2, output, row);
+            route(FrontR, gain * FRAC_1_SQRT_2, output, row);
+        }
+        FrontL | FrontR => route(FrontC, gain * FRAC_1_SQRT_2, output, row),
+        // LFE is dropped when the output has no LFE channel.
+        Lfe => {}
+        BackL if has(SideL) => route(SideL, gain, output, row),
+        BackR if has(SideR) => route(SideR, gain, output, row),
+        SideL if has(BackL) => route(BackL, gain, output, row),
+        SideR if has(BackR) => route(BackR, gain, output, row),
+        BackL | SideL => route(FrontL, gain * FRAC_1_SQRT_2, output, row),
+        BackR | SideR => route(FrontR, gain * FRAC_1_SQRT_2, output, row),
+        BackC => {
+            route(BackL, gain * FRAC_1_SQRT_2, output, row);
+            route(BackR, gain * FRAC_1_SQRT_2, output, row);
+        }
+    }
+}
+
+/// Mixing matrix from one channel count to another, for channels in the FLAC
+/// channel order (see the [crate] documentation).
+#[derive(Debug)]
+pub struct Mixer {
+    // Number of input channels.
+    input: usize,
+    // Gains, `output` rows of `input` columns.
+    matrix: Vec<f64>,
+}
+
+impl Mixer {
+    /// Create a mixer from `input` channels to `output` channels.  Returns
+    /// `None` if no mixing is needed.
+    ///
+    /// Channel counts with a known layout (1 through 8) are mixed by speaker
+    /// position.  Otherwise channels are mapped by index, dropping extra input
+    /// channels and leaving extra output channels silent.
+    pub fn new(input: usize, output: usize) -> Option<Self> {
+        if input == output {
+            return None;
+        }
+        let mut matrix = vec![0.0; input * output];
+        match (layout(input), layout(output)) {
+            // Mono is duplicated rather than panned when upmixing.
+            (Some([FrontC]), Some(out)) if !out.contains(&FrontC) => {
+                for (row, pos) in matrix.chunks_mut(input).zip(out) {
+                    if *pos == FrontL || *pos == FrontR {
+                        row[0] = 1.0;
+                    }
+                }
+            }
+            (Some(inp), Some(out)) => {
+                let mut row = vec![0.0; output];
+                for (i, pos) in inp.iter().enumerate() {
+                    row.iter_mut().for_each(|gain| *gain = 0.0);
+                    route(*pos, 1.0, out, &mut row);
+                    for (o, gain) in row.iter().enumerate() {
+                        matrix[o * input + i] = *gain;
+                    }
+                }
+            }
+            _ => {
+                for i in 0..input.min(output) {
+                    matrix[i * input + i] = 1.0;
+                }
+            }
+        }
+        Some(Mixer { input, matrix })
+    }
+
+    /// Mix `input` channels into `output` channels.
+    pub fn mix<C: Channel>(&self, input: &[C], output: &mut [C]) {
+        for (out, row) in output.iter_mut().zip(self.matrix.chunks(self.input))
+        {
+            let sum = input
+                .iter()
+                .zip(row)
+                .map(|(chan, gain)| chan.to_f64() * gain)
+                .sum();
+            *out = C::from(Ch64::new(sum));
+        }
+    }
+}
+
+/// Build a frame from decoded channels, mixing if needed.
+pub(crate) fn frame<F: Frame>(
+    mixer: &Option<Mixer>,
+    channels: &[F::Chan],
+) -> F {
+    let mut frame = F::default();
+    match mixer {
+        Some(mixer) => mixer.mix(channels, frame.channels_mut()),
+        None => frame.channels_mut().copy_from_slice(channels),
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downmix_5_1_to_stereo() {
+        let mixer = Mixer::new(6, 2).unwrap();
+        let h = FRAC_1_SQRT_2;
+        assert_eq!(
+            mixer.matrix,
+            [1.0, 0.0, h, 0.0, h, 0.0, 0.0, 1.0, h, 0.0, 0.0, h]
+        );
+    }
+
+    #[test]
+    fn upmix_mono_to_stereo() {
+        let mixer = Mixer::new(1, 2).unwrap();
+        assert_eq!(mixer.matrix, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn unknown_layout_by_index() {
+        let mixer = Mixer::new(16, 2).unwrap();
+        assert_eq!(mixer.matrix[..16][0], 1.0);
+        assert_eq!(mixer.matrix[16..][1], 1.0);
+        assert_eq!(mixer.matrix.iter().sum::<f64>(), 2.0);
+    }
+}
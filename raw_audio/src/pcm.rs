@@ -6,13 +6,28 @@
 // LICENSE_BOOST_1_0.txt)
 
 //! Supported Audio formats by this crate.
+//!
+//! ADPCM formats (IMA and G.726) encode each sample relative to the previous
+//! samples of the same channel, so streams must be decoded from the start.
 
-mod seal {
-    pub trait Seal {}
+pub(crate) mod seal {
+    pub trait Seal {
+        /// Size of one encoded group of samples in bytes.
+        const SIZE: usize;
+        /// Number of samples packed into one group.
+        const SAMPLES: usize = 1;
+        /// Bits of an integer sample, or 0 for formats that aren't dithered.
+        const BITS: u32 = 0;
+        /// Whether each sample is coded relative to earlier samples of its
+        /// channel, so decoding has to start from the beginning of the audio.
+        const STATEFUL: bool = false;
+        /// Codec state kept for each channel (for ADPCM formats).
+        type State: Clone + Default;
+    }
 }
 
 /// A PCM Format
-pub trait Pcm: seal::Seal {}
+pub trait Pcm: crate::decoder::Decode + crate::encoder::Encode {}
 
 /// Unsigned 8-bit PCM
 pub struct U8;
@@ -50,6 +65,91 @@ pub struct S32Le;
 /// Signed 32-bit PCM Big Endian
 pub struct S32Be;
 
+/// Signed 24-bit PCM Little Endian, left-justified in 32 bits (low byte is
+/// padding)
+pub struct S24_32Le;
+/// Signed 24-bit PCM Big Endian, left-justified in 32 bits (low byte is
+/// padding)
+pub struct S24_32Be;
+/// Signed 24-bit PCM Little Endian, right-justified in 32 bits (high byte is
+/// padding)
+pub struct S24_32RLe;
+/// Signed 24-bit PCM Big Endian, right-justified in 32 bits (high byte is
+/// padding)
+pub struct S24_32RBe;
+
+/// Unsigned 20-bit PCM Little Endian, two samples packed into 5 bytes
+pub struct U20Le;
+/// Unsigned 20-bit PCM Big Endian, two samples packed into 5 bytes
+pub struct U20Be;
+/// Signed 20-bit PCM Little Endian, two samples packed into 5 bytes
+pub struct S20Le;
+/// Signed 20-bit PCM Big Endian, two samples packed into 5 bytes
+pub struct S20Be;
+
+/// Unsigned 12-bit PCM Little Endian, two samples packed into 3 bytes
+pub struct U12Le;
+/// Unsigned 12-bit PCM Big Endian, two samples packed into 3 bytes
+pub struct U12Be;
+/// Signed 12-bit PCM Little Endian, two samples packed into 3 bytes
+pub struct S12Le;
+/// Signed 12-bit PCM Big Endian, two samples packed into 3 bytes
+pub struct S12Be;
+
+/// Unsigned 4-bit PCM, two samples packed into a byte (first sample in the
+/// low nibble)
+pub struct U4Le;
+/// Unsigned 4-bit PCM, two samples packed into a byte (first sample in the
+/// high nibble)
+pub struct U4Be;
+/// Signed 4-bit PCM, two samples packed into a byte (first sample in the low
+/// nibble)
+pub struct S4Le;
+/// Signed 4-bit PCM, two samples packed into a byte (first sample in the high
+/// nibble)
+pub struct S4Be;
+
+/// 16-bit (IEEE 754 Half Precision) Floating Point PCM Little Endian
+pub struct F16Le;
+/// 16-bit (IEEE 754 Half Precision) Floating Point PCM Big Endian
+pub struct F16Be;
+/// 16-bit (bfloat16) Floating Point PCM Little Endian
+pub struct BF16Le;
+/// 16-bit (bfloat16) Floating Point PCM Big Endian
+pub struct BF16Be;
+
+/// IMA (DVI) ADPCM, two 4-bit samples packed into a byte (first sample in the
+/// low nibble)
+pub struct ImaAdpcmLe;
+/// IMA (DVI) ADPCM, two 4-bit samples packed into a byte (first sample in the
+/// high nibble)
+pub struct ImaAdpcmBe;
+
+/// G.726 16 kbit/s ADPCM, four 2-bit samples packed into a byte (first sample
+/// in the low bits, as in RFC 3551)
+pub struct G726_16Le;
+/// G.726 16 kbit/s ADPCM, four 2-bit samples packed into a byte (first sample
+/// in the high bits)
+pub struct G726_16Be;
+/// G.726 24 kbit/s ADPCM, eight 3-bit samples packed into 3 bytes (first
+/// sample in the low bits, as in RFC 3551)
+pub struct G726_24Le;
+/// G.726 24 kbit/s ADPCM, eight 3-bit samples packed into 3 bytes (first
+/// sample in the high bits)
+pub struct G726_24Be;
+/// G.726 32 kbit/s ADPCM, two 4-bit samples packed into a byte (first sample
+/// in the low bits, as in RFC 3551)
+pub struct G726_32Le;
+/// G.726 32 kbit/s ADPCM, two 4-bit samples packed into a byte (first sample
+/// in the high bits)
+pub struct G726_32Be;
+/// G.726 40 kbit/s ADPCM, eight 5-bit samples packed into 5 bytes (first
+/// sample in the low bits, as in RFC 3551)
+pub struct G726_40Le;
+/// G.726 40 kbit/s ADPCM, eight 5-bit samples packed into 5 bytes (first
+/// sample in the high bits)
+pub struct G726_40Be;
+
 /// 32-bit Floating Point PCM Little Endian
 pub struct F32Le;
 /// 32-bit Floating Point PCM Big Endian
@@ -59,47 +159,325 @@ pub struct F64Le;
 /// 64-bit Floating Point PCM Big Endian
 pub struct F64Be;
 
-impl seal::Seal for U8 {}
+impl seal::Seal for U8 {
+    const SIZE: usize = 1;
+    const BITS: u32 = 8;
+    type State = ();
+}
 impl Pcm for U8 {}
-impl seal::Seal for S8 {}
+impl seal::Seal for S8 {
+    const SIZE: usize = 1;
+    const BITS: u32 = 8;
+    type State = ();
+}
 impl Pcm for S8 {}
-impl seal::Seal for MuLaw {}
+impl seal::Seal for MuLaw {
+    const SIZE: usize = 1;
+    type State = ();
+}
 impl Pcm for MuLaw {}
-impl seal::Seal for ALaw {}
+impl seal::Seal for ALaw {
+    const SIZE: usize = 1;
+    type State = ();
+}
 impl Pcm for ALaw {}
 
-impl seal::Seal for U16Le {}
+impl seal::Seal for U16Le {
+    const SIZE: usize = 2;
+    const BITS: u32 = 16;
+    type State = ();
+}
 impl Pcm for U16Le {}
-impl seal::Seal for U16Be {}
+impl seal::Seal for U16Be {
+    const SIZE: usize = 2;
+    const BITS: u32 = 16;
+    type State = ();
+}
 impl Pcm for U16Be {}
-impl seal::Seal for S16Le {}
+impl seal::Seal for S16Le {
+    const SIZE: usize = 2;
+    const BITS: u32 = 16;
+    type State = ();
+}
 impl Pcm for S16Le {}
-impl seal::Seal for S16Be {}
+impl seal::Seal for S16Be {
+    const SIZE: usize = 2;
+    const BITS: u32 = 16;
+    type State = ();
+}
 impl Pcm for S16Be {}
 
-impl seal::Seal for U24Le {}
+impl seal::Seal for U24Le {
+    const SIZE: usize = 3;
+    const BITS: u32 = 24;
+    type State = ();
+}
 impl Pcm for U24Le {}
-impl seal::Seal for U24Be {}
+impl seal::Seal for U24Be {
+    const SIZE: usize = 3;
+    const BITS: u32 = 24;
+    type State = ();
+}
 impl Pcm for U24Be {}
-impl seal::Seal for S24Le {}
+impl seal::Seal for S24Le {
+    const SIZE: usize = 3;
+    const BITS: u32 = 24;
+    type State = ();
+}
 impl Pcm for S24Le {}
-impl seal::Seal for S24Be {}
+impl seal::Seal for S24Be {
+    const SIZE: usize = 3;
+    const BITS: u32 = 24;
+    type State = ();
+}
 impl Pcm for S24Be {}
 
-impl seal::Seal for U32Le {}
+impl seal::Seal for U32Le {
+    const SIZE: usize = 4;
+    const BITS: u32 = 32;
+    type State = ();
+}
 impl Pcm for U32Le {}
-impl seal::Seal for U32Be {}
+impl seal::Seal for U32Be {
+    const SIZE: usize = 4;
+    const BITS: u32 = 32;
+    type State = ();
+}
 impl Pcm for U32Be {}
-impl seal::Seal for S32Le {}
+impl seal::Seal for S32Le {
+    const SIZE: usize = 4;
+    const BITS: u32 = 32;
+    type State = ();
+}
 impl Pcm for S32Le {}
-impl seal::Seal for S32Be {}
+impl seal::Seal for S32Be {
+    const SIZE: usize = 4;
+    const BITS: u32 = 32;
+    type State = ();
+}
 impl Pcm for S32Be {}
 
-impl seal::Seal for F32Le {}
+impl seal::Seal for S24_32Le {
+    const SIZE: usize = 4;
+    const BITS: u32 = 24;
+    type State = ();
+}
+impl Pcm for S24_32Le {}
+impl seal::Seal for S24_32Be {
+    const SIZE: usize = 4;
+    const BITS: u32 = 24;
+    type State = ();
+}
+impl Pcm for S24_32Be {}
+impl seal::Seal for S24_32RLe {
+    const SIZE: usize = 4;
+    const BITS: u32 = 24;
+    type State = ();
+}
+impl Pcm for S24_32RLe {}
+impl seal::Seal for S24_32RBe {
+    const SIZE: usize = 4;
+    const BITS: u32 = 24;
+    type State = ();
+}
+impl Pcm for S24_32RBe {}
+
+impl seal::Seal for U20Le {
+    const SIZE: usize = 5;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 20;
+    type State = ();
+}
+impl Pcm for U20Le {}
+impl seal::Seal for U20Be {
+    const SIZE: usize = 5;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 20;
+    type State = ();
+}
+impl Pcm for U20Be {}
+impl seal::Seal for S20Le {
+    const SIZE: usize = 5;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 20;
+    type State = ();
+}
+impl Pcm for S20Le {}
+impl seal::Seal for S20Be {
+    const SIZE: usize = 5;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 20;
+    type State = ();
+}
+impl Pcm for S20Be {}
+
+impl seal::Seal for U12Le {
+    const SIZE: usize = 3;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 12;
+    type State = ();
+}
+impl Pcm for U12Le {}
+impl seal::Seal for U12Be {
+    const SIZE: usize = 3;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 12;
+    type State = ();
+}
+impl Pcm for U12Be {}
+impl seal::Seal for S12Le {
+    const SIZE: usize = 3;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 12;
+    type State = ();
+}
+impl Pcm for S12Le {}
+impl seal::Seal for S12Be {
+    const SIZE: usize = 3;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 12;
+    type State = ();
+}
+impl Pcm for S12Be {}
+
+impl seal::Seal for U4Le {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 4;
+    type State = ();
+}
+impl Pcm for U4Le {}
+impl seal::Seal for U4Be {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 4;
+    type State = ();
+}
+impl Pcm for U4Be {}
+impl seal::Seal for S4Le {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 4;
+    type State = ();
+}
+impl Pcm for S4Le {}
+impl seal::Seal for S4Be {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const BITS: u32 = 4;
+    type State = ();
+}
+impl Pcm for S4Be {}
+
+impl seal::Seal for F16Le {
+    const SIZE: usize = 2;
+    type State = ();
+}
+impl Pcm for F16Le {}
+impl seal::Seal for F16Be {
+    const SIZE: usize = 2;
+    type State = ();
+}
+impl Pcm for F16Be {}
+impl seal::Seal for BF16Le {
+    const SIZE: usize = 2;
+    type State = ();
+}
+impl Pcm for BF16Le {}
+impl seal::Seal for BF16Be {
+    const SIZE: usize = 2;
+    type State = ();
+}
+impl Pcm for BF16Be {}
+
+impl seal::Seal for ImaAdpcmLe {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const STATEFUL: bool = true;
+    type State = crate::ima::State;
+}
+impl Pcm for ImaAdpcmLe {}
+impl seal::Seal for ImaAdpcmBe {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const STATEFUL: bool = true;
+    type State = crate::ima::State;
+}
+impl Pcm for ImaAdpcmBe {}
+impl seal::Seal for G726_16Le {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 4;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_16Le {}
+impl seal::Seal for G726_16Be {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 4;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_16Be {}
+impl seal::Seal for G726_24Le {
+    const SIZE: usize = 3;
+    const SAMPLES: usize = 8;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_24Le {}
+impl seal::Seal for G726_24Be {
+    const SIZE: usize = 3;
+    const SAMPLES: usize = 8;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_24Be {}
+impl seal::Seal for G726_32Le {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_32Le {}
+impl seal::Seal for G726_32Be {
+    const SIZE: usize = 1;
+    const SAMPLES: usize = 2;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_32Be {}
+impl seal::Seal for G726_40Le {
+    const SIZE: usize = 5;
+    const SAMPLES: usize = 8;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_40Le {}
+impl seal::Seal for G726_40Be {
+    const SIZE: usize = 5;
+    const SAMPLES: usize = 8;
+    const STATEFUL: bool = true;
+    type State = crate::g726::State;
+}
+impl Pcm for G726_40Be {}
+
+impl seal::Seal for F32Le {
+    const SIZE: usize = 4;
+    type State = ();
+}
 impl Pcm for F32Le {}
-impl seal::Seal for F32Be {}
+impl seal::Seal for F32Be {
+    const SIZE: usize = 4;
+    type State = ();
+}
 impl Pcm for F32Be {}
-impl seal::Seal for F64Le {}
+impl seal::Seal for F64Le {
+    const SIZE: usize = 8;
+    type State = ();
+}
 impl Pcm for F64Le {}
-impl seal::Seal for F64Be {}
+impl seal::Seal for F64Be {
+    const SIZE: usize = 8;
+    type State = ();
+}
 impl Pcm for F64Be {}
@@ -0,0 +1,430 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::cell::RefCell;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::slice;
+
+use fon::chan::{Ch64, Channel};
+use fon::{Frame, Stream};
+
+use crate::mix::{self, Mixer};
+use crate::pcm::Pcm;
+use crate::{Clip, BLOCK};
+
+/// One channel's plane of a single planar RAW audio file, shared with the
+/// other planes of the file.  Created by
+/// [`PlanarDecoder::with_frames()`] and [`PlanarEncoder::with_frames()`].
+///
+/// Each plane keeps its own position, seeking the shared file before every
+/// read or write, and only moves past the bytes actually read or written.
+#[derive(Debug)]
+pub struct Plane<T> {
+    inner: Rc<RefCell<T>>,
+    // Current byte position within the file.
+    pos: u64,
+    // Byte position of the end of the plane.
+    end: u64,
+}
+
+impl<T: Seek> Plane<T> {
+    /// Split a file starting at the current position into `channels` planes
+    /// of `size` bytes each.
+    fn split(
+        mut inner: T,
+        channels: usize,
+        size: u64,
+    ) -> std::io::Result<Vec<Self>> {
+        let start = inner.stream_position()?;
+        let inner = Rc::new(RefCell::new(inner));
+        Ok((0..channels as u64)
+            .map(|i| Plane {
+                inner: inner.clone(),
+                pos: start + size * i,
+                end: start + size * (i + 1),
+            })
+            .collect())
+    }
+
+    // Limit `len` bytes to the end of the plane.
+    fn remaining(&self, len: usize) -> usize {
+        len.min((self.end - self.pos) as usize)
+    }
+}
+
+impl<T: Read + Seek> Read for Plane<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.remaining(buf.len());
+        let mut inner = self.inner.borrow_mut();
+        inner.seek(SeekFrom::Start(self.pos))?;
+        let read = inner.read(&mut buf[..len])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<T: Write + Seek> Write for Plane<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.remaining(buf.len());
+        let mut inner = self.inner.borrow_mut();
+        inner.seek(SeekFrom::Start(self.pos))?;
+        let written = inner.write(&buf[..len])?;
+        self.pos += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.borrow_mut().flush()
+    }
+}
+
+// Bytes read from one channel's reader.
+struct Buffer<C: Channel> {
+    bytes: Vec<u8>,
+    // Number of valid bytes.
+    len: usize,
+    // Number of bytes already decoded.
+    pos: usize,
+    // Last decoded group of samples.
+    samples: Vec<C>,
+    // Index of the next sample in `samples`.
+    next: usize,
+}
+
+impl<C: Channel> Buffer<C> {
+    // Read until at least `size` bytes are buffered, returning `false` if the
+    // reader ends first.  Bytes read before an error stay buffered, so reading
+    // resumes where it stopped.
+    fn refill<R: Read>(
+        &mut self,
+        reader: &mut R,
+        size: usize,
+    ) -> std::io::Result<bool> {
+        if self.len - self.pos < size {
+            self.bytes.copy_within(self.pos..self.len, 0);
+            self.len -= self.pos;
+            self.pos = 0;
+        }
+        while self.len - self.pos < size {
+            match reader.read(&mut self.bytes[self.len..]) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.len += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Decoder for planar (non-interleaved) RAW audio, where each channel is
+/// stored separately.
+///
+/// No channel advances unless every channel has a sample, so after an error
+/// from one of the readers, decoding can be retried without the channels
+/// going out of step.
+pub struct PlanarDecoder<R: Read, F: Frame, P: Pcm> {
+    // One reader for each channel.
+    readers: Vec<R>,
+    buffers: Vec<Buffer<F::Chan>>,
+    // Codec state of each channel.
+    states: Vec<P::State>,
+    // Number of frames left, if known.
+    remaining: Option<u64>,
+    // Channels of the frame currently being decoded.
+    decoded: Vec<F::Chan>,
+    // Mixing from RAW audio channels to `F` channels, if they differ.
+    mixer: Option<Mixer>,
+    _phantom: PhantomData<(F, P)>,
+}
+
+impl<R: Read, F: Frame, P: Pcm> PlanarDecoder<R, F, P> {
+    /// Create a new planar RAW audio decoder with one reader for each
+    /// channel.  Channels are mixed like [`Decoder`](crate::Decoder)'s if the
+    /// number of readers differs from `F`'s channel count.
+    ///
+    /// # Panics
+    /// If `readers` is empty.
+    pub fn new(readers: Vec<R>, pcm: P) -> Self {
+        let _ = pcm;
+        assert!(
+            !readers.is_empty(),
+            "RAW audio must have at least one channel"
+        );
+        let channels = readers.len();
+        Self {
+            readers,
+            buffers: (0..channels)
+                .map(|_| Buffer {
+                    bytes: vec![0; BLOCK * P::SIZE],
+                    len: 0,
+                    pos: 0,
+                    samples: vec![F::Chan::default(); P::SAMPLES],
+                    next: P::SAMPLES,
+                })
+                .collect(),
+            states: vec![P::State::default(); channels],
+            remaining: None,
+            decoded: vec![F::Chan::default(); channels],
+            mixer: Mixer::new(channels, F::CHAN_COUNT),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the number of channels in the RAW audio.
+    pub fn channels(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Decode the next frame without mixing, returning one channel for each
+    /// of the RAW audio's [`channels()`](PlanarDecoder::channels).  Returns
+    /// `None` at the end of the audio.
+    pub fn next_channels(&mut self) -> Option<std::io::Result<&[F::Chan]>> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        // Decode the next group of samples of channels that ran out first.
+        let channels = self.readers.iter_mut().zip(self.buffers.iter_mut());
+        for (i, (reader, buffer)) in channels.enumerate() {
+            if buffer.next < P::SAMPLES {
+                continue;
+            }
+            match buffer.refill(reader, P::SIZE) {
+                Ok(true) => {}
+                // End of audio is detected on the first channel.
+                Ok(false) if i == 0 && buffer.len == buffer.pos => return None,
+                Ok(false) => {
+                    return Some(Err(ErrorKind::UnexpectedEof.into()));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+            let bytes = &buffer.bytes[buffer.pos..][..P::SIZE];
+            let state = slice::from_mut(&mut self.states[i]);
+            P::decode_with(state, 0, bytes, &mut buffer.samples);
+            buffer.pos += P::SIZE;
+            buffer.next = 0;
+        }
+        for (decoded, buffer) in self.decoded.iter_mut().zip(&mut self.buffers)
+        {
+            *decoded = buffer.samples[buffer.next];
+            buffer.next += 1;
+        }
+        if let Some(ref mut remaining) = self.remaining {
+            *remaining -= 1;
+        }
+        Some(Ok(&self.decoded))
+    }
+}
+
+impl<T: Read + Seek, F: Frame, P: Pcm> PlanarDecoder<Plane<T>, F, P> {
+    /// Create a new planar RAW audio decoder for a single file containing
+    /// `frames` samples of each channel, one channel after another, starting
+    /// at the reader's current position.
+    ///
+    /// # Panics
+    /// If `channels` is zero.
+    pub fn with_frames(
+        reader: T,
+        pcm: P,
+        channels: usize,
+        frames: u64,
+    ) -> std::io::Result<Self> {
+        let size = plane_size::<P>(frames);
+        let mut decoder = Self::new(Plane::split(reader, channels, size)?, pcm);
+        decoder.remaining = Some(frames);
+        Ok(decoder)
+    }
+}
+
+impl<R: Read, F: Frame, P: Pcm> Iterator for PlanarDecoder<R, F, P> {
+    type Item = std::io::Result<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.next_channels()? {
+            return Some(Err(e));
+        }
+        Some(Ok(mix::frame(&self.mixer, &self.decoded)))
+    }
+}
+
+// Size in bytes of a plane of `frames` samples.
+fn plane_size<P: Pcm>(frames: u64) -> u64 {
+    frames.div_ceil(P::SAMPLES as u64) * P::SIZE as u64
+}
+
+/// Encoder for planar (non-interleaved) RAW audio, where each channel is
+/// stored separately.  Like [`Encoder`](crate::Encoder), encoded bytes are
+/// buffered until [`flush()`](PlanarEncoder::flush) or
+/// [`into_inner()`](PlanarEncoder::into_inner).
+pub struct PlanarEncoder<W: Write, F: Frame, P: Pcm> {
+    // One writer for each channel.
+    writers: Vec<W>,
+    // Encoded bytes not yet written, for each channel.
+    buffers: Vec<Vec<u8>>,
+    // Samples waiting to be packed together with following samples, for
+    // each channel.
+    pending: Vec<Vec<F::Chan>>,
+    // Codec state of each channel.
+    states: Vec<P::State>,
+    // Policy for out-of-range samples.
+    clip: Clip,
+    // Number of out-of-range samples.
+    clipped: u64,
+    _phantom: PhantomData<(F, P)>,
+}
+
+impl<W: Write, F: Frame, P: Pcm> PlanarEncoder<W, F, P> {
+    /// Create a new planar RAW audio encoder with one writer for each of
+    /// `F`'s channels.
+    ///
+    /// # Panics
+    /// If the number of writers doesn't match `F`'s channel count.
+    pub fn new(writers: Vec<W>, pcm: P) -> Self {
+        let _ = pcm;
+        assert_eq!(writers.len(), F::CHAN_COUNT, "Need one writer per channel");
+        Self {
+            buffers: (0..writers.len())
+                .map(|_| Vec::with_capacity(BLOCK * P::SIZE))
+                .collect(),
+            pending: (0..writers.len())
+                .map(|_| Vec::with_capacity(P::SAMPLES))
+                .collect(),
+            states: vec![P::State::default(); writers.len()],
+            writers,
+            clip: Clip::default(),
+            clipped: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Set how samples outside of -1 to 1 (and NaN) are encoded.
+    pub fn with_clip(mut self, clip: Clip) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    /// Get the number of samples encoded so far that were outside of -1 to 1
+    /// (or NaN).  With [`Clip::Error`], the failing sample is counted too, and
+    /// no channel of its frame is encoded.
+    pub fn clipped(&self) -> u64 {
+        self.clipped
+    }
+
+    /// Append encoded data from a stream to each channel's output, returning
+    /// the number of frames written.  This can be called multiple times to
+    /// encode as needed instead of all at once.  Never returns for infinite
+    /// streams; use [`encode_frames()`](PlanarEncoder::encode_frames) for
+    /// those.
+    pub fn encode<S: Stream<F>>(
+        &mut self,
+        stream: S,
+    ) -> std::io::Result<usize> {
+        self.encode_frames(&mut stream.into_iter(), usize::MAX)
+    }
+
+    /// Append at most `frames` frames of encoded data from an iterator (for
+    /// example a [`Stream`]'s) to each channel's output, returning the number
+    /// of frames written (fewer at the end of the iterator).  Streams with an
+    /// unknown or infinite length can be encoded a chunk at a time this way,
+    /// each call continuing where the last one stopped.
+    pub fn encode_frames<I: Iterator<Item = F>>(
+        &mut self,
+        iter: &mut I,
+        frames: usize,
+    ) -> std::io::Result<usize> {
+        let mut written = 0;
+        for mut frame in iter.take(frames) {
+            for chan in frame.channels_mut() {
+                let sample = chan.to_f64();
+                if !(-1.0..=1.0).contains(&sample) {
+                    self.clipped += 1;
+                }
+                if let Some(clipped) = self.clip.apply(sample)? {
+                    *chan = F::Chan::from(Ch64::new(clipped));
+                }
+            }
+            let channels = frame.channels().iter().zip(&mut self.pending);
+            let channels =
+                channels.zip(&mut self.buffers).zip(&mut self.states);
+            for (((chan, pending), buffer), state) in channels {
+                pending.push(*chan);
+                if pending.len() == P::SAMPLES {
+                    encode::<P, _>(pending, buffer, state);
+                }
+            }
+            if self.buffers[0].len() >= BLOCK * P::SIZE {
+                self.write()?;
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Write out buffered bytes and flush each channel's writer.  For formats
+    /// that pack multiple samples together, samples of an incomplete group
+    /// stay buffered until the group is filled.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.write()?;
+        self.writers
+            .iter_mut()
+            .try_for_each(|writer| writer.flush())
+    }
+
+    /// Finish encoding, write out buffered bytes and return the writers.  For
+    /// formats that pack multiple samples together, a partially filled group
+    /// of samples at the end of each channel is padded with silence.
+    pub fn into_inner(mut self) -> std::io::Result<Vec<W>> {
+        let channels = self.pending.iter_mut().zip(&mut self.buffers);
+        for ((pending, buffer), state) in channels.zip(&mut self.states) {
+            if !pending.is_empty() {
+                pending.resize(P::SAMPLES, F::Chan::default());
+                encode::<P, _>(pending, buffer, state);
+            }
+        }
+        self.write()?;
+        Ok(self.writers)
+    }
+
+    // Write out buffered bytes for each channel.
+    fn write(&mut self) -> std::io::Result<()> {
+        for (writer, buffer) in self.writers.iter_mut().zip(&mut self.buffers) {
+            writer.write_all(buffer)?;
+            buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<T: Write + Seek, F: Frame, P: Pcm> PlanarEncoder<Plane<T>, F, P> {
+    /// Create a new planar RAW audio encoder for a single file containing
+    /// `frames` samples of each channel, one channel after another, starting
+    /// at the writer's current position.  Encoding more than `frames` frames
+    /// fails with [`ErrorKind::WriteZero`].
+    pub fn with_frames(
+        writer: T,
+        pcm: P,
+        frames: u64,
+    ) -> std::io::Result<Self> {
+        let size = plane_size::<P>(frames);
+        Ok(Self::new(Plane::split(writer, F::CHAN_COUNT, size)?, pcm))
+    }
+}
+
+// Encode a full group of pending samples, appending to `buffer`.
+fn encode<P: Pcm, C: Channel>(
+    pending: &mut Vec<C>,
+    buffer: &mut Vec<u8>,
+    state: &mut P::State,
+) {
+    let start = buffer.len();
+    buffer.resize(start + P::SIZE, 0);
+    let state = slice::from_mut(state);
+    P::encode_with(state, 0, pending, &mut buffer[start..]);
+    pending.clear();
+}
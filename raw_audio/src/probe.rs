@@ -0,0 +1,218 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+//! Heuristic detection of RAW audio parameters.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use fon::chan::Channel;
+use fon::mono::Mono64;
+
+use crate::pcm::{self, Pcm};
+use crate::Decoder;
+
+/// Maximum number of bytes looked at.
+const LIMIT: usize = 1 << 16;
+
+/// Maximum channel count considered.
+const MAX_CHANNELS: usize = 8;
+
+/// Sample formats considered by [`probe()`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Unsigned 8-bit PCM ([`pcm::U8`])
+    U8,
+    /// Signed 8-bit PCM ([`pcm::S8`])
+    S8,
+    /// 8-bit µ-Law companded PCM ([`pcm::MuLaw`])
+    MuLaw,
+    /// 8-bit A-Law companded PCM ([`pcm::ALaw`])
+    ALaw,
+    /// Unsigned 16-bit PCM, little endian ([`pcm::U16Le`])
+    U16Le,
+    /// Unsigned 16-bit PCM, big endian ([`pcm::U16Be`])
+    U16Be,
+    /// Signed 16-bit PCM, little endian ([`pcm::S16Le`])
+    S16Le,
+    /// Signed 16-bit PCM, big endian ([`pcm::S16Be`])
+    S16Be,
+    /// Signed 24-bit PCM, little endian ([`pcm::S24Le`])
+    S24Le,
+    /// Signed 24-bit PCM, big endian ([`pcm::S24Be`])
+    S24Be,
+    /// Signed 32-bit PCM, little endian ([`pcm::S32Le`])
+    S32Le,
+    /// Signed 32-bit PCM, big endian ([`pcm::S32Be`])
+    S32Be,
+    /// 32-bit float PCM, little endian ([`pcm::F32Le`])
+    F32Le,
+    /// 32-bit float PCM, big endian ([`pcm::F32Be`])
+    F32Be,
+    /// 64-bit float PCM, little endian ([`pcm::F64Le`])
+    F64Le,
+    /// 64-bit float PCM, big endian ([`pcm::F64Be`])
+    F64Be,
+}
+
+/// A possible interpretation of RAW audio, from [`probe()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Guess {
+    /// Sample format.
+    pub format: Format,
+    /// Number of interleaved channels.
+    pub channels: usize,
+    /// How likely this interpretation is, from 0 to 1.
+    pub score: f64,
+}
+
+/// Guess the sample format and channel count of headerless RAW audio from
+/// its first bytes, most likely interpretation first.
+///
+/// Guesses are scored with signal statistics: audio is smooth from one sample
+/// to the next within a channel, has little DC offset, and float samples are
+/// finite and near -1 to 1.  Silence or very short input can't be told apart,
+/// and noise-like audio scores low for every interpretation.
+pub fn probe(bytes: &[u8]) -> Vec<Guess> {
+    let bytes = &bytes[..bytes.len().min(LIMIT)];
+    let mut guesses = Vec::new();
+    for channels in 1..=MAX_CHANNELS {
+        let mut guess = |format, score| {
+            guesses.push(Guess {
+                format,
+                channels,
+                score,
+            })
+        };
+        guess(Format::U8, score(bytes, pcm::U8, channels, 0, true));
+        guess(Format::S8, score(bytes, pcm::S8, channels, 0, true));
+        guess(Format::MuLaw, score(bytes, pcm::MuLaw, channels, 0, true));
+        guess(Format::ALaw, score(bytes, pcm::ALaw, channels, 0, true));
+        guess(Format::U16Le, score(bytes, pcm::U16Le, channels, 1, true));
+        guess(Format::U16Be, score(bytes, pcm::U16Be, channels, 1, false));
+        guess(Format::S16Le, score(bytes, pcm::S16Le, channels, 1, true));
+        guess(Format::S16Be, score(bytes, pcm::S16Be, channels, 1, false));
+        guess(Format::S24Le, score(bytes, pcm::S24Le, channels, 2, true));
+        guess(Format::S24Be, score(bytes, pcm::S24Be, channels, 2, false));
+        guess(Format::S32Le, score(bytes, pcm::S32Le, channels, 3, true));
+        guess(Format::S32Be, score(bytes, pcm::S32Be, channels, 3, false));
+        guess(Format::F32Le, score(bytes, pcm::F32Le, channels, 1, true));
+        guess(Format::F32Be, score(bytes, pcm::F32Be, channels, 1, false));
+        guess(Format::F64Le, score(bytes, pcm::F64Le, channels, 4, true));
+        guess(Format::F64Be, score(bytes, pcm::F64Be, channels, 4, false));
+    }
+    guesses.sort_by(|a, b| b.score.total_cmp(&a.score));
+    guesses
+}
+
+// Score one interpretation of the bytes, checking the `lower` least
+// significant bytes of each sample in byte order `little` for smoothness.
+fn score<P: Pcm>(
+    bytes: &[u8],
+    pcm: P,
+    channels: usize,
+    lower: usize,
+    little: bool,
+) -> f64 {
+    let mut decoder =
+        Decoder::<_, Mono64, _>::with_channels(bytes, pcm, channels);
+    let mut stats = vec![Stats::default(); channels];
+    let mut invalid = 0;
+    let mut count = 0;
+    while let Some(Ok(frame)) = decoder.next_channels() {
+        for (chan, stats) in frame.iter().zip(stats.iter_mut()) {
+            let sample = chan.to_f64();
+            count += 1;
+            // Garbage read as float is often huge, tiny, NaN or infinite.
+            if !sample.is_finite()
+                || sample.abs() > 2.0
+                || (sample != 0.0 && sample.abs() < 1e-20)
+            {
+                invalid += 1;
+                continue;
+            }
+            stats.add(sample);
+        }
+    }
+    // Need a few frames to say anything.
+    if count < channels * 16 {
+        return 0.0;
+    }
+    let smoothness =
+        stats.iter().map(Stats::smoothness).sum::<f64>() / channels as f64;
+    let offset = stats.iter().map(Stats::offset).fold(0.0, f64::max);
+    let invalid = invalid as f64 / count as f64;
+    // The lower bytes of audio samples are close to noise (or zero); if one
+    // is smooth, it's more likely part of a sample of its own.
+    let lanes = (0..lower)
+        .map(|i| if little { i } else { P::SIZE - 1 - i })
+        .map(|offset| lane(bytes, P::SIZE, channels, offset))
+        .fold(0.0, f64::max);
+    let lanes = (2.0 * lanes - 1.0).max(0.0);
+    // Slightly prefer fewer channels when otherwise equal.
+    let channels = 0.001 * channels as f64;
+    (smoothness - offset - 4.0 * invalid - lanes - channels).clamp(0.0, 1.0)
+}
+
+// Get the smoothness of one byte lane of each channel's samples.
+fn lane(bytes: &[u8], size: usize, channels: usize, offset: usize) -> f64 {
+    let mut stats = vec![Stats::default(); channels];
+    for frame in bytes.chunks_exact(size * channels) {
+        for (sample, stats) in frame.chunks_exact(size).zip(stats.iter_mut()) {
+            stats.add(sample[offset].into());
+        }
+    }
+    stats.iter().map(Stats::smoothness).sum::<f64>() / channels as f64
+}
+
+/// Running statistics of one channel.
+#[derive(Clone, Default)]
+struct Stats {
+    count: f64,
+    sum: f64,
+    // Sum of squares.
+    energy: f64,
+    // Sum of squared differences of consecutive samples.
+    diffs: f64,
+    last: Option<f64>,
+}
+
+impl Stats {
+    fn add(&mut self, sample: f64) {
+        self.count += 1.0;
+        self.sum += sample;
+        self.energy += sample * sample;
+        if let Some(last) = self.last {
+            self.diffs += (sample - last) * (sample - last);
+        }
+        self.last = Some(sample);
+    }
+
+    // Lag-1 autocorrelation (without DC offset); near 1 for audio, near 0 for
+    // noise.
+    fn smoothness(&self) -> f64 {
+        if self.count < 2.0 {
+            return 0.0;
+        }
+        let mean = self.sum / self.count;
+        let variance = self.energy / self.count - mean * mean;
+        if variance <= f64::EPSILON {
+            return 0.0;
+        }
+        // Difference energy is independent of DC offset.
+        let diffs = self.diffs / (self.count - 1.0);
+        1.0 - diffs / (2.0 * variance)
+    }
+
+    // DC offset, 0 to 1.
+    fn offset(&self) -> f64 {
+        if self.count == 0.0 {
+            return 0.0;
+        }
+        (self.sum / self.count).abs().min(1.0)
+    }
+}
@@ -0,0 +1,154 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::io::ErrorKind;
+
+use fon::chan::{Ch16, Ch64, Channel};
+use fon::mono::{Mono16, Mono64};
+use fon::stereo::Stereo64;
+use fon::{Audio, Frame};
+use raw_audio::pcm;
+use raw_audio::{Clip, Decoder, Encoder, PlanarEncoder};
+
+// Out-of-range and non-finite samples, then two in range.
+const SAMPLES: [f64; 7] = [
+    1.5,
+    -3.0,
+    f64::NAN,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    0.5,
+    0.95,
+];
+
+fn mono(samples: &[f64]) -> Vec<Mono64> {
+    samples
+        .iter()
+        .map(|&sample| Mono64::new(Ch64::from_f64(sample)))
+        .collect()
+}
+
+// Encode samples as S16Le with a clip policy, returning the clipped count.
+fn encode(clip: Clip, samples: &[f64]) -> (Vec<u8>, u64) {
+    let audio = Audio::with_frames(48_000, mono(samples));
+    let mut encoder = Encoder::new(Vec::new(), pcm::S16Le).with_clip(clip);
+    encoder.encode(&audio).unwrap();
+    let clipped = encoder.clipped();
+    (encoder.into_inner().unwrap(), clipped)
+}
+
+#[test]
+fn hard() {
+    let (bytes, clipped) = encode(Clip::Hard, &SAMPLES);
+    assert_eq!(
+        bytes[..10],
+        [0xFF, 0x7F, 0x00, 0x80, 0, 0, 0xFF, 0x7F, 0x00, 0x80]
+    );
+    assert_eq!(bytes[10..], encode(Clip::Hard, &[0.5, 0.95]).0[..]);
+    assert_eq!(clipped, 5);
+    assert_eq!(encode(Clip::default(), &SAMPLES), (bytes, 5));
+}
+
+#[test]
+fn soft() {
+    let soft = |over: f64| 0.9 + 0.1 * over.tanh();
+    let expected = [soft(6.0), -soft(21.0), 0.0, 1.0, -1.0, 0.5, soft(0.5)];
+    let (bytes, clipped) = encode(Clip::Soft, &SAMPLES);
+    assert_eq!(bytes, encode(Clip::Hard, &expected).0);
+    // Samples above the knee are changed, but only those out of range count.
+    assert_eq!(clipped, 5);
+}
+
+#[test]
+fn wrap() {
+    let expected = [-0.5, -1.0, 0.0, 0.0, 0.0, 0.5, 0.95];
+    let (bytes, clipped) = encode(Clip::Wrap, &SAMPLES);
+    assert_eq!(bytes, encode(Clip::Hard, &expected).0);
+    assert_eq!(clipped, 5);
+}
+
+#[test]
+fn error() {
+    for &sample in &SAMPLES[..5] {
+        let audio = Audio::with_frames(48_000, mono(&[0.5, sample, 0.5]));
+        let mut encoder =
+            Encoder::new(Vec::new(), pcm::S16Le).with_clip(Clip::Error);
+        let error = encoder.encode(&audio).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(encoder.clipped(), 1);
+        // Samples before the failing one are still encoded.
+        let bytes = encoder.into_inner().unwrap();
+        assert_eq!(bytes, encode(Clip::Error, &[0.5]).0);
+    }
+    assert_eq!(encode(Clip::Error, &[0.5, -1.0, 1.0]).1, 0);
+}
+
+#[test]
+fn planar() {
+    // Each policy encodes planes like the interleaved encoder.
+    for &clip in &[Clip::Hard, Clip::Soft, Clip::Wrap] {
+        let audio = Audio::with_frames(48_000, mono(&SAMPLES));
+        let writers = vec![Vec::new()];
+        let mut encoder =
+            PlanarEncoder::new(writers, pcm::S16Le).with_clip(clip);
+        encoder.encode(&audio).unwrap();
+        assert_eq!(encoder.clipped(), 5);
+        let planes = encoder.into_inner().unwrap();
+        assert_eq!(planes[0], encode(clip, &SAMPLES).0, "{:?}", clip);
+    }
+
+    // A failing frame isn't encoded on any channel.
+    let frames = [(0.5, 0.5), (0.5, f64::NAN), (0.5, 0.5)];
+    let frames: Vec<Stereo64> = frames
+        .iter()
+        .map(|&(l, r)| Stereo64::new(Ch64::from_f64(l), Ch64::from_f64(r)))
+        .collect();
+    let audio = Audio::with_frames(48_000, frames);
+    let writers = vec![Vec::new(), Vec::new()];
+    let mut encoder =
+        PlanarEncoder::new(writers, pcm::S16Le).with_clip(Clip::Error);
+    let error = encoder.encode(&audio).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(encoder.clipped(), 1);
+    let planes = encoder.into_inner().unwrap();
+    assert_eq!(planes[0], encode(Clip::Error, &[0.5]).0);
+    assert_eq!(planes[1], planes[0]);
+}
+
+#[test]
+fn sanitize() {
+    let samples = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 2.0, 0.25];
+    let bytes: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+    let decode = |sanitize| -> Vec<f64> {
+        Decoder::<_, Mono64, _>::with_channels(&bytes[..], pcm::F32Le, 1)
+            .with_sanitize(sanitize)
+            .map(|frame| frame.unwrap().channels()[0].to_f64())
+            .collect()
+    };
+    let decoded = decode(false);
+    assert!(decoded[0].is_nan());
+    assert_eq!(decoded[1..], [1.0, -1.0, 1.0, 0.25]);
+    assert_eq!(decode(true), [0.0, 1.0, -1.0, 1.0, 0.25]);
+
+    // 16-bit channels can't hold NaN.
+    let decoded: Vec<Mono16> =
+        Decoder::<_, Mono16, _>::with_channels(&bytes[..], pcm::F32Le, 1)
+            .map(Result::unwrap)
+            .collect();
+    assert_eq!(decoded[0], Mono16::new(Ch16::new(0)));
+
+    let bytes = f64::NAN.to_be_bytes();
+    let decoded: Vec<Mono64> =
+        Decoder::<_, Mono64, _>::with_channels(&bytes[..], pcm::F64Be, 1)
+            .with_sanitize(true)
+            .map(Result::unwrap)
+            .collect();
+    assert_eq!(decoded, [Mono64::new(Ch64::new(0.0))]);
+}
@@ -0,0 +1,30 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+// Helpers shared by the integration tests; not every test uses all of them.
+#![allow(dead_code)]
+
+use std::f64::consts::TAU;
+
+use fon::chan::Ch64;
+use fon::Frame;
+
+// Generate a sine wave with a different frequency on each channel, so swapped
+// channels are caught.
+pub fn signal<F: Frame>(len: usize) -> Vec<F> {
+    (0..len)
+        .map(|i| {
+            let mut frame = F::default();
+            for (c, chan) in frame.channels_mut().iter_mut().enumerate() {
+                let t = i as f64 / 48_000.0;
+                let x = 0.9 * (TAU * 441.0 * (c + 1) as f64 * t).sin();
+                *chan = Ch64::new(x).into();
+            }
+            frame
+        })
+        .collect()
+}
@@ -0,0 +1,136 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign,
+};
+
+use fon::chan::Ch64;
+use fon::stereo::Stereo64;
+use fon::{Audio, Frame};
+use raw_audio::pcm::{self, Pcm};
+use raw_audio::{decode_into, encode_into, Context, Decoder, Encoder};
+
+use common::signal;
+
+// Encode and decode a stream a chunk of frames at a time, checking it matches
+// the streaming encoder and decoder.
+fn check<F: Frame, P: Pcm>(pcm: impl Fn() -> P) {
+    let input = signal::<F>(1000);
+    let audio = Audio::with_frames(48_000, input.clone());
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    encoder.encode(&audio).unwrap();
+    let expected = encoder.into_inner().unwrap();
+
+    let mut context = Context::new(pcm());
+    let mut bytes = Vec::new();
+    for chunk in input.chunks(100) {
+        let mut buffer = [0; 8192];
+        let converted = encode_into(&mut context, chunk, &mut buffer);
+        assert_eq!(converted.frames, chunk.len());
+        bytes.extend(&buffer[..converted.bytes]);
+    }
+    assert_eq!(bytes, expected);
+
+    let decoder =
+        Decoder::<_, F, _>::with_channels(&expected[..], pcm(), F::CHAN_COUNT);
+    let expected: Vec<F> = decoder.map(Result::unwrap).collect();
+    let mut context = Context::new(pcm());
+    let mut frames: Vec<F> = Vec::new();
+    for chunk in bytes.chunks(bytes.len() / 10) {
+        let mut buffer = [F::default(); 100];
+        let converted = decode_into(&mut context, chunk, &mut buffer);
+        assert_eq!(converted.bytes, chunk.len());
+        frames.extend(&buffer[..converted.frames]);
+    }
+    assert_eq!(frames, expected);
+}
+
+#[test]
+fn chunks() {
+    check::<Stereo64, _>(|| pcm::ImaAdpcmLe);
+    check::<Stereo64, _>(|| pcm::G726_40Be);
+    check::<Stereo64, _>(|| pcm::S20Le);
+}
+
+// A frame with more channels than any of fon's.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct Wide([Ch64; 10]);
+
+impl Frame for Wide {
+    type Chan = Ch64;
+
+    const CONFIG: &'static [f64] =
+        &[-0.8, -0.6, -0.4, -0.2, 0.0, 0.2, 0.4, 0.6, 0.8, 1.0];
+
+    fn channels(&self) -> &[Ch64] {
+        &self.0
+    }
+
+    fn channels_mut(&mut self) -> &mut [Ch64] {
+        &mut self.0
+    }
+
+    fn from_channels(channels: &[Ch64]) -> Self {
+        let mut frame = Self::default();
+        frame.0.copy_from_slice(channels);
+        frame
+    }
+}
+
+impl Iterator for Wide {
+    type Item = Self;
+
+    fn next(&mut self) -> Option<Self> {
+        Some(*self)
+    }
+}
+
+impl Neg for Wide {
+    type Output = Self;
+
+    fn neg(mut self) -> Self {
+        self.0.iter_mut().for_each(|chan| *chan = -*chan);
+        self
+    }
+}
+
+// Implement a channel-wise operator and its assigning form.
+macro_rules! op {
+    ($op:ident, $fn:ident, $op_assign:ident, $fn_assign:ident) => {
+        impl $op for Wide {
+            type Output = Self;
+
+            fn $fn(mut self, other: Self) -> Self {
+                self.$fn_assign(other);
+                self
+            }
+        }
+
+        impl $op_assign for Wide {
+            fn $fn_assign(&mut self, other: Self) {
+                for (chan, other) in self.0.iter_mut().zip(other.0) {
+                    *chan = $op::$fn(*chan, other);
+                }
+            }
+        }
+    };
+}
+
+op!(Add, add, AddAssign, add_assign);
+op!(Sub, sub, SubAssign, sub_assign);
+op!(Mul, mul, MulAssign, mul_assign);
+op!(Div, div, DivAssign, div_assign);
+
+#[test]
+fn many_channels() {
+    check::<Wide, _>(|| pcm::ImaAdpcmBe);
+    check::<Wide, _>(|| pcm::S16Le);
+    check::<Wide, _>(|| pcm::U12Be);
+}
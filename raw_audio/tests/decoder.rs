@@ -0,0 +1,89 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use fon::chan::Channel;
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::surround::Surround64;
+use fon::Frame;
+use raw_audio::pcm;
+use raw_audio::Decoder;
+
+const FRAMES: usize = 100;
+
+// Interleave frames of S16Le samples, each channel holding a constant level.
+fn s16le(levels: &[f64]) -> Vec<u8> {
+    let frame: Vec<u8> = levels
+        .iter()
+        .flat_map(|level| ((level * 32767.5) as i16).to_le_bytes())
+        .collect();
+    frame.repeat(FRAMES)
+}
+
+// Decode all frames, checking each against the expected channel levels.
+fn check<F: Frame>(bytes: &[u8], channels: usize, expected: &[f64]) {
+    let decoder =
+        Decoder::<_, F, _>::with_channels(bytes, pcm::S16Le, channels);
+    assert_eq!(decoder.channels(), channels);
+    let mut count = 0;
+    for frame in decoder {
+        let frame = frame.unwrap();
+        for (chan, expected) in frame.channels().iter().zip(expected) {
+            let error = chan.to_f64() - expected;
+            assert!(error.abs() < 1e-4, "{:?} != {:?}", frame, expected);
+        }
+        count += 1;
+    }
+    assert_eq!(count, FRAMES);
+}
+
+#[test]
+fn surround_to_stereo() {
+    // Front left, front right, center, LFE, back left, back right.
+    let bytes = s16le(&[0.1, 0.2, 0.3, 0.4, 0.05, -0.05]);
+    let h = FRAC_1_SQRT_2;
+    // Center and back channels fold into the front pair; LFE is dropped.
+    let left = 0.1 + 0.3 * h + 0.05 * h;
+    let right = 0.2 + 0.3 * h - 0.05 * h;
+    check::<Stereo64>(&bytes, 6, &[left, right]);
+}
+
+#[test]
+fn mono_to_surround() {
+    let bytes = s16le(&[0.5]);
+    check::<Surround64>(&bytes, 1, &[0.0, 0.0, 0.5, 0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn stereo_to_mono() {
+    let bytes = s16le(&[0.4, -0.2]);
+    check::<Mono64>(&bytes, 2, &[(0.4 - 0.2) * FRAC_1_SQRT_2]);
+}
+
+#[test]
+fn many_channels() {
+    // More channels than any `Frame` type, each with its own level.
+    let levels: Vec<f64> = (0..32).map(|i| i as f64 / 32.0 - 0.5).collect();
+    let bytes = s16le(&levels);
+
+    let mut decoder =
+        Decoder::<_, Mono64, _>::with_channels(&bytes[..], pcm::S16Le, 32);
+    assert_eq!(decoder.channels(), 32);
+    for _ in 0..FRAMES {
+        let channels = decoder.next_channels().unwrap().unwrap();
+        assert_eq!(channels.len(), 32);
+        for (chan, level) in channels.iter().zip(&levels) {
+            assert!((chan.to_f64() - level).abs() < 1e-4);
+        }
+    }
+    assert!(decoder.next_channels().is_none());
+
+    // Without a known layout, channels are mapped by index.
+    check::<Stereo64>(&bytes, 32, &levels[..2]);
+}
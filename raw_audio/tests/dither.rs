@@ -0,0 +1,123 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use fon::chan::{Ch64, Channel};
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::{Audio, Frame};
+use raw_audio::pcm::{self, Pcm};
+use raw_audio::{Decoder, Dither, Encoder, Shaping};
+
+use common::signal;
+
+const FRAMES: usize = 10_000;
+
+// Size of one step of 8-bit PCM.
+const LSB: f64 = 1.0 / 128.0;
+
+fn encode<F: Frame, P: Pcm>(
+    pcm: P,
+    frames: &[F],
+    dither: Option<Dither>,
+) -> Vec<u8> {
+    let audio = Audio::with_frames(48_000, frames.to_vec());
+    let mut encoder = Encoder::new(Vec::new(), pcm);
+    if let Some(dither) = dither {
+        encoder = encoder.with_dither(dither);
+    }
+    encoder.encode(&audio).unwrap();
+    encoder.into_inner().unwrap()
+}
+
+// A slow ramp from -0.9 to 0.9.
+fn ramp() -> Vec<Mono64> {
+    (0..FRAMES)
+        .map(|i| {
+            let x = -0.9 + 1.8 * i as f64 / FRAMES as f64;
+            Mono64::new(Ch64::new(x))
+        })
+        .collect()
+}
+
+// Encode the ramp as S8 and get the error of each decoded sample.
+fn errors(dither: Option<Dither>) -> Vec<f64> {
+    let input = ramp();
+    let bytes = encode(pcm::S8, &input, dither);
+    let decoder =
+        Decoder::<_, Mono64, _>::with_channels(&bytes[..], pcm::S8, 1);
+    decoder
+        .zip(input)
+        .map(|(decoded, input)| {
+            let decoded = decoded.unwrap().channels()[0].to_f64();
+            decoded - input.channels()[0].to_f64()
+        })
+        .collect()
+}
+
+#[test]
+fn seed() {
+    let input = signal::<Stereo64>(FRAMES);
+    let bytes = encode(pcm::S16Le, &input, Some(Dither::new(1)));
+    assert_eq!(bytes, encode(pcm::S16Le, &input, Some(Dither::new(1))));
+    assert_ne!(bytes, encode(pcm::S16Le, &input, Some(Dither::new(2))));
+    assert_ne!(bytes, encode(pcm::S16Le, &input, None));
+}
+
+#[test]
+fn float() {
+    let input = signal::<Stereo64>(FRAMES);
+    let dither = Dither::new(1).with_shaping(Shaping::SecondOrder);
+    assert_eq!(
+        encode(pcm::F32Le, &input, Some(dither)),
+        encode(pcm::F32Le, &input, None)
+    );
+    assert_eq!(
+        encode(pcm::F16Be, &input, Some(dither)),
+        encode(pcm::F16Be, &input, None)
+    );
+}
+
+#[test]
+fn mean() {
+    for &shaping in &[Shaping::Off, Shaping::FirstOrder, Shaping::SecondOrder] {
+        let errors = errors(Some(Dither::new(7).with_shaping(shaping)));
+        let mean = errors.iter().sum::<f64>() / FRAMES as f64;
+        assert!(mean.abs() < 0.05 * LSB, "{:?}: {}", shaping, mean / LSB);
+        // TPDF dither adds at most one step of noise either way, and shaping
+        // adds back up to two steps of each earlier error.
+        let max = errors.iter().fold(0.0f64, |max, e| max.max(e.abs()));
+        let limit = match shaping {
+            Shaping::Off => 1.5,
+            Shaping::FirstOrder => 4.0,
+            Shaping::SecondOrder => 8.0,
+        };
+        assert!(max <= limit * LSB, "{:?}: {}", shaping, max / LSB);
+    }
+}
+
+#[test]
+fn shaping() {
+    // Feeding back the quantization error makes the errors cancel out, so
+    // their running sum stays within a few steps, unlike without shaping.
+    let drift = |errors: Vec<f64>| {
+        let mut sum = 0.0;
+        let mut max = 0.0f64;
+        for error in errors {
+            sum += error;
+            max = max.max(sum.abs());
+        }
+        max / LSB
+    };
+    let dither = Dither::new(7);
+    assert!(drift(errors(Some(dither))) > 20.0);
+    let first = drift(errors(Some(dither.with_shaping(Shaping::FirstOrder))));
+    assert!(first <= 2.0, "{}", first);
+    let second = drift(errors(Some(dither.with_shaping(Shaping::SecondOrder))));
+    assert!(second <= 4.0, "{}", second);
+}
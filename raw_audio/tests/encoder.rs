@@ -0,0 +1,115 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use fon::chan::Ch64;
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::Audio;
+use raw_audio::pcm;
+use raw_audio::{Encoder, PlanarEncoder};
+
+use common::signal;
+
+#[test]
+fn chunks() {
+    let input = signal::<Stereo64>(1000);
+    let audio = Audio::with_frames(48_000, input.clone());
+    let mut encoder = Encoder::new(Vec::new(), pcm::ImaAdpcmLe);
+    encoder.encode(&audio).unwrap();
+    let expected = encoder.into_inner().unwrap();
+
+    // An endless stream, encoded a chunk at a time.
+    let mut stream = input.iter().cloned().chain(std::iter::repeat(input[0]));
+    let mut encoder = Encoder::new(Vec::new(), pcm::ImaAdpcmLe);
+    for _ in 0..7 {
+        assert_eq!(encoder.encode_frames(&mut stream, 130).unwrap(), 130);
+    }
+    assert_eq!(encoder.encode_frames(&mut stream, 90).unwrap(), 90);
+    assert_eq!(encoder.into_inner().unwrap(), expected);
+
+    // The end of a finite stream.
+    let mut frames = input.iter().cloned();
+    let mut encoder = Encoder::new(Vec::new(), pcm::ImaAdpcmLe);
+    assert_eq!(encoder.encode_frames(&mut frames, 600).unwrap(), 600);
+    assert_eq!(encoder.encode_frames(&mut frames, 600).unwrap(), 400);
+    assert_eq!(encoder.encode_frames(&mut frames, 600).unwrap(), 0);
+    assert_eq!(encoder.into_inner().unwrap(), expected);
+}
+
+#[test]
+fn planar_chunks() {
+    let input = signal::<Stereo64>(1000);
+    let audio = Audio::with_frames(48_000, input.clone());
+    let writers = vec![Vec::new(), Vec::new()];
+    let mut encoder = PlanarEncoder::new(writers, pcm::S12Be);
+    encoder.encode(&audio).unwrap();
+    let expected = encoder.into_inner().unwrap();
+
+    let mut stream = input.iter().cloned().chain(std::iter::repeat(input[0]));
+    let writers = vec![Vec::new(), Vec::new()];
+    let mut encoder = PlanarEncoder::new(writers, pcm::S12Be);
+    for _ in 0..10 {
+        assert_eq!(encoder.encode_frames(&mut stream, 99).unwrap(), 99);
+    }
+    assert_eq!(encoder.encode_frames(&mut stream, 10).unwrap(), 10);
+    assert_eq!(encoder.into_inner().unwrap(), expected);
+}
+
+#[test]
+fn flush() {
+    // S20Le packs two samples into each group of 5 bytes.
+    let input = signal::<Mono64>(3);
+    let mut output = Vec::new();
+    let mut encoder = Encoder::new(&mut output, pcm::S20Le);
+    encoder
+        .encode_frames(&mut input.iter().cloned(), 3)
+        .unwrap();
+    encoder.flush().unwrap();
+    drop(encoder);
+    // The third sample is waiting for its group to fill.
+    let mut encoder = Encoder::new(Vec::new(), pcm::S20Le);
+    encoder
+        .encode_frames(&mut input.iter().cloned(), 2)
+        .unwrap();
+    assert_eq!(output, encoder.into_inner().unwrap());
+    assert_eq!(output.len(), 5);
+}
+
+#[test]
+fn into_inner() {
+    let input = signal::<Mono64>(3);
+    let mut encoder = Encoder::new(Vec::new(), pcm::S20Le);
+    encoder
+        .encode_frames(&mut input.iter().cloned(), 3)
+        .unwrap();
+    let output = encoder.into_inner().unwrap();
+
+    // The last group is padded with silence.
+    let mut padded = input;
+    padded.push(Mono64::new(Ch64::new(0.0)));
+    let mut encoder = Encoder::new(Vec::new(), pcm::S20Le);
+    encoder.encode_frames(&mut padded.into_iter(), 4).unwrap();
+    assert_eq!(output, encoder.into_inner().unwrap());
+    assert_eq!(output.len(), 10);
+}
+
+#[test]
+fn unflushed() {
+    // Nothing reaches the writer until a block is full or the encoder is
+    // flushed, so dropping the encoder loses the rest.
+    let input = signal::<Stereo64>(100);
+    let mut output = Vec::new();
+    let mut encoder = Encoder::new(&mut output, pcm::S16Le);
+    assert_eq!(
+        encoder.encode_frames(&mut input.into_iter(), 100).unwrap(),
+        100
+    );
+    drop(encoder);
+    assert!(output.is_empty());
+}
@@ -0,0 +1,177 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::io::{self, Cursor, ErrorKind, Read};
+
+use fon::chan::{Ch64, Channel};
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::surround::Surround64;
+use fon::{Audio, Frame};
+use raw_audio::pcm::{self, Pcm};
+use raw_audio::{Decoder, Encoder, PlanarDecoder, PlanarEncoder};
+
+use common::signal;
+
+const FRAMES: usize = 1001;
+
+// Encode frames with one writer for each channel.
+fn encode<F: Frame, P: Pcm>(pcm: P, frames: &[F]) -> Vec<Vec<u8>> {
+    let audio = Audio::with_frames(48_000, frames.to_vec());
+    let writers = vec![Vec::new(); F::CHAN_COUNT];
+    let mut encoder = PlanarEncoder::<_, F, _>::new(writers, pcm);
+    assert_eq!(encoder.encode(&audio).unwrap(), frames.len());
+    encoder.into_inner().unwrap()
+}
+
+// Check each channel's plane holds the same bytes as encoding that channel on
+// its own, and that the planes decode like interleaved audio.
+fn check<F: Frame, P: Pcm>(pcm: impl Fn() -> P) {
+    let input = signal::<F>(FRAMES);
+    let planes = encode(pcm(), &input);
+    assert_eq!(planes.len(), F::CHAN_COUNT);
+    for (c, plane) in planes.iter().enumerate() {
+        let mono: Vec<Mono64> = input
+            .iter()
+            .map(|frame| Mono64::new(Ch64::new(frame.channels()[c].to_f64())))
+            .collect();
+        let mut encoder = Encoder::new(Vec::new(), pcm());
+        encoder.encode(&Audio::with_frames(48_000, mono)).unwrap();
+        assert_eq!(*plane, encoder.into_inner().unwrap(), "channel {}", c);
+    }
+
+    let readers = planes.iter().map(|plane| &plane[..]).collect();
+    let decoded: Vec<F> = PlanarDecoder::<_, F, _>::new(readers, pcm())
+        .map(Result::unwrap)
+        .collect();
+    let audio = Audio::with_frames(48_000, input);
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    encoder.encode(&audio).unwrap();
+    let interleaved = encoder.into_inner().unwrap();
+    let decoder = Decoder::<_, F, _>::with_channels(
+        &interleaved[..],
+        pcm(),
+        F::CHAN_COUNT,
+    );
+    let expected: Vec<F> = decoder.map(Result::unwrap).collect();
+    assert!(decoded.len() >= FRAMES);
+    assert_eq!(decoded[..FRAMES], expected[..FRAMES]);
+}
+
+#[test]
+fn separate() {
+    fn frames<P: Pcm>(pcm: impl Fn() -> P) {
+        check::<Mono64, P>(&pcm);
+        check::<Stereo64, P>(&pcm);
+        check::<Surround64, P>(&pcm);
+    }
+    frames(|| pcm::S16Le);
+    frames(|| pcm::U24Be);
+    frames(|| pcm::S12Be);
+    frames(|| pcm::U4Le);
+    frames(|| pcm::F32Be);
+    frames(|| pcm::ImaAdpcmLe);
+    frames(|| pcm::G726_32Be);
+}
+
+#[test]
+fn single_file() {
+    let input = signal::<Stereo64>(FRAMES);
+    let planes = encode(pcm::S20Le, &input);
+
+    // After a "header", the left plane then the right plane.
+    let mut file = Cursor::new(vec![0xAA; 5]);
+    file.set_position(5);
+    let audio = Audio::with_frames(48_000, input);
+    let mut encoder = PlanarEncoder::<_, Stereo64, _>::with_frames(
+        &mut file,
+        pcm::S20Le,
+        1001,
+    )
+    .unwrap();
+    encoder.encode(&audio).unwrap();
+    encoder.into_inner().unwrap();
+    assert_eq!(file.get_ref()[..5], [0xAA; 5]);
+    assert_eq!(
+        file.get_ref()[5..],
+        [&planes[0][..], &planes[1]].concat()[..]
+    );
+
+    let readers = planes.iter().map(|plane| &plane[..]).collect();
+    let expected: Vec<Stereo64> =
+        PlanarDecoder::<_, Stereo64, _>::new(readers, pcm::S20Le)
+            .map(Result::unwrap)
+            .collect();
+    file.set_position(5);
+    let decoded: Vec<Stereo64> =
+        PlanarDecoder::<_, Stereo64, _>::with_frames(file, pcm::S20Le, 2, 1001)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+    assert_eq!(decoded, expected[..FRAMES]);
+}
+
+// A reader that fails once after `fail_at` bytes.
+struct Flaky<'a> {
+    bytes: &'a [u8],
+    fail_at: usize,
+}
+
+impl Read for Flaky<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.fail_at == 0 {
+            self.fail_at = usize::MAX;
+            return Err(io::Error::other("flaky"));
+        }
+        let len = buf.len().min(self.bytes.len()).min(self.fail_at);
+        buf[..len].copy_from_slice(&self.bytes[..len]);
+        self.bytes = &self.bytes[len..];
+        self.fail_at -= len;
+        Ok(len)
+    }
+}
+
+#[test]
+fn retry() {
+    let input = signal::<Stereo64>(5000);
+    let planes = encode(pcm::G726_40Le, &input);
+    let readers = planes.iter().map(|plane| &plane[..]).collect();
+    let expected: Vec<Stereo64> =
+        PlanarDecoder::<_, Stereo64, _>::new(readers, pcm::G726_40Le)
+            .map(Result::unwrap)
+            .collect();
+
+    // The right channel fails part way through a group of samples, after the
+    // left channel has decoded its next group.
+    let readers = vec![
+        Flaky {
+            bytes: &planes[0],
+            fail_at: usize::MAX,
+        },
+        Flaky {
+            bytes: &planes[1],
+            fail_at: 1502,
+        },
+    ];
+    let mut decoder =
+        PlanarDecoder::<_, Stereo64, _>::new(readers, pcm::G726_40Le);
+    let mut decoded = Vec::new();
+    let mut errors = 0;
+    for frame in &mut decoder {
+        match frame {
+            Ok(frame) => decoded.push(frame),
+            Err(e) => {
+                assert_eq!(e.kind(), ErrorKind::Other);
+                errors += 1;
+            }
+        }
+    }
+    assert_eq!(errors, 1);
+    assert_eq!(decoded, expected);
+}
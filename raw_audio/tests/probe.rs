@@ -0,0 +1,77 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use fon::mono::Mono64;
+use fon::stereo::Stereo64;
+use fon::surround::Surround64;
+use fon::{Audio, Frame};
+use raw_audio::pcm::{self, Pcm};
+use raw_audio::{probe, Encoder, Format};
+
+use common::signal;
+
+// Check the top guess for a signal encoded in a format.
+fn check<F: Frame, P: Pcm>(pcm: P, format: Format) {
+    let audio = Audio::with_frames(48_000, signal::<F>(1024));
+    let mut encoder = Encoder::new(Vec::new(), pcm);
+    encoder.encode(&audio).unwrap();
+    let bytes = encoder.into_inner().unwrap();
+    let guesses = probe(&bytes);
+    let best = guesses[0];
+    assert_eq!(
+        (best.format, best.channels),
+        (format, F::CHAN_COUNT),
+        "{:?}",
+        &guesses[..4]
+    );
+    assert!(best.score > 0.5, "{:?}", best);
+    assert!(guesses
+        .windows(2)
+        .all(|pair| pair[0].score >= pair[1].score));
+}
+
+fn formats<F: Frame>() {
+    check::<F, _>(pcm::U8, Format::U8);
+    check::<F, _>(pcm::S8, Format::S8);
+    check::<F, _>(pcm::MuLaw, Format::MuLaw);
+    check::<F, _>(pcm::ALaw, Format::ALaw);
+    check::<F, _>(pcm::U16Le, Format::U16Le);
+    check::<F, _>(pcm::U16Be, Format::U16Be);
+    check::<F, _>(pcm::S16Le, Format::S16Le);
+    check::<F, _>(pcm::S16Be, Format::S16Be);
+    check::<F, _>(pcm::S24Le, Format::S24Le);
+    check::<F, _>(pcm::S24Be, Format::S24Be);
+    check::<F, _>(pcm::S32Le, Format::S32Le);
+    check::<F, _>(pcm::S32Be, Format::S32Be);
+    check::<F, _>(pcm::F32Le, Format::F32Le);
+    check::<F, _>(pcm::F32Be, Format::F32Be);
+    check::<F, _>(pcm::F64Le, Format::F64Le);
+    check::<F, _>(pcm::F64Be, Format::F64Be);
+}
+
+#[test]
+fn mono() {
+    formats::<Mono64>();
+}
+
+#[test]
+fn stereo() {
+    formats::<Stereo64>();
+}
+
+#[test]
+fn surround() {
+    formats::<Surround64>();
+}
+
+#[test]
+fn too_short() {
+    let guesses = probe(&[0x12, 0x34, 0x56]);
+    assert!(guesses.iter().all(|guess| guess.score == 0.0));
+}
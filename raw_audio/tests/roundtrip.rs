@@ -0,0 +1,558 @@
+// RAW Audio
+// Copyright © 2021 Jeron Aldaron Lau.
+//
+// Licensed under the Boost Software License, Version 1.0
+// (https://www.boost.org/LICENSE_1_0.txt or see accompanying file
+// LICENSE_BOOST_1_0.txt)
+
+mod common;
+
+use std::f64::consts::TAU;
+use std::io::Cursor;
+
+use fon::chan::{Ch16, Ch64, Channel};
+use fon::mono::{Mono16, Mono64};
+use fon::stereo::Stereo64;
+use fon::surround::Surround64;
+use fon::{Audio, Frame};
+use raw_audio::pcm::{self, Pcm};
+use raw_audio::{
+    decode_into, encode_into, BitOrder, Context, Decoder, DsdDecoder, Encoder,
+    Layout, DSD64,
+};
+
+use common::signal;
+
+/// Number of frames round-tripped (odd, so packed groups get padded).
+const FRAMES: usize = 1001;
+
+// Encode frames, then decode them again.
+fn roundtrip<F: Frame, P: Pcm>(pcm: impl Fn() -> P, frames: &[F]) -> Vec<F> {
+    let audio = Audio::with_frames(48_000, frames.to_vec());
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    assert_eq!(encoder.encode(&audio).unwrap(), frames.len());
+    let bytes = encoder.into_inner().unwrap();
+    let decoder =
+        Decoder::<_, F, _>::with_channels(&bytes[..], pcm(), F::CHAN_COUNT);
+    let decoded: Vec<F> = decoder.map(Result::unwrap).collect();
+    // Padding of the last packed group may decode as extra frames.
+    assert!(decoded.len() >= frames.len(), "{}", decoded.len());
+    assert!(decoded.len() < frames.len() + 8, "{}", decoded.len());
+    decoded
+}
+
+// Pair up input and output samples of every channel, skipping `skip` frames.
+fn pairs<F: Frame>(input: &[F], output: &[F], skip: usize) -> Vec<(f64, f64)> {
+    input
+        .iter()
+        .zip(output)
+        .skip(skip)
+        .flat_map(|(a, b)| {
+            let a = a.channels().iter().map(|c| c.to_f64());
+            let b = b.channels().iter().map(|c| c.to_f64());
+            a.zip(b).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// Check every sample comes back within `tolerance`.
+fn check<P: Pcm>(pcm: impl Fn() -> P, tolerance: f64) {
+    fn frames<F: Frame, P: Pcm>(pcm: &impl Fn() -> P, tolerance: f64) {
+        let input = signal::<F>(FRAMES);
+        let output = roundtrip(pcm, &input);
+        for (i, (a, b)) in pairs(&input, &output, 0).into_iter().enumerate() {
+            assert!(
+                (a - b).abs() <= tolerance,
+                "{} ({} channels) sample {}: {} became {}",
+                std::any::type_name::<P>(),
+                F::CHAN_COUNT,
+                i,
+                a,
+                b,
+            );
+        }
+    }
+    frames::<Mono64, P>(&pcm, tolerance);
+    frames::<Stereo64, P>(&pcm, tolerance);
+    frames::<Surround64, P>(&pcm, tolerance);
+}
+
+// Check the signal to noise ratio is at least `snr` dB once the adaptive
+// codec has settled.
+fn check_adpcm<P: Pcm>(pcm: impl Fn() -> P, snr: f64) {
+    fn frames<F: Frame, P: Pcm>(pcm: &impl Fn() -> P, snr: f64) {
+        let input = signal::<F>(FRAMES);
+        let output = roundtrip(pcm, &input);
+        let pairs = pairs(&input, &output, 200);
+        let signal: f64 = pairs.iter().map(|(a, _)| a * a).sum();
+        let noise: f64 = pairs.iter().map(|(a, b)| (a - b) * (a - b)).sum();
+        let measured = 10.0 * (signal / noise).log10();
+        assert!(
+            measured >= snr,
+            "{} ({} channels): {} dB",
+            std::any::type_name::<P>(),
+            F::CHAN_COUNT,
+            measured,
+        );
+    }
+    frames::<Mono64, P>(&pcm, snr);
+    frames::<Stereo64, P>(&pcm, snr);
+    frames::<Surround64, P>(&pcm, snr);
+}
+
+// One step of a `bits`-bit integer format.
+fn step(bits: u32) -> f64 {
+    1.0 / (1u64 << (bits - 1)) as f64
+}
+
+#[test]
+fn integer() {
+    check(|| pcm::U8, step(8));
+    check(|| pcm::S8, step(8));
+    check(|| pcm::U16Le, step(16));
+    check(|| pcm::U16Be, step(16));
+    check(|| pcm::S16Le, step(16));
+    check(|| pcm::S16Be, step(16));
+    check(|| pcm::U24Le, step(24));
+    check(|| pcm::U24Be, step(24));
+    check(|| pcm::S24Le, step(24));
+    check(|| pcm::S24Be, step(24));
+    check(|| pcm::U32Le, step(32));
+    check(|| pcm::U32Be, step(32));
+    check(|| pcm::S32Le, step(32));
+    check(|| pcm::S32Be, step(32));
+    check(|| pcm::S24_32Le, step(24));
+    check(|| pcm::S24_32Be, step(24));
+    check(|| pcm::S24_32RLe, step(24));
+    check(|| pcm::S24_32RBe, step(24));
+}
+
+#[test]
+fn packed() {
+    check(|| pcm::U20Le, step(20));
+    check(|| pcm::U20Be, step(20));
+    check(|| pcm::S20Le, step(20));
+    check(|| pcm::S20Be, step(20));
+    check(|| pcm::U12Le, step(12));
+    check(|| pcm::U12Be, step(12));
+    check(|| pcm::S12Le, step(12));
+    check(|| pcm::S12Be, step(12));
+    check(|| pcm::U4Le, step(4));
+    check(|| pcm::U4Be, step(4));
+    check(|| pcm::S4Le, step(4));
+    check(|| pcm::S4Be, step(4));
+}
+
+#[test]
+fn companded() {
+    // Half of the largest step (1024 of 16-bit), plus rounding.
+    check(|| pcm::MuLaw, 1.0 / 32.0);
+    check(|| pcm::ALaw, 1.0 / 32.0);
+}
+
+#[test]
+fn float() {
+    check(|| pcm::F16Le, 1.0 / 1024.0);
+    check(|| pcm::F16Be, 1.0 / 1024.0);
+    check(|| pcm::BF16Le, 1.0 / 128.0);
+    check(|| pcm::BF16Be, 1.0 / 128.0);
+    check(|| pcm::F32Le, f64::from(f32::EPSILON));
+    check(|| pcm::F32Be, f64::from(f32::EPSILON));
+    check(|| pcm::F64Le, 0.0);
+    check(|| pcm::F64Be, 0.0);
+}
+
+#[test]
+fn adpcm() {
+    check_adpcm(|| pcm::ImaAdpcmLe, 20.0);
+    check_adpcm(|| pcm::ImaAdpcmBe, 20.0);
+    check_adpcm(|| pcm::G726_16Le, 15.0);
+    check_adpcm(|| pcm::G726_16Be, 15.0);
+    check_adpcm(|| pcm::G726_24Le, 22.0);
+    check_adpcm(|| pcm::G726_24Be, 22.0);
+    check_adpcm(|| pcm::G726_32Le, 28.0);
+    check_adpcm(|| pcm::G726_32Be, 28.0);
+    check_adpcm(|| pcm::G726_40Le, 34.0);
+    check_adpcm(|| pcm::G726_40Be, 34.0);
+}
+
+// G.711 µ-law expansion to 16 bits, written from the recommendation.
+fn mulaw_reference(code: u8) -> i16 {
+    let code = !code;
+    let exponent = (code >> 4) & 0x7;
+    let mantissa = i32::from(code & 0xF);
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+    let magnitude = magnitude as i16;
+    if code & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+// G.711 A-law expansion to 16 bits, written from the recommendation.
+fn alaw_reference(code: u8) -> i16 {
+    let code = code ^ 0x55;
+    let exponent = (code >> 4) & 0x7;
+    let mantissa = i32::from(code & 0xF);
+    let magnitude = match exponent {
+        0 => (mantissa << 4) + 8,
+        e => ((mantissa << 4) + 0x108) << (e - 1),
+    };
+    let magnitude = magnitude as i16;
+    if code & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+// Decode every code, compare it to the reference, then encode it again.
+fn check_g711<P: Pcm>(pcm: impl Fn() -> P, reference: fn(u8) -> i16) {
+    let codes: Vec<u8> = (0..=255).collect();
+    let decoder = Decoder::<_, Mono16, _>::with_channels(&codes[..], pcm(), 1);
+    let decoded: Vec<Mono16> = decoder.map(Result::unwrap).collect();
+    assert_eq!(decoded.len(), 256);
+    for (code, frame) in codes.iter().zip(decoded.iter()) {
+        let sample: i16 = frame.channels()[0].into();
+        assert_eq!(sample, reference(*code), "code {:02X}", code);
+    }
+    let audio = Audio::with_frames(8_000, decoded);
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    encoder.encode(&audio).unwrap();
+    let encoded = encoder.into_inner().unwrap();
+    for (code, byte) in codes.iter().zip(encoded.iter()) {
+        // µ-law has a negative zero, which encodes as positive zero.
+        let expected = if reference(*code) == 0 { 0xFF } else { *code };
+        assert_eq!(*byte, expected, "code {:02X}", code);
+    }
+}
+
+#[test]
+fn mulaw_table() {
+    check_g711(|| pcm::MuLaw, mulaw_reference);
+}
+
+#[test]
+fn alaw_table() {
+    check_g711(|| pcm::ALaw, alaw_reference);
+}
+
+// Decode `bytes`, which hold the group `expected` (-1, 0 or 1) with `bits`
+// bits per sample, then check they encode back to the same bytes.
+fn boundary<P: Pcm>(
+    pcm: impl Fn() -> P,
+    bits: u32,
+    bytes: &[u8],
+    expected: &[f64],
+) {
+    let name = std::any::type_name::<P>();
+    let decoder = Decoder::<_, Mono64, _>::with_channels(bytes, pcm(), 1);
+    let decoded: Vec<Mono64> = decoder.map(Result::unwrap).collect();
+    assert_eq!(decoded.len(), expected.len(), "{}", name);
+    for (frame, expected) in decoded.iter().zip(expected) {
+        let sample = frame.channels()[0].to_f64();
+        assert!(
+            (sample - expected).abs() <= step(bits),
+            "{}: {:02X?} decoded to {}",
+            name,
+            bytes,
+            sample,
+        );
+    }
+    let audio = Audio::with_frames(48_000, decoded);
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    encoder.encode(&audio).unwrap();
+    let encoded = encoder.into_inner().unwrap();
+    assert_eq!(encoded, bytes, "{}", name);
+    // Full scale encodes as the extremes.
+    if expected.iter().all(|x| *x != 0.0) {
+        let frames = expected.iter().map(|x| Mono64::new(Ch64::new(*x)));
+        let audio = Audio::with_frames(48_000, frames.collect::<Vec<_>>());
+        let mut encoder = Encoder::new(Vec::new(), pcm());
+        encoder.encode(&audio).unwrap();
+        assert_eq!(encoder.into_inner().unwrap(), bytes, "{}", name);
+    }
+}
+
+// Check the minimum, zero and maximum of a one sample per group format.
+fn extremes<P: Pcm>(
+    pcm: impl Fn() -> P,
+    bits: u32,
+    min: &[u8],
+    zero: &[u8],
+    max: &[u8],
+) {
+    boundary(&pcm, bits, min, &[-1.0]);
+    boundary(&pcm, bits, zero, &[0.0]);
+    boundary(&pcm, bits, max, &[1.0]);
+}
+
+#[test]
+fn boundaries() {
+    extremes(|| pcm::U8, 8, &[0x00], &[0x80], &[0xFF]);
+    extremes(|| pcm::S8, 8, &[0x80], &[0x00], &[0x7F]);
+
+    extremes(|| pcm::U16Le, 16, &[0, 0], &[0x00, 0x80], &[0xFF, 0xFF]);
+    extremes(|| pcm::U16Be, 16, &[0, 0], &[0x80, 0x00], &[0xFF, 0xFF]);
+    extremes(|| pcm::S16Le, 16, &[0x00, 0x80], &[0, 0], &[0xFF, 0x7F]);
+    extremes(|| pcm::S16Be, 16, &[0x80, 0x00], &[0, 0], &[0x7F, 0xFF]);
+
+    extremes(|| pcm::U24Le, 24, &[0; 3], &[0, 0, 0x80], &[0xFF; 3]);
+    extremes(|| pcm::U24Be, 24, &[0; 3], &[0x80, 0, 0], &[0xFF; 3]);
+    extremes(
+        || pcm::S24Le,
+        24,
+        &[0, 0, 0x80],
+        &[0; 3],
+        &[0xFF, 0xFF, 0x7F],
+    );
+    extremes(
+        || pcm::S24Be,
+        24,
+        &[0x80, 0, 0],
+        &[0; 3],
+        &[0x7F, 0xFF, 0xFF],
+    );
+
+    extremes(|| pcm::U32Le, 32, &[0; 4], &[0, 0, 0, 0x80], &[0xFF; 4]);
+    extremes(|| pcm::U32Be, 32, &[0; 4], &[0x80, 0, 0, 0], &[0xFF; 4]);
+    let (min, max) = ([0, 0, 0, 0x80], [0xFF, 0xFF, 0xFF, 0x7F]);
+    extremes(|| pcm::S32Le, 32, &min, &[0; 4], &max);
+    let (min, max) = ([0x80, 0, 0, 0], [0x7F, 0xFF, 0xFF, 0xFF]);
+    extremes(|| pcm::S32Be, 32, &min, &[0; 4], &max);
+
+    let (min, max) = ([0, 0, 0, 0x80], [0, 0xFF, 0xFF, 0x7F]);
+    extremes(|| pcm::S24_32Le, 24, &min, &[0; 4], &max);
+    let (min, max) = ([0x80, 0, 0, 0], [0x7F, 0xFF, 0xFF, 0]);
+    extremes(|| pcm::S24_32Be, 24, &min, &[0; 4], &max);
+    // The padding byte sign extends.
+    let (min, max) = ([0, 0, 0x80, 0xFF], [0xFF, 0xFF, 0x7F, 0]);
+    extremes(|| pcm::S24_32RLe, 24, &min, &[0; 4], &max);
+    let (min, max) = ([0xFF, 0x80, 0, 0], [0, 0x7F, 0xFF, 0xFF]);
+    extremes(|| pcm::S24_32RBe, 24, &min, &[0; 4], &max);
+}
+
+#[test]
+fn packed_boundaries() {
+    let (max_min, zeros) = (&[1.0, -1.0], &[0.0, 0.0]);
+
+    boundary(|| pcm::U20Le, 20, &[0xFF, 0xFF, 0x0F, 0, 0], max_min);
+    boundary(|| pcm::U20Le, 20, &[0, 0, 0x08, 0, 0x80], zeros);
+    boundary(|| pcm::U20Be, 20, &[0xFF, 0xFF, 0xF0, 0, 0], max_min);
+    boundary(|| pcm::U20Be, 20, &[0x80, 0, 0x08, 0, 0], zeros);
+    boundary(|| pcm::S20Le, 20, &[0xFF, 0xFF, 0x07, 0, 0x80], max_min);
+    boundary(|| pcm::S20Le, 20, &[0; 5], zeros);
+    boundary(|| pcm::S20Be, 20, &[0x7F, 0xFF, 0xF8, 0, 0], max_min);
+    boundary(|| pcm::S20Be, 20, &[0; 5], zeros);
+
+    boundary(|| pcm::U12Le, 12, &[0xFF, 0x0F, 0], max_min);
+    boundary(|| pcm::U12Le, 12, &[0, 0x08, 0x80], zeros);
+    boundary(|| pcm::U12Be, 12, &[0xFF, 0xF0, 0], max_min);
+    boundary(|| pcm::U12Be, 12, &[0x80, 0x08, 0], zeros);
+    boundary(|| pcm::S12Le, 12, &[0xFF, 0x07, 0x80], max_min);
+    boundary(|| pcm::S12Le, 12, &[0; 3], zeros);
+    boundary(|| pcm::S12Be, 12, &[0x7F, 0xF8, 0], max_min);
+    boundary(|| pcm::S12Be, 12, &[0; 3], zeros);
+
+    boundary(|| pcm::U4Le, 4, &[0x0F], max_min);
+    boundary(|| pcm::U4Le, 4, &[0x88], zeros);
+    boundary(|| pcm::U4Be, 4, &[0xF0], max_min);
+    boundary(|| pcm::U4Be, 4, &[0x88], zeros);
+    boundary(|| pcm::S4Le, 4, &[0x87], max_min);
+    boundary(|| pcm::S4Le, 4, &[0x00], zeros);
+    boundary(|| pcm::S4Be, 4, &[0x78], max_min);
+    boundary(|| pcm::S4Be, 4, &[0x00], zeros);
+}
+
+#[test]
+fn sixteen_bit_exact() {
+    // 16-bit samples survive every 16-bit or wider integer format unchanged.
+    let frames: Vec<Mono16> = (i16::MIN..=i16::MAX)
+        .step_by(7)
+        .chain([i16::MIN, -1, 0, 1, i16::MAX].iter().cloned())
+        .map(|x| Mono16::new(Ch16::new(x)))
+        .collect();
+    fn exact<P: Pcm>(pcm: impl Fn() -> P, frames: &[Mono16]) {
+        let output = roundtrip(pcm, frames);
+        assert_eq!(&output[..frames.len()], frames);
+    }
+    exact(|| pcm::S16Le, &frames);
+    exact(|| pcm::U16Be, &frames);
+    exact(|| pcm::S24Le, &frames);
+    exact(|| pcm::U24Be, &frames);
+    exact(|| pcm::S32Le, &frames);
+    exact(|| pcm::U32Be, &frames);
+    exact(|| pcm::S24_32Le, &frames);
+    exact(|| pcm::S24_32RBe, &frames);
+    exact(|| pcm::F32Le, &frames);
+    exact(|| pcm::F64Be, &frames);
+}
+
+// Check slice conversion matches the streaming encoder and decoder.
+fn check_slices<F: Frame, P: Pcm>(pcm: impl Fn() -> P) {
+    let input = signal::<F>(FRAMES);
+    let audio = Audio::with_frames(48_000, input.clone());
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    encoder.encode(&audio).unwrap();
+    let expected = encoder.into_inner().unwrap();
+    let mut bytes = vec![0; expected.len()];
+    let encoded = encode_into(&mut Context::new(pcm()), &input, &mut bytes);
+    assert!(input.len() - encoded.frames < 8);
+    assert_eq!(&bytes[..encoded.bytes], &expected[..encoded.bytes]);
+
+    let mut frames = vec![F::default(); input.len()];
+    let decoded = decode_into(&mut Context::new(pcm()), &expected, &mut frames);
+    assert_eq!(decoded, encoded);
+    let decoder =
+        Decoder::<_, F, _>::with_channels(&expected[..], pcm(), F::CHAN_COUNT);
+    for (a, b) in frames[..decoded.frames].iter().zip(decoder) {
+        assert_eq!(*a, b.unwrap());
+    }
+}
+
+#[test]
+fn slices() {
+    fn frames<P: Pcm>(pcm: impl Fn() -> P) {
+        check_slices::<Mono64, P>(&pcm);
+        check_slices::<Stereo64, P>(&pcm);
+        check_slices::<Surround64, P>(&pcm);
+    }
+    frames(|| pcm::MuLaw);
+    frames(|| pcm::S16Le);
+    frames(|| pcm::U24Be);
+    frames(|| pcm::S20Be);
+    frames(|| pcm::U4Le);
+    frames(|| pcm::F16Be);
+    frames(|| pcm::ImaAdpcmLe);
+    frames(|| pcm::G726_24Be);
+    frames(|| pcm::F64Le);
+}
+
+// Check seeking gives the same frames as decoding from the start.
+fn check_seek<F: Frame, P: Pcm>(pcm: impl Fn() -> P) {
+    let input = signal::<F>(FRAMES);
+    let audio = Audio::with_frames(48_000, input.clone());
+    let mut encoder = Encoder::new(Vec::new(), pcm());
+    encoder.encode(&audio).unwrap();
+    // Seeking is relative to where the reader starts, after a "header".
+    let mut bytes = vec![0xAA; 7];
+    bytes.extend(encoder.into_inner().unwrap());
+    let expected: Vec<F> =
+        Decoder::<_, F, _>::with_channels(&bytes[7..], pcm(), F::CHAN_COUNT)
+            .map(Result::unwrap)
+            .collect();
+    let mut reader = Cursor::new(&bytes);
+    reader.set_position(7);
+    let mut decoder =
+        Decoder::<_, F, _>::with_channels(reader, pcm(), F::CHAN_COUNT);
+    assert_eq!(decoder.frame_count().unwrap(), expected.len() as u64);
+    for frame in [500, 3, 0, 999, 1000, 77, 501].iter().cloned() {
+        decoder.seek(frame).unwrap();
+        assert_eq!(decoder.position(), frame);
+        let decoded = decoder.next().unwrap().unwrap();
+        assert_eq!(decoded, expected[frame as usize]);
+        assert_eq!(decoder.position(), frame + 1);
+    }
+    decoder.seek(expected.len() as u64 + 10).unwrap();
+    assert!(decoder.next().is_none());
+    assert_eq!(decoder.frame_count().unwrap(), expected.len() as u64);
+}
+
+#[test]
+fn seek() {
+    fn frames<P: Pcm>(pcm: impl Fn() -> P) {
+        check_seek::<Mono64, P>(&pcm);
+        check_seek::<Stereo64, P>(&pcm);
+        check_seek::<Surround64, P>(&pcm);
+    }
+    frames(|| pcm::S16Le);
+    frames(|| pcm::U24Be);
+    frames(|| pcm::S12Le);
+    frames(|| pcm::S4Be);
+    frames(|| pcm::ImaAdpcmLe);
+    frames(|| pcm::G726_40Le);
+}
+
+// Modulate a sine wave of each channel into DSD64 (second order delta-sigma),
+// most significant bit first, one byte of each channel in turn.
+fn dsd(channels: usize, bytes: usize) -> Vec<u8> {
+    let mut state = vec![[0.0f64; 2]; channels];
+    let mut out = vec![0u8; bytes * channels];
+    for i in 0..bytes * 8 {
+        let t = i as f64 / f64::from(DSD64);
+        for (c, [a, b]) in state.iter_mut().enumerate() {
+            let x = 0.25 * (TAU * 1000.0 * (c + 1) as f64 * t).sin();
+            let y = if *b >= 0.0 { 1.0 } else { -1.0 };
+            *a += x - y;
+            *b += *a - y;
+            if y > 0.0 {
+                out[i / 8 * channels + c] |= 0x80 >> (i % 8);
+            }
+        }
+    }
+    out
+}
+
+// Check one second of decoded DSD against the modulated sine waves.
+fn check_dsd<F: Frame>(decoder: DsdDecoder<&[u8], F>, hz: u32) {
+    let frames = hz as usize;
+    let decoded: Vec<F> = decoder.map(Result::unwrap).collect();
+    assert_eq!(decoded.len(), frames);
+    let (mut signal, mut noise) = (0.0, 0.0);
+    // Skip the edges, where the modulator and filter settle.
+    for (i, frame) in decoded.iter().enumerate().take(frames - 64).skip(64) {
+        for (c, chan) in frame.channels().iter().enumerate() {
+            let t = i as f64 / f64::from(hz);
+            let x = 0.25 * (TAU * 1000.0 * (c + 1) as f64 * t).sin();
+            signal += x * x;
+            noise += (chan.to_f64() - x) * (chan.to_f64() - x);
+        }
+    }
+    let snr = 10.0 * (signal / noise).log10();
+    assert!(snr > 40.0, "{} dB", snr);
+}
+
+#[test]
+fn dsd_to_pcm() {
+    // One second of stereo DSD64, 44100 bytes of each channel.
+    let bytes = dsd(2, 352_800);
+    let decoder = DsdDecoder::<_, Stereo64>::new(&bytes[..], DSD64, 2, 44_100);
+    check_dsd(decoder, 44_100);
+    let decoder = DsdDecoder::<_, Stereo64>::new(&bytes[..], DSD64, 2, 88_200);
+    check_dsd(decoder, 88_200);
+
+    // Least significant bit first, in blocks of 4096 bytes (like DSF).
+    let mut blocks = Vec::new();
+    for chunk in bytes.chunks(4096 * 2) {
+        for c in 0..2 {
+            let mut block: Vec<u8> = chunk
+                .iter()
+                .skip(c)
+                .step_by(2)
+                .map(|byte| byte.reverse_bits())
+                .collect();
+            block.resize(4096, 0x69);
+            blocks.extend(block);
+        }
+    }
+    let decoder = DsdDecoder::<_, Stereo64>::new(&blocks[..], DSD64, 2, 44_100)
+        .with_order(BitOrder::LsbFirst)
+        .with_layout(Layout::Blocks(4096));
+    let decoded: Vec<Stereo64> = decoder.map(Result::unwrap).collect();
+    let expected: Vec<Stereo64> =
+        DsdDecoder::<_, Stereo64>::new(&bytes[..], DSD64, 2, 44_100)
+            .map(Result::unwrap)
+            .collect();
+    assert_eq!(
+        &decoded[..expected.len() - 32],
+        &expected[..expected.len() - 32]
+    );
+}
+
+#[test]
+#[should_panic(expected = "DSD bit rate")]
+fn dsd_rate_overflow() {
+    // 8 × the output rate doesn't fit in a `u32`.
+    DsdDecoder::<_, Stereo64>::new(&[][..], DSD64, 2, u32::MAX / 4);
+}